@@ -1,27 +1,63 @@
-#import bevy_pbr::forward_io::{VertexOutput, FragmentOutput};
+#import bevy_pbr::{
+    forward_io::FragmentOutput,
+    mesh_functions,
+    view_transformations::position_world_to_clip
+}
 
-@fragment
-fn fragment(
-    in: VertexOutput,
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(5) color: vec4<f32>,
+    @location(8) tex_idx: vec3<u32>,
+    // The first of the `extra_vertex_attributes` from the config,
+    // at `rendering::FIRST_EXTRA_SHADER_LOCATION`
+    @location(10) height: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(5) color: vec4<f32>,
     @location(8) tex_idx: vec3<u32>,
-) -> FragmentOutput {
+    @location(10) height: f32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let model = mesh_functions::get_world_from_local(vertex.instance_index);
+    let world_position = mesh_functions::mesh_position_local_to_world(
+        model, vec4<f32>(vertex.position, 1.0));
+
+    out.position = position_world_to_clip(world_position.xyz);
+    out.color = vertex.color;
+    out.tex_idx = vertex.tex_idx;
+    out.height = vertex.height;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
     var out: FragmentOutput;
 
     // tex_idx will be the array produced by the texture_index_mapper
     // You can use it to send three unsigned integers to the shader
     // based on the voxel type.
-    if (tex_idx[0] == 1u) {
+    if (in.tex_idx[0] == 1u) {
         out.color = vec4<f32>(1.0, 0.1, 0.1, 1.0);
     }
-    if (tex_idx[0] == 2u) {
+    if (in.tex_idx[0] == 2u) {
         out.color = vec4<f32>(0.1, 1.0, 0.1, 1.0);
     }
-    if (tex_idx[0] == 3u) {
+    if (in.tex_idx[0] == 3u) {
         out.color = vec4<f32>(0.1, 0.1, 1.0, 1.0);
     }
 
     // Multiply by the vertex color to get amient occlusion
     out.color = out.color * in.color;
 
+    // Darken the voxels closer to the ground, using the height written by the meshing delegate
+    let shade = clamp((in.height + 1.0) / 3.0, 0.4, 1.0);
+    out.color = vec4<f32>(out.color.rgb * shade, out.color.a);
+
     return out;
 }
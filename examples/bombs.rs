@@ -166,22 +166,7 @@ fn explosion(
         let vox = voxel_world.get_voxel(impact_point - IVec3::Y);
 
         // Dig out a spherical volume centered around the impact point
-        let radius = 10;
-        for x in -radius..=radius {
-            for y in -radius..=radius {
-                for z in -radius..=radius {
-                    let pos = IVec3::new(
-                        x + impact_point.x,
-                        y + impact_point.y,
-                        z + impact_point.z,
-                    );
-
-                    if pos.distance_squared(impact_point) <= radius.pow(2) {
-                        voxel_world.set_voxel(pos, WorldVoxel::Air);
-                    }
-                }
-            }
-        }
+        voxel_world.fill_sphere(impact_point, 10, WorldVoxel::Air);
 
         // Spread some voxels out around the impact zone
         let num_voxels = 50;
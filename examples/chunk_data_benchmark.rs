@@ -0,0 +1,102 @@
+//! Measures how long it takes to read the chunk data of all loaded chunks with
+//! `VoxelWorld::get_chunk_data`, which returns a copy of it, and with
+//! `VoxelWorld::get_chunk_data_arc`, which returns the chunk data shared with the chunk map.
+//!
+//! ```sh
+//! cargo run --release --example chunk_data_benchmark --features test-util
+//! ```
+
+use std::time::{Duration, Instant};
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_voxel_world::{prelude::*, test_util::*};
+
+const CHUNKS_TO_LOAD: usize = 500;
+const ROUNDS: u32 = 100;
+
+#[derive(Resource, Clone, Default)]
+struct HillsWorld;
+
+impl VoxelWorldConfig for HillsWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        8
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_chunk_pos| {
+            Box::new(|pos: IVec3| {
+                let height =
+                    ((pos.x as f32 / 16.0).sin() + (pos.z as f32 / 16.0).cos()) * 8.0;
+                if (pos.y as f32) < height {
+                    WorldVoxel::Solid(pos.y.rem_euclid(4) as u8)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+fn main() {
+    let mut app = spawn_test_app::<HillsWorld>();
+
+    if !run_until_chunks_loaded::<HillsWorld>(&mut app, CHUNKS_TO_LOAD, 100_000) {
+        println!(
+            "Only {} chunks were generated",
+            loaded_chunk_count::<HillsWorld>(&app)
+        );
+        return;
+    }
+
+    let (chunk_count, copied, shared) = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<HillsWorld>| {
+            let chunk_positions = voxel_world
+                .chunks_by_distance(Vec3::ZERO)
+                .into_iter()
+                .map(|(chunk_pos, _)| chunk_pos)
+                .collect::<Vec<_>>();
+
+            let time_rounds = |read_chunk: &dyn Fn(IVec3) -> usize| {
+                let started = Instant::now();
+                let mut materials = 0;
+                for _ in 0..ROUNDS {
+                    for chunk_pos in chunk_positions.iter() {
+                        materials += read_chunk(*chunk_pos);
+                    }
+                }
+                // Keep the reads from being optimized away
+                std::hint::black_box(materials);
+                started.elapsed()
+            };
+
+            let copied = time_rounds(&|chunk_pos| {
+                voxel_world
+                    .get_chunk_data(chunk_pos)
+                    .map_or(0, |chunk_data| chunk_data.materials().len())
+            });
+            let shared = time_rounds(&|chunk_pos| {
+                voxel_world
+                    .get_chunk_data_arc(chunk_pos)
+                    .map_or(0, |chunk_data| chunk_data.materials().len())
+            });
+
+            (chunk_positions.len(), copied, shared)
+        })
+        .unwrap();
+
+    let reads = chunk_count as u32 * ROUNDS;
+    let per_read = |elapsed: Duration| elapsed / reads.max(1);
+    println!("Read {chunk_count} chunks {ROUNDS} times");
+    println!(
+        "get_chunk_data:     {copied:.2?} ({:.2?} per read)",
+        per_read(copied)
+    );
+    println!(
+        "get_chunk_data_arc: {shared:.2?} ({:.2?} per read)",
+        per_read(shared)
+    );
+}
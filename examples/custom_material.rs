@@ -2,17 +2,19 @@ use bevy::{
     pbr::{MaterialPipeline, MaterialPipelineKey},
     prelude::*,
     render::{
-        mesh::MeshVertexBufferLayoutRef,
+        mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef, VertexAttributeValues},
         render_resource::{
             AsBindGroup, RenderPipelineDescriptor, ShaderDefVal, ShaderRef,
-            SpecializedMeshPipelineError,
+            SpecializedMeshPipelineError, VertexFormat,
         },
     },
 };
 use bevy_voxel_world::{
+    custom_meshing::{ChunkNeighbours, VoxelArray, CHUNK_SIZE_F},
     prelude::*,
-    rendering::{vertex_layout, VOXEL_TEXTURE_SHADER_HANDLE},
+    rendering::world_vertex_layout,
 };
+use ndshape::RuntimeShape;
 use std::sync::Arc;
 
 // Declare materials as consts for convenience
@@ -20,6 +22,11 @@ const RED: u8 = 0;
 const GREEN: u8 = 1;
 const BLUE: u8 = 2;
 
+// An extra vertex attribute that our meshing delegate writes to the chunk meshes. It holds the
+// height of each vertex in world space, which the shader uses to shade the voxels.
+const ATTRIBUTE_HEIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Height", 2904163113, VertexFormat::Float32);
+
 #[derive(Resource, Clone, Default)]
 struct MyMainWorld;
 
@@ -36,6 +43,43 @@ impl VoxelWorldConfig for MyMainWorld {
             BLUE | _ => [3, 3, 3],
         })
     }
+
+    // We mesh the chunks with the default mesher, and then add our own attribute to the mesh
+    fn chunk_meshing_delegate(
+        &self,
+    ) -> ChunkMeshingDelegate<Self::MaterialIndex, Self::ChunkUserBundle> {
+        Some(Box::new(|pos: IVec3| {
+            let mut default_meshing = default_chunk_meshing_delegate(pos);
+            Box::new(
+                move |voxels: VoxelArray<Self::MaterialIndex>,
+                      shape: RuntimeShape<u32, 3>,
+                      texture_index_mapper: TextureIndexMapperFn<Self::MaterialIndex>,
+                      neighbours: Option<ChunkNeighbours<Self::MaterialIndex>>| {
+                    let (mut mesh, user_bundle, aabb) =
+                        default_meshing(voxels, shape, texture_index_mapper, neighbours);
+
+                    // Vertex positions are relative to the padded chunk, which starts one
+                    // voxel before the chunk itself
+                    let heights = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+                        Some(VertexAttributeValues::Float32x3(positions)) => positions
+                            .iter()
+                            .map(|[_, y, _]| pos.y as f32 * CHUNK_SIZE_F + y - 1.0)
+                            .collect::<Vec<_>>(),
+                        _ => Vec::new(),
+                    };
+                    mesh.insert_attribute(ATTRIBUTE_HEIGHT, heights);
+
+                    (mesh, user_bundle, aabb)
+                },
+            )
+        }))
+    }
+
+    // Extra attributes need to be declared here, so that `world_vertex_layout` can give them a
+    // shader location. The first one ends up at `rendering::FIRST_EXTRA_SHADER_LOCATION`.
+    fn extra_vertex_attributes(&self) -> Vec<MeshVertexAttribute> {
+        vec![ATTRIBUTE_HEIGHT]
+    }
 }
 
 fn main() {
@@ -94,9 +138,11 @@ struct CustomVoxelMaterial {
 }
 
 impl Material for CustomVoxelMaterial {
+    // The default vertex shader from bevy_voxel_world (`rendering::VOXEL_TEXTURE_SHADER_HANDLE`)
+    // can be used if you don't need any extra attributes. Here we need our own, to pass the
+    // height on to the fragment shader.
     fn vertex_shader() -> ShaderRef {
-        // You can use the default shader from bevy_voxel_world for the vertex shader for simplicity
-        VOXEL_TEXTURE_SHADER_HANDLE.into()
+        "custom_material.wgsl".into()
     }
 
     fn fragment_shader() -> ShaderRef {
@@ -119,8 +165,14 @@ impl Material for CustomVoxelMaterial {
             return Ok(());
         }
 
-        // Use `vertex_layout()` from `bevy_voxel_world` to get the correct vertex layout
-        let vertex_layout = layout.0.get_layout(&vertex_layout())?;
+        // Use `world_vertex_layout()` from `bevy_voxel_world` to get the correct vertex layout,
+        // including our extra attribute. Some of the attributes are optional, so only the ones
+        // present in the mesh are required.
+        let attributes = world_vertex_layout(&MyMainWorld)
+            .into_iter()
+            .filter(|attribute| layout.0.contains(attribute.id))
+            .collect::<Vec<_>>();
+        let vertex_layout = layout.0.get_layout(&attributes)?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
     }
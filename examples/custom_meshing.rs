@@ -8,9 +8,7 @@ use bevy::{
     },
     prelude::*,
     render::{
-        mesh::{Indices, VertexAttributeValues},
-        render_asset::RenderAssetUsages,
-        render_resource::{PrimitiveTopology, WgpuFeatures},
+        render_resource::WgpuFeatures,
         settings::{RenderCreation, WgpuSettings},
         RenderPlugin,
     },
@@ -18,13 +16,11 @@ use bevy::{
 };
 
 use bevy_voxel_world::{
-    custom_meshing::{PaddedChunkShape, VoxelArray, CHUNK_SIZE_U},
+    custom_meshing::{generate_chunk_mesh_greedy, ChunkNeighbours, VoxelArray},
     prelude::*,
-    rendering::ATTRIBUTE_TEX_INDEX,
 };
 
-use block_mesh::RIGHT_HANDED_Y_UP_CONFIG;
-use ndshape::ConstShape;
+use ndshape::RuntimeShape;
 use noise::{HybridMulti, NoiseFn, Perlin};
 
 #[derive(Resource, Clone, Default)]
@@ -59,8 +55,9 @@ impl VoxelWorldConfig for MainWorld {
 
     // A custom meshing delegate can be added via the config implementation
     //
-    // In this example we use the greedy meshing algorithm from the block_mesh crate
-    // instead of the default simple meshing.
+    // In this example we use the built-in greedy meshing function instead of the default
+    // simple meshing. Greedy meshing merges adjacent faces of the same material into larger
+    // quads, which results in far fewer vertices.
     //
     // The closure returned here is executed on a thread in the task pool, so it's OK to block
     // for as long as needed.
@@ -72,90 +69,33 @@ impl VoxelWorldConfig for MainWorld {
             // and move it into the closure below.
             Box::new(
                 // The array of voxels for the chunk
-                |voxels: VoxelArray<Self::MaterialIndex>,
-                 // A reference to the texture index mapper function as defined in the config
-                 texture_index_mapper: TextureIndexMapperFn<Self::MaterialIndex>| {
-                    let faces = block_mesh::RIGHT_HANDED_Y_UP_CONFIG.faces;
-                    let mut buffer = block_mesh::GreedyQuadsBuffer::new(voxels.len());
-
-                    // Call the greedy meshing algorithm from the block_mesh crate
-                    block_mesh::greedy_quads(
-                        &*voxels,
-                        &PaddedChunkShape {},
-                        [0; 3],
-                        [CHUNK_SIZE_U + 1; 3],
-                        &faces,
-                        &mut buffer,
-                    );
-
-                    let num_indices = buffer.quads.num_quads() * 6;
-                    let num_vertices = buffer.quads.num_quads() * 4;
-                    let mut indices = Vec::with_capacity(num_indices);
-                    let mut positions = Vec::with_capacity(num_vertices);
-                    let mut normals = Vec::with_capacity(num_vertices);
-                    let mut tex_coords = Vec::with_capacity(num_vertices);
-                    let mut material_types = Vec::with_capacity(num_vertices);
-
-                    for (group, face) in buffer.quads.groups.into_iter().zip(faces.into_iter()) {
-                        for quad in group.into_iter() {
-                            let normal = IVec3::from([
-                                face.signed_normal().x,
-                                face.signed_normal().y,
-                                face.signed_normal().z,
-                            ]);
-
-                            indices
-                                .extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
-                            positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
-                            normals.extend_from_slice(&face.quad_mesh_normals());
-
-                            tex_coords.extend_from_slice(&face.tex_coords(
-                                RIGHT_HANDED_Y_UP_CONFIG.u_flip_face,
-                                true,
-                                &quad.into(),
-                            ));
-
-                            let voxel_index = PaddedChunkShape::linearize(quad.minimum) as usize;
-                            let material_type = match voxels[voxel_index] {
-                                // Here we call the texture index mapper function to get the texture index
-                                // for the material type of the voxel
-                                WorldVoxel::Solid(mt) => texture_index_mapper(mt),
-                                _ => [1, 1, 1],
-                            };
-                            material_types.extend(std::iter::repeat(material_type).take(4));
-                        }
-                    }
-
-                    let mut render_mesh = Mesh::new(
-                        PrimitiveTopology::TriangleList,
-                        RenderAssetUsages::RENDER_WORLD,
-                    );
-                    render_mesh.insert_attribute(
-                        Mesh::ATTRIBUTE_POSITION,
-                        VertexAttributeValues::Float32x3(positions),
-                    );
-                    render_mesh.insert_attribute(
-                        Mesh::ATTRIBUTE_NORMAL,
-                        VertexAttributeValues::Float32x3(normals),
-                    );
-                    render_mesh.insert_attribute(
-                        Mesh::ATTRIBUTE_UV_0,
-                        VertexAttributeValues::Float32x2(vec![[0.0; 2]; num_vertices]),
-                    );
-                    render_mesh.insert_attribute(
-                        ATTRIBUTE_TEX_INDEX,
-                        VertexAttributeValues::Uint32x3(material_types),
+                move |voxels: VoxelArray<Self::MaterialIndex>,
+                      // The padded shape of the voxel array. This is one voxel larger than the
+                      // chunk size on each side, to include the neighbouring voxels.
+                      shape: RuntimeShape<u32, 3>,
+                      // A reference to the texture index mapper function as defined in the config
+                      texture_index_mapper: TextureIndexMapperFn<Self::MaterialIndex>,
+                      // The six chunks next to this one, for meshing that needs to look past the
+                      // padding. Not needed for greedy meshing.
+                      _neighbours: Option<ChunkNeighbours<Self::MaterialIndex>>| {
+                    let render_mesh = generate_chunk_mesh_greedy(
+                        voxels,
+                        pos,
+                        shape,
+                        texture_index_mapper,
+                        Arc::new(|_| 1.0),
                     );
-                    render_mesh
-                        .insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0; 4]; num_vertices]);
-                    render_mesh.insert_indices(Indices::U32(indices.clone()));
 
                     // The second value in this tuple is an optional component bundle.
                     // If you want to generate some custom data for the chunk, like a nav mesh,
                     // you can put it here in a regular Bevy component. This will then get added
                     // to the spawned Chunk entity.
                     // The type of this bundle is defined in the `ChunkUserBundle` associated type.
-                    (render_mesh, None)
+                    //
+                    // The third value is an optional `Aabb` for the chunk, which is used for
+                    // frustum culling instead of the bounds Bevy computes from the mesh. This is
+                    // useful if the chunk has geometry that extends beyond the mesh.
+                    (render_mesh, None, None)
                 },
             )
         }))
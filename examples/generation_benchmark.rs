@@ -0,0 +1,70 @@
+//! Measures how long it takes to generate the chunks around a camera with an expensive voxel
+//! lookup, without rendering anything.
+//!
+//! Run it with and without the `parallel_gen` feature to compare:
+//!
+//! ```sh
+//! cargo run --release --example generation_benchmark --features noise,test-util
+//! cargo run --release --example generation_benchmark --features noise,test-util,parallel_gen
+//! ```
+
+use std::{sync::Arc, time::Instant};
+
+use bevy::prelude::*;
+use bevy_voxel_world::{prelude::*, test_util::*};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+const CHUNKS_TO_LOAD: usize = 500;
+
+#[derive(Resource, Clone, Default)]
+struct HeavyNoiseWorld;
+
+impl VoxelWorldConfig for HeavyNoiseWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        8
+    }
+
+    fn shared_voxel_lookup_delegate(
+        &self,
+    ) -> Option<SharedVoxelLookupDelegate<Self::MaterialIndex>> {
+        // Several octaves of 3D noise for every voxel, which makes the lookup much more
+        // expensive than the meshing
+        let noise = Fbm::<Perlin>::new(1234).set_octaves(8);
+        Some(Box::new(move |_chunk_pos| {
+            let noise = noise.clone();
+            Arc::new(move |pos: IVec3| {
+                let density = noise.get((pos.as_dvec3() / 64.0).to_array());
+                if density - pos.y as f64 / 32.0 > 0.0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        }))
+    }
+}
+
+fn main() {
+    let mut app = spawn_test_app::<HeavyNoiseWorld>();
+
+    let started = Instant::now();
+    let loaded =
+        run_until_chunks_loaded::<HeavyNoiseWorld>(&mut app, CHUNKS_TO_LOAD, 100_000);
+    let elapsed = started.elapsed();
+
+    if loaded {
+        println!(
+            "Generated {CHUNKS_TO_LOAD} chunks in {elapsed:.2?} ({:.2?} per chunk), parallel_gen: {}",
+            elapsed / CHUNKS_TO_LOAD as u32,
+            cfg!(feature = "parallel_gen"),
+        );
+    } else {
+        println!(
+            "Only {} chunks were generated in {elapsed:.2?}",
+            loaded_chunk_count::<HeavyNoiseWorld>(&app)
+        );
+    }
+}
@@ -203,8 +203,13 @@ impl Material for CustomVoxelMaterial {
             return Ok(());
         }
 
-        // Use `vertex_layout()` from `bevy_voxel_world` to get the correct vertex layout
-        let vertex_layout = layout.0.get_layout(&vertex_layout())?;
+        // Use `vertex_layout()` from `bevy_voxel_world` to get the correct vertex layout. Some of
+        // the attributes are optional, so only the ones present in the mesh are required.
+        let attributes = vertex_layout()
+            .into_iter()
+            .filter(|attribute| layout.0.contains(attribute.id))
+            .collect::<Vec<_>>();
+        let vertex_layout = layout.0.get_layout(&attributes)?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
     }
@@ -1,34 +1,106 @@
-use bevy::{prelude::*, render::primitives::Aabb, tasks::Task, utils::HashSet};
-use ndshape::{ConstShape, ConstShape3u32};
+use bevy::{
+    prelude::*,
+    render::primitives::Aabb,
+    tasks::Task,
+    utils::{HashMap, HashSet},
+};
+use block_mesh::{visible_block_faces, UnitQuadBuffer, RIGHT_HANDED_Y_UP_CONFIG};
+use ndshape::{ConstShape3u32, RuntimeShape, Shape};
 use std::{
-    hash::{Hash, Hasher},
+    hash::{BuildHasher, Hash},
     marker::PhantomData,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use crate::{
-    prelude::{ChunkMeshingFn, TextureIndexMapperFn, VoxelWorldConfig},
+    coords::{chunk_pos_of_for_size, local_in_chunk_for_size},
+    prelude::{
+        ChunkMeshingFn, ModificationPrecedence, SharedVoxelLookupFn,
+        TextureIndexMapperFn, VoxelWorldConfig,
+    },
     voxel::WorldVoxel,
+    voxel_world::InvalidationReason,
     voxel_world_internal::ModifiedVoxels,
 };
 
-// The size of a chunk in voxels
-// TODO: implement a way to change this though the configuration
+// The default size of a chunk in voxels. The size can be changed per world through
+// `VoxelWorldConfig::chunk_size`.
 pub const CHUNK_SIZE_U: u32 = 32;
 pub const CHUNK_SIZE_I: i32 = CHUNK_SIZE_U as i32;
 pub const CHUNK_SIZE_F: f32 = CHUNK_SIZE_U as f32;
 
-// A chunk with 1-voxel boundary padding.
+// A chunk of the default size with 1-voxel boundary padding.
 pub(crate) const PADDED_CHUNK_SIZE: u32 = CHUNK_SIZE_U + 2;
 pub type PaddedChunkShape =
     ConstShape3u32<PADDED_CHUNK_SIZE, PADDED_CHUNK_SIZE, PADDED_CHUNK_SIZE>;
 
-pub type VoxelArray<I> = [WorldVoxel<I>; PaddedChunkShape::SIZE as usize];
+/// The voxel data of a chunk, including the 1-voxel boundary padding.
+/// Use the shape from `padded_chunk_shape_uniform` to index into it.
+pub type VoxelArray<I> = [WorldVoxel<I>];
+
+/// Returns the shape of a chunk with the given size, including the 1-voxel boundary padding.
+pub fn padded_chunk_shape_uniform(chunk_size: u32) -> RuntimeShape<u32, 3> {
+    RuntimeShape::<u32, 3>::new([chunk_size + 2; 3])
+}
+
+/// Returns the hash of a padded voxel array, which is used as the key in the mesh cache
+///
+/// Chunks with the same hash share a mesh, so two different voxel arrays with the same hash would
+/// render one of them with the other's mesh, without any error. With a 64 bit hash and many
+/// thousands of distinct chunks over a session, that is unlikely but not negligible, so the hash
+/// is made of two independent 64 bit hashes, with different fixed seeds.
+///
+/// The hash only depends on the voxel values in array order, and the seeds are fixed, so the same
+/// voxels give the same hash in every world and every run of the same build. `preload_mesh`
+/// relies on this. Don't feed anything with an unspecified iteration order, like a `HashMap` or
+/// `HashSet`, into the hasher here. The hasher is not guaranteed to be stable across builds or
+/// platforms, so the hashes should not be persisted.
+pub(crate) fn voxels_hash<I: Hash>(voxels: &VoxelArray<I>) -> u128 {
+    stable_hash(voxels)
+}
+
+/// Returns the mesh cache key of a chunk with the given voxels hash. Chunks with the same voxels
+/// can still need different meshes, for example when they have different neighbours, so
+/// anything else their mesh depends on is hashed into `mesh_variant` and folded into the key.
+///
+/// Chunks without a variant are keyed by their voxels hash alone, which `preload_mesh` relies on.
+pub(crate) fn mesh_cache_key(voxels_hash: u128, mesh_variant: Option<u128>) -> u128 {
+    match mesh_variant {
+        Some(mesh_variant) => stable_hash(&(voxels_hash, mesh_variant)),
+        None => voxels_hash,
+    }
+}
+
+/// A 128 bit hash made of two independent 64 bit hashes with fixed seeds. See `voxels_hash`.
+pub(crate) fn stable_hash<T: Hash + ?Sized>(value: &T) -> u128 {
+    let low = ahash::RandomState::with_seeds(
+        0x243f_6a88_85a3_08d3,
+        0x1319_8a2e_0370_7344,
+        0xa409_3822_299f_31d0,
+        0x082e_fa98_ec4e_6c89,
+    )
+    .hash_one(value);
+    let high = ahash::RandomState::with_seeds(
+        0x4528_21e6_38d0_1377,
+        0xbe54_66cf_34e9_0c6c,
+        0xc0ac_29b7_c97c_50dd,
+        0x3f84_d5b5_b547_0917,
+    )
+    .hash_one(value);
+    ((high as u128) << 64) | low as u128
+}
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub(crate) struct ChunkThread<C: VoxelWorldConfig, I>(
     pub Task<ChunkTask<C, I>>,
+    pub Arc<AtomicBool>,
+    /// Why the chunk is being remeshed, kept in case the chunk has to be queued again
+    pub InvalidationReason,
     PhantomData<C>,
 );
 
@@ -36,18 +108,165 @@ impl<C, I> ChunkThread<C, I>
 where
     C: VoxelWorldConfig,
 {
-    pub fn new(task: Task<ChunkTask<C, I>>, _pos: IVec3) -> Self {
-        Self(task, PhantomData)
+    pub fn new(
+        task: Task<ChunkTask<C, I>>,
+        cancelled: Arc<AtomicBool>,
+        reason: InvalidationReason,
+    ) -> Self {
+        Self(task, cancelled, reason, PhantomData)
+    }
+
+    /// Tells the task to skip any work it hasn't started yet. Used when the chunk is despawned
+    /// before the task has completed.
+    pub fn cancel(&self) {
+        self.1.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.1.load(Ordering::Relaxed)
     }
 }
 
+/// Marks a chunk to be regenerated and remeshed, along with the reason that is sent with
+/// `ChunkInvalidated`
 #[derive(Component)]
 #[component(storage = "SparseSet")]
-pub struct NeedsRemesh;
+pub struct NeedsRemesh(pub InvalidationReason);
 
 #[derive(Component)]
 pub struct NeedsDespawn;
 
+/// Voxel positions edited since the chunk was last generated. Only tracked with
+/// `ChunkRegenerateStrategy::Reuse`, to update the voxels of the chunk in place.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct ChunkVoxelEdits(pub Vec<IVec3>);
+
+/// Inserted on chunks whose generation has failed and is waiting to be retried.
+/// Removed again once the chunk has generated successfully.
+#[derive(Component, Clone, Debug)]
+pub struct ChunkGenerationRetry {
+    /// Number of failed generation attempts so far
+    pub attempts: u32,
+    /// Elapsed time at which the next attempt will be made
+    pub retry_at: Duration,
+}
+
+/// Palette compressed voxel data of a chunk. Each distinct voxel is stored once in the palette,
+/// and every voxel in the padded chunk is stored as an index into it.
+#[derive(Clone, Debug)]
+pub(crate) struct PaletteVoxels<I> {
+    palette: Vec<WorldVoxel<I>>,
+    indices: PaletteIndices,
+}
+
+/// Palette indices are stored as `u8` when the palette is small enough, which is the common case
+#[derive(Clone, Debug)]
+enum PaletteIndices {
+    U8(Box<[u8]>),
+    U16(Box<[u16]>),
+    U32(Box<[u32]>),
+}
+
+impl<I: Copy + Eq + Hash> PaletteVoxels<I> {
+    pub fn from_voxels(voxels: &VoxelArray<I>) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_lookup = HashMap::<WorldVoxel<I>, u32>::new();
+        let mut palette_index = |voxel: &WorldVoxel<I>| {
+            *palette_lookup.entry(*voxel).or_insert_with(|| {
+                palette.push(*voxel);
+                (palette.len() - 1) as u32
+            })
+        };
+
+        // The palette can't be larger than the voxel array, and the padded array of a chunk of
+        // 39 or more voxels has more than u16::MAX voxels, so the indices are collected as u32
+        let wide_indices = voxels.iter().map(&mut palette_index).collect::<Vec<_>>();
+        let indices = if palette.len() <= u8::MAX as usize + 1 {
+            PaletteIndices::U8(wide_indices.into_iter().map(|i| i as u8).collect())
+        } else if palette.len() <= u16::MAX as usize + 1 {
+            PaletteIndices::U16(wide_indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            PaletteIndices::U32(wide_indices.into_boxed_slice())
+        };
+
+        Self { palette, indices }
+    }
+
+    pub fn get(&self, index: usize) -> WorldVoxel<I> {
+        let palette_index = match &self.indices {
+            PaletteIndices::U8(indices) => indices[index] as usize,
+            PaletteIndices::U16(indices) => indices[index] as usize,
+            PaletteIndices::U32(indices) => indices[index] as usize,
+        };
+        self.palette[palette_index]
+    }
+
+    pub fn decode(&self) -> Arc<VoxelArray<I>> {
+        match &self.indices {
+            PaletteIndices::U8(indices) => {
+                indices.iter().map(|i| self.palette[*i as usize]).collect()
+            }
+            PaletteIndices::U16(indices) => {
+                indices.iter().map(|i| self.palette[*i as usize]).collect()
+            }
+            PaletteIndices::U32(indices) => {
+                indices.iter().map(|i| self.palette[*i as usize]).collect()
+            }
+        }
+    }
+}
+
+/// Voxel data of a chunk with only a few filled voxels. The voxels that differ from `background`
+/// are kept in a map, keyed by their index in the padded voxel array.
+#[derive(Clone, Debug)]
+pub(crate) struct SparseVoxels<I> {
+    pub(crate) background: WorldVoxel<I>,
+    pub(crate) voxels: HashMap<u32, WorldVoxel<I>>,
+    pub(crate) len: usize,
+}
+
+impl<I: Copy + PartialEq> SparseVoxels<I> {
+    /// Returns the sparse voxels of the array, or `None` if more than `max_voxels` voxels differ
+    /// from the background. The voxels are counted before the map is built, so dense chunks
+    /// don't pay for a map that would be thrown away.
+    pub fn from_voxels(voxels: &VoxelArray<I>, max_voxels: usize) -> Option<Self> {
+        // Air and unset voxels are the only ones that can make up most of a sparse chunk
+        let air_count = voxels.iter().filter(|voxel| voxel.is_air()).count();
+        let unset_count = voxels.iter().filter(|voxel| voxel.is_unset()).count();
+        let (background, background_count) = if air_count >= unset_count {
+            (WorldVoxel::Air, air_count)
+        } else {
+            (WorldVoxel::Unset, unset_count)
+        };
+        if voxels.len() - background_count > max_voxels {
+            return None;
+        }
+
+        Some(Self {
+            background,
+            voxels: voxels
+                .iter()
+                .enumerate()
+                .filter(|(_, voxel)| **voxel != background)
+                .map(|(index, voxel)| (index as u32, *voxel))
+                .collect(),
+            len: voxels.len(),
+        })
+    }
+
+    pub fn get(&self, index: usize) -> WorldVoxel<I> {
+        self.voxels
+            .get(&(index as u32))
+            .copied()
+            .unwrap_or(self.background)
+    }
+
+    pub fn decode(&self) -> Arc<VoxelArray<I>> {
+        (0..self.len).map(|index| self.get(index)).collect()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum FillType<I> {
     Empty,
@@ -61,56 +280,152 @@ pub enum FillType<I> {
 pub struct ChunkData<I> {
     pub(crate) position: IVec3,
     pub(crate) voxels: Option<Arc<VoxelArray<I>>>,
-    pub(crate) voxels_hash: u64,
+    /// Used in place of `voxels` once the chunk has been compressed
+    pub(crate) palette_voxels: Option<Arc<PaletteVoxels<I>>>,
+    /// Used in place of `voxels` for chunks with only a few filled voxels
+    pub(crate) sparse_voxels: Option<Arc<SparseVoxels<I>>>,
+    pub(crate) voxels_hash: u128,
+    /// Anything other than the voxels that the mesh of the chunk depends on. See `mesh_cache_key`.
+    pub(crate) mesh_variant: Option<u128>,
     pub(crate) is_full: bool,
     pub(crate) is_empty: bool,
     pub(crate) fill_type: FillType<I>,
+    /// The distinct materials of the filled voxels in the padded voxel array
+    pub(crate) materials: Vec<I>,
     pub(crate) entity: Entity,
     pub(crate) has_generated: bool,
+    pub(crate) chunk_size: u32,
 }
 
-impl<I: Hash + Copy + PartialEq> ChunkData<I> {
+impl<I: Hash + Copy + Eq> ChunkData<I> {
     pub(crate) fn new() -> Self {
         Self {
             position: IVec3::ZERO,
             voxels: None,
+            palette_voxels: None,
+            sparse_voxels: None,
             voxels_hash: 0,
+            mesh_variant: None,
             is_full: false,
             is_empty: true,
             fill_type: FillType::Empty,
+            materials: Vec::new(),
             entity: Entity::PLACEHOLDER,
             has_generated: false,
+            chunk_size: CHUNK_SIZE_U,
         }
     }
 
-    pub(crate) fn with_entity(entity: Entity) -> Self {
+    pub(crate) fn with_entity(entity: Entity, chunk_size: u32) -> Self {
         let new = Self::new();
-        Self { entity, ..new }
+        Self {
+            entity,
+            chunk_size,
+            ..new
+        }
     }
 
     pub(crate) fn generate_hash(&mut self) {
         if let Some(voxels) = &self.voxels {
-            let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            voxels.hash(&mut hasher);
-            self.voxels_hash = hasher.finish();
+            self.voxels_hash = voxels_hash(voxels);
+        }
+    }
+
+    /// Returns the key of the mesh of this chunk in the mesh cache
+    pub(crate) fn mesh_cache_key(&self) -> u128 {
+        mesh_cache_key(self.voxels_hash, self.mesh_variant)
+    }
+
+    /// Replace the voxel array with a palette compressed copy, to save memory while the chunk is
+    /// stored in the chunk map. The hash is left as is, so it still matches the mesh cache.
+    pub(crate) fn compress(&mut self) {
+        if let Some(voxels) = self.voxels.take() {
+            self.palette_voxels = Some(Arc::new(PaletteVoxels::from_voxels(&voxels)));
+        }
+    }
+
+    /// Replace the voxel array with a sparse copy, if that holds at most `max_voxels` voxels.
+    /// Returns true if the chunk was made sparse.
+    pub(crate) fn make_sparse(&mut self, max_voxels: usize) -> bool {
+        if max_voxels == 0 {
+            return false;
         }
+        let Some(sparse_voxels) = self
+            .voxels
+            .as_ref()
+            .and_then(|voxels| SparseVoxels::from_voxels(voxels, max_voxels))
+        else {
+            return false;
+        };
+
+        self.voxels = None;
+        self.sparse_voxels = Some(Arc::new(sparse_voxels));
+        true
+    }
+
+    /// Returns the padded voxel array of the chunk, decoding it if the chunk is compressed. Use
+    /// the shape from `padded_chunk_shape_uniform` with `chunk_size` to index into it.
+    ///
+    /// Decoding a compressed chunk allocates a new array on every call, which is the default with
+    /// `VoxelWorldConfig::compress_chunk_storage`. Chunks with a raw voxel array share it without
+    /// copying, like `voxels_slice`. Returns `None` for empty and uniform chunks.
+    pub fn decoded_voxels(&self) -> Option<Arc<VoxelArray<I>>> {
+        self.voxels
+            .clone()
+            .or_else(|| {
+                self.palette_voxels
+                    .as_ref()
+                    .map(|palette_voxels| palette_voxels.decode())
+            })
+            .or_else(|| {
+                self.sparse_voxels
+                    .as_ref()
+                    .map(|sparse_voxels| sparse_voxels.decode())
+            })
     }
 
     /// Get the voxel at the given position in the chunk
     /// The position is given in local chunk coordinates
+    ///
+    /// Returns `WorldVoxel::Unset` if the chunk is mixed but its voxel array is missing, which
+    /// should not happen, but is logged as a warning rather than causing a panic.
     pub fn get_voxel(&self, position: UVec3) -> WorldVoxel<I> {
-        if self.voxels.is_some() {
-            self.voxels.as_ref().unwrap()
-                [PaddedChunkShape::linearize(position.to_array()) as usize]
+        if let Some(voxels) = self.voxels.as_ref() {
+            let shape = padded_chunk_shape_uniform(self.chunk_size);
+            voxels[shape.linearize(position.to_array()) as usize]
+        } else if let Some(palette_voxels) = self.palette_voxels.as_ref() {
+            let shape = padded_chunk_shape_uniform(self.chunk_size);
+            palette_voxels.get(shape.linearize(position.to_array()) as usize)
+        } else if let Some(sparse_voxels) = self.sparse_voxels.as_ref() {
+            let shape = padded_chunk_shape_uniform(self.chunk_size);
+            sparse_voxels.get(shape.linearize(position.to_array()) as usize)
         } else {
             match self.fill_type {
                 FillType::Uniform(voxel) => voxel,
                 FillType::Empty => WorldVoxel::Unset,
-                FillType::Mixed => unreachable!(),
+                FillType::Mixed => {
+                    warn!(
+                        "Mixed chunk at {:?} has no voxel data, returning WorldVoxel::Unset",
+                        self.position
+                    );
+                    WorldVoxel::Unset
+                }
             }
         }
     }
 
+    /// Returns the padded voxel array of the chunk, for scanning the whole chunk without going
+    /// through `get_voxel` for each voxel. Use the shape from `padded_chunk_shape_uniform` with
+    /// `chunk_size` to index into it.
+    ///
+    /// The voxels are only there when the chunk keeps its raw voxel array. Returns `None` for
+    /// empty and uniform chunks, and for chunks stored compressed, which `decoded_voxels`
+    /// decodes. See `VoxelWorldConfig::compress_chunk_storage` and
+    /// `VoxelWorldConfig::sparse_chunk_threshold`.
+    pub fn voxels_slice(&self) -> Option<&[WorldVoxel<I>]> {
+        self.voxels.as_deref()
+    }
+
     /// Returns true if the chunk is full. No mesh will be generated for full chunks.
     pub fn is_full(&self) -> bool {
         self.is_full
@@ -131,20 +446,32 @@ impl<I: Hash + Copy + PartialEq> ChunkData<I> {
         &self.fill_type
     }
 
+    /// Returns the distinct materials of the solid and translucent voxels in the chunk, in no
+    /// particular order. This includes the one voxel wide border the chunk shares with its
+    /// neighbours.
+    pub fn materials(&self) -> &[I] {
+        &self.materials
+    }
+
     /// Returns the entity of the corresponding Chunk
     pub fn get_entity(&self) -> Entity {
         self.entity
     }
 
+    /// Returns the size of the chunk in voxels, excluding padding
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
     /// Returns the position of the chunk in world coordinates
     pub fn world_position(&self) -> Vec3 {
-        self.position.as_vec3() * CHUNK_SIZE_F
+        self.position.as_vec3() * self.chunk_size as f32
     }
 
     /// Returns the AABB of the chunk
     pub fn aabb(&self) -> Aabb {
         let min = Vec3::ZERO;
-        let max = min + Vec3::splat(CHUNK_SIZE_F);
+        let max = min + Vec3::splat(self.chunk_size as f32);
         Aabb::from_min_max(min, max)
     }
 
@@ -164,13 +491,12 @@ impl<I: Hash + Copy + PartialEq> ChunkData<I> {
     }
 
     /// Returns true if the given voxel is within the bounds of the chunk
-    /// and the voxel data at the given position matcheso the given voxel
+    /// and the voxel data at the given position matches the given voxel
     pub fn has_voxel(&self, voxel_pos: IVec3, voxel: WorldVoxel<I>) -> bool {
-        let chunk_pos = voxel_pos / CHUNK_SIZE_I;
-        if self.position != chunk_pos {
+        if self.position != chunk_pos_of_for_size(voxel_pos, self.chunk_size) {
             return false;
         }
-        self.get_voxel(voxel_pos.as_uvec3() % CHUNK_SIZE_U) == voxel
+        self.get_voxel(local_in_chunk_for_size(voxel_pos, self.chunk_size) + 1) == voxel
     }
 
     /// Returns true if this chunk has been processed by the voxel generation system (typically to generate terrain)
@@ -178,27 +504,111 @@ impl<I: Hash + Copy + PartialEq> ChunkData<I> {
     pub fn has_generated(&self) -> bool {
         self.has_generated
     }
+
+    /// Returns collision geometry for the chunk, as a list of deduplicated vertex positions and
+    /// triangle indices into that list. The positions are in the same local space as the chunk
+    /// mesh.
+    ///
+    /// Only the visible faces are included, same as for the render mesh, so this is empty for
+    /// chunks that are empty or full.
+    pub fn collision_vertices(&self) -> (Vec<Vec3>, Vec<[u32; 3]>) {
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+
+        if self.is_empty || self.is_full {
+            return (positions, triangles);
+        }
+
+        let Some(voxels) = self.decoded_voxels() else {
+            return (positions, triangles);
+        };
+
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let shape = padded_chunk_shape_uniform(self.chunk_size);
+        let [x, y, z] = shape.as_array();
+        let mut buffer = UnitQuadBuffer::new();
+
+        visible_block_faces(
+            &voxels[..],
+            &shape,
+            [0; 3],
+            [x - 1, y - 1, z - 1],
+            &faces,
+            &mut buffer,
+        );
+
+        let mut vertex_indices = HashMap::<IVec3, u32>::new();
+
+        for (group, face) in buffer.groups.into_iter().zip(faces) {
+            for quad in group.into_iter() {
+                let quad_indices =
+                    face.quad_mesh_positions(&quad.into(), 1.0).map(|position| {
+                        let position = Vec3::from(position);
+                        *vertex_indices
+                            .entry(position.round().as_ivec3())
+                            .or_insert_with(|| {
+                                positions.push(position);
+                                positions.len() as u32 - 1
+                            })
+                    });
+
+                // Remap the quad indices from block_mesh to the deduplicated positions
+                let mesh_indices = face.quad_mesh_indices(0);
+                for triangle in mesh_indices.chunks_exact(3) {
+                    triangles.push([
+                        quad_indices[triangle[0] as usize],
+                        quad_indices[triangle[1] as usize],
+                        quad_indices[triangle[2] as usize],
+                    ]);
+                }
+            }
+        }
+
+        (positions, triangles)
+    }
 }
 
-impl<I: Hash + Copy + PartialEq> Default for ChunkData<I> {
+impl<I: Hash + Copy + Eq> Default for ChunkData<I> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Collision geometry for a chunk, inserted on the chunk entity when
+/// `VoxelWorldConfig::generate_collision_data` is enabled. Can be used to build a trimesh
+/// collider for a physics engine. See `ChunkData::collision_vertices`.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ChunkCollisionData {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl ChunkCollisionData {
+    pub fn from_chunk_data<I: Hash + Copy + Eq>(chunk_data: &ChunkData<I>) -> Self {
+        let (positions, indices) = chunk_data.collision_vertices();
+        Self { positions, indices }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
 /// A marker component for chunks, with some helpful data
 #[derive(Component, Clone)]
 pub struct Chunk<C> {
     pub position: IVec3,
     pub entity: Entity,
+    chunk_size: u32,
     _marker: PhantomData<C>,
 }
 
 impl<C> Chunk<C> {
-    pub fn new(position: IVec3, entity: Entity) -> Self {
+    pub fn new(position: IVec3, entity: Entity, chunk_size: u32) -> Self {
         Self {
             position,
             entity,
+            chunk_size,
             _marker: PhantomData,
         }
     }
@@ -207,17 +617,126 @@ impl<C> Chunk<C> {
         Self {
             position: chunk.position,
             entity: chunk.entity,
+            chunk_size: chunk.chunk_size,
             _marker: PhantomData,
         }
     }
 
+    /// Returns the size of the chunk in voxels, excluding padding
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
     pub fn aabb(&self) -> Aabb {
         let min = Vec3::ZERO;
-        let max = min + Vec3::splat(CHUNK_SIZE_F);
+        let max = min + Vec3::splat(self.chunk_size as f32);
         Aabb::from_min_max(min, max)
     }
 }
 
+/// The six chunks that share a face with a chunk, as they were in the chunk map when the chunk
+/// was queued for meshing. Passed to custom meshing delegates, so that they can look at voxels
+/// beyond the one voxel padding of the chunk.
+#[derive(Clone, Debug)]
+pub struct ChunkNeighbours<I> {
+    chunks: [Option<Arc<ChunkData<I>>>; 6],
+}
+
+impl<I> ChunkNeighbours<I> {
+    /// The directions of the neighbours, in the order they are stored in
+    pub const DIRECTIONS: [IVec3; 6] = [
+        IVec3::NEG_X,
+        IVec3::X,
+        IVec3::NEG_Y,
+        IVec3::Y,
+        IVec3::NEG_Z,
+        IVec3::Z,
+    ];
+
+    pub(crate) fn new(chunks: [Option<Arc<ChunkData<I>>>; 6]) -> Self {
+        Self { chunks }
+    }
+
+    /// Get the neighbour in the given direction, which should be one of `DIRECTIONS`. Returns
+    /// `None` if that chunk is not loaded, or for any other direction.
+    pub fn get(&self, direction: IVec3) -> Option<&ChunkData<I>> {
+        let index = Self::DIRECTIONS.iter().position(|d| *d == direction)?;
+        self.chunks[index].as_deref()
+    }
+
+    /// Iterate over the loaded neighbours, together with their direction
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &ChunkData<I>)> {
+        Self::DIRECTIONS
+            .iter()
+            .zip(self.chunks.iter())
+            .filter_map(|(direction, chunk)| Some((*direction, chunk.as_deref()?)))
+    }
+}
+
+/// Number of voxels that each rayon job works out in `ChunkTask::generate_shared`
+#[cfg(feature = "parallel_gen")]
+const GENERATION_SHARD_SIZE: usize = 4096;
+
+/// The settings of a chunk task that decide the value of each voxel when generating, split off
+/// from the task so that they can be shared between threads
+#[derive(Clone, Copy)]
+struct VoxelGenerator<I> {
+    padded_min: IVec3,
+    bedrock: Option<(i32, I)>,
+    default_fill: Option<(i32, I)>,
+    modification_precedence: ModificationPrecedence,
+}
+
+impl<I: Copy + Eq + Hash> VoxelGenerator<I> {
+    /// Work out the voxel at `index` in the padded voxel array of the chunk
+    fn voxel(
+        &self,
+        index: u32,
+        shape: &RuntimeShape<u32, 3>,
+        modified_voxels: &HashMap<IVec3, WorldVoxel<I>>,
+        voxel_data_fn: impl FnOnce(IVec3) -> WorldVoxel<I>,
+    ) -> WorldVoxel<I> {
+        let block_pos =
+            self.padded_min + IVec3::from(shape.delinearize(index).map(|v| v as i32));
+
+        let bedrock_voxel = self
+            .bedrock
+            .filter(|(bedrock_y, _)| block_pos.y <= *bedrock_y)
+            .map(|(_, material)| WorldVoxel::Solid(material));
+
+        let voxel = match self.modification_precedence {
+            ModificationPrecedence::Override => bedrock_voxel
+                .or_else(|| modified_voxels.get(&block_pos).copied())
+                .unwrap_or_else(|| voxel_data_fn(block_pos)),
+            // Modified voxels are only used where the lookup leaves the voxel unset
+            ModificationPrecedence::Fallback => {
+                let voxel = bedrock_voxel.unwrap_or_else(|| voxel_data_fn(block_pos));
+                if voxel.is_unset() {
+                    modified_voxels
+                        .get(&block_pos)
+                        .copied()
+                        .unwrap_or(WorldVoxel::Unset)
+                } else {
+                    voxel
+                }
+            }
+        };
+
+        // Voxels left unset by both the lookup and the modifications fall back to the
+        // default fill, when there is one
+        match self.default_fill {
+            Some((surface_y, material)) if voxel.is_unset() => {
+                if block_pos.y < surface_y {
+                    WorldVoxel::Solid(material)
+                } else {
+                    WorldVoxel::Air
+                }
+            }
+            _ => voxel,
+        }
+    }
+}
+
 /// Holds all data needed to generate and mesh a chunk
 #[derive(Component)]
 pub(crate) struct ChunkTask<C, I>
@@ -229,6 +748,13 @@ where
     pub modified_voxels: ModifiedVoxels<C, I>,
     pub mesh: Option<Mesh>,
     pub user_bundle: Option<C::ChunkUserBundle>,
+    pub aabb: Option<Aabb>,
+    pub collision_data: Option<ChunkCollisionData>,
+    pub generation_failed: bool,
+    bedrock: Option<(i32, I)>,
+    default_fill: Option<(i32, I)>,
+    modification_precedence: ModificationPrecedence,
+    neighbours: Option<ChunkNeighbours<I>>,
     _marker: PhantomData<C>,
 }
 
@@ -236,73 +762,219 @@ impl<C: VoxelWorldConfig + Send + Sync + 'static, I: Hash + Copy + Eq> ChunkTask
     pub fn new(
         entity: Entity,
         position: IVec3,
+        chunk_size: u32,
         modified_voxels: ModifiedVoxels<C, I>,
     ) -> Self {
         Self {
             position,
-            chunk_data: ChunkData::with_entity(entity),
+            chunk_data: ChunkData::with_entity(entity, chunk_size),
             modified_voxels,
             mesh: None,
             user_bundle: None,
+            aabb: None,
+            collision_data: None,
+            generation_failed: false,
+            bedrock: None,
+            default_fill: None,
+            modification_precedence: ModificationPrecedence::default(),
+            neighbours: None,
             _marker: PhantomData,
         }
     }
 
+    /// Make voxels at or below the given height solid with the given material when generating,
+    /// ignoring both the voxel lookup and modified voxels there.
+    pub fn with_bedrock(mut self, bedrock: Option<(i32, I)>) -> Self {
+        self.bedrock = bedrock;
+        self
+    }
+
+    /// When generating, make the voxels that the voxel lookup leaves unset solid with the given
+    /// material below the given height, and air at or above it.
+    pub fn with_default_fill(mut self, default_fill: Option<(i32, I)>) -> Self {
+        self.default_fill = default_fill;
+        self
+    }
+
+    /// Set how modified voxels combine with the voxel lookup when generating.
+    pub fn with_modification_precedence(
+        mut self,
+        modification_precedence: ModificationPrecedence,
+    ) -> Self {
+        self.modification_precedence = modification_precedence;
+        self
+    }
+
+    /// Set the neighbouring chunks that get passed to the meshing function.
+    pub fn with_neighbours(mut self, neighbours: Option<ChunkNeighbours<I>>) -> Self {
+        self.neighbours = neighbours;
+        self
+    }
+
+    /// Set what else than the voxels the mesh of the chunk depends on, so that it gets its own
+    /// entry in the mesh cache. See `mesh_cache_key`.
+    pub fn with_mesh_variant(mut self, mesh_variant: Option<u128>) -> Self {
+        self.chunk_data.mesh_variant = mesh_variant;
+        self
+    }
+
     /// Generate voxel data for the chunk. The supplied `modified_voxels` map is first checked,
     /// and where no voxeles are modified, the `voxel_data_fn` is called to get data from the
-    /// consumer.
+    /// consumer. With `ModificationPrecedence::Fallback`, `voxel_data_fn` is called first
+    /// instead, and the modified voxels are only used where it returns `WorldVoxel::Unset`.
     pub fn generate<F>(&mut self, mut voxel_data_fn: F)
     where
         F: FnMut(IVec3) -> WorldVoxel<I> + Send + 'static,
     {
-        let mut filled_count = 0;
         let modified_voxels = (*self.modified_voxels).read().unwrap();
-        let mut voxels = [WorldVoxel::Unset; PaddedChunkShape::SIZE as usize];
-        let mut material_count = HashSet::new();
+        let shape = padded_chunk_shape_uniform(self.chunk_data.chunk_size);
+        let generator = self.voxel_generator();
+
+        let voxels = (0..shape.size())
+            .map(|i| generator.voxel(i, &shape, &modified_voxels, &mut voxel_data_fn))
+            .collect::<Vec<_>>();
+        drop(modified_voxels);
 
         self.chunk_data.has_generated = true;
+        self.set_generated_voxels(voxels);
+    }
+
+    /// Same as `generate`, but with a voxel lookup that can be called from several threads at
+    /// once. With the `parallel_gen` feature, the voxels are generated in parallel on the rayon
+    /// thread pool, in shards of `GENERATION_SHARD_SIZE` voxels.
+    pub fn generate_shared(&mut self, voxel_data_fn: SharedVoxelLookupFn<I>)
+    where
+        I: Send + Sync,
+    {
+        let modified_voxels = (*self.modified_voxels).read().unwrap();
+        let shape = padded_chunk_shape_uniform(self.chunk_data.chunk_size);
+        let generator = self.voxel_generator();
+        let mut voxels = vec![WorldVoxel::Unset; shape.size() as usize];
+
+        #[cfg(feature = "parallel_gen")]
+        {
+            use rayon::prelude::*;
+            voxels
+                .par_chunks_mut(GENERATION_SHARD_SIZE)
+                .enumerate()
+                .for_each(|(shard, shard_voxels)| {
+                    let first = shard * GENERATION_SHARD_SIZE;
+                    for (offset, voxel) in shard_voxels.iter_mut().enumerate() {
+                        *voxel = generator.voxel(
+                            (first + offset) as u32,
+                            &shape,
+                            &modified_voxels,
+                            voxel_data_fn.as_ref(),
+                        );
+                    }
+                });
+        }
+        #[cfg(not(feature = "parallel_gen"))]
+        for (i, voxel) in voxels.iter_mut().enumerate() {
+            *voxel = generator.voxel(
+                i as u32,
+                &shape,
+                &modified_voxels,
+                voxel_data_fn.as_ref(),
+            );
+        }
+        drop(modified_voxels);
 
-        for i in 0..PaddedChunkShape::SIZE {
-            let chunk_block = PaddedChunkShape::delinearize(i);
+        self.chunk_data.has_generated = true;
+        self.set_generated_voxels(voxels);
+    }
 
-            let block_pos = IVec3 {
-                x: chunk_block[0] as i32 + (self.position.x * CHUNK_SIZE_I) - 1,
-                y: chunk_block[1] as i32 + (self.position.y * CHUNK_SIZE_I) - 1,
-                z: chunk_block[2] as i32 + (self.position.z * CHUNK_SIZE_I) - 1,
-            };
+    fn voxel_generator(&self) -> VoxelGenerator<I> {
+        VoxelGenerator {
+            padded_min: self.position * self.chunk_data.chunk_size as i32 - 1,
+            bedrock: self.bedrock,
+            default_fill: self.default_fill,
+            modification_precedence: self.modification_precedence,
+        }
+    }
 
-            if let Some(voxel) = modified_voxels.get(&block_pos) {
-                voxels[i as usize] = *voxel;
-                if !voxel.is_unset() && !voxel.is_air() {
-                    filled_count += 1;
-                }
+    /// Update the voxel data of a chunk that has been generated before, by writing the voxels at
+    /// the `changed` positions from the modified voxels into a copy of `previous_voxels`. Unlike
+    /// `generate`, this doesn't call the voxel lookup at all.
+    pub fn regenerate_changed(
+        &mut self,
+        previous_voxels: &VoxelArray<I>,
+        changed: &[IVec3],
+    ) {
+        let modified_voxels = (*self.modified_voxels).read().unwrap();
+        let chunk_size = self.chunk_data.chunk_size as i32;
+        let shape = padded_chunk_shape_uniform(self.chunk_data.chunk_size);
+        let mut voxels = previous_voxels.to_vec();
+
+        self.chunk_data.has_generated = true;
+
+        // Positions are relative to the padded chunk, so that edits in the padding are included
+        let padded_min = self.position * chunk_size - 1;
+        for position in changed {
+            let local = *position - padded_min;
+            if local.cmplt(IVec3::ZERO).any()
+                || local.cmpgt(IVec3::splat(chunk_size + 1)).any()
+            {
                 continue;
             }
+            if let Some(voxel) = modified_voxels.get(position) {
+                voxels[shape.linearize(local.as_uvec3().to_array()) as usize] = *voxel;
+            }
+        }
+        drop(modified_voxels);
 
-            let voxel = voxel_data_fn(block_pos);
-
-            voxels[i as usize] = voxel;
+        self.set_generated_voxels(voxels);
+    }
 
-            if let WorldVoxel::Solid(m) = voxel {
-                filled_count += 1;
-                material_count.insert(m);
+    /// Count the filled voxels and their materials, and store the voxels with `set_voxels`
+    fn set_generated_voxels(&mut self, voxels: Vec<WorldVoxel<I>>) {
+        let mut filled_count = 0;
+        let mut translucent_count = 0;
+        let mut materials = HashSet::new();
+        for voxel in voxels.iter() {
+            match voxel {
+                WorldVoxel::Solid(m) => {
+                    filled_count += 1;
+                    materials.insert(*m);
+                }
+                WorldVoxel::Translucent(m) => {
+                    filled_count += 1;
+                    translucent_count += 1;
+                    materials.insert(*m);
+                }
+                _ => {}
             }
         }
 
+        self.set_voxels(voxels, filled_count, translucent_count, materials);
+    }
+
+    /// Store the voxel array along with its fill type, and update the hash
+    fn set_voxels(
+        &mut self,
+        voxels: Vec<WorldVoxel<I>>,
+        filled_count: u32,
+        translucent_count: u32,
+        materials: HashSet<I>,
+    ) {
+        let shape = padded_chunk_shape_uniform(self.chunk_data.chunk_size);
+
         self.chunk_data.is_empty = filled_count == 0;
-        self.chunk_data.is_full = filled_count == PaddedChunkShape::SIZE;
+        // Faces behind translucent voxels can be seen, so only opaque chunks count as full
+        self.chunk_data.is_full = filled_count == shape.size() && translucent_count == 0;
 
-        if self.chunk_data.is_full && material_count.len() == 1 {
+        if self.chunk_data.is_full && materials.len() == 1 {
             self.chunk_data.fill_type = FillType::Uniform(voxels[0]);
             self.chunk_data.voxels = None;
         } else if filled_count > 0 {
             self.chunk_data.fill_type = FillType::Mixed;
-            self.chunk_data.voxels = Some(Arc::new(voxels));
+            self.chunk_data.voxels = Some(Arc::from(voxels));
         } else {
             self.chunk_data.fill_type = FillType::Empty;
             self.chunk_data.voxels = None;
         };
 
+        self.chunk_data.materials = materials.into_iter().collect();
         self.chunk_data.generate_hash();
     }
 
@@ -315,13 +987,21 @@ impl<C: VoxelWorldConfig + Send + Sync + 'static, I: Hash + Copy + Eq> ChunkTask
         if self.mesh.is_none() && self.chunk_data.voxels.is_some() {
             let mesh_and_bundle = chunk_meshing_fn(
                 self.chunk_data.voxels.as_ref().unwrap().clone(),
+                padded_chunk_shape_uniform(self.chunk_data.chunk_size),
                 texture_index_mapper,
+                self.neighbours.clone(),
             );
             self.mesh = Some(mesh_and_bundle.0);
             self.user_bundle = mesh_and_bundle.1;
+            self.aabb = mesh_and_bundle.2;
         }
     }
 
+    /// Generate collision geometry for the chunk based on the current voxel data
+    pub fn generate_collision_data(&mut self) {
+        self.collision_data = Some(ChunkCollisionData::from_chunk_data(&self.chunk_data));
+    }
+
     pub fn is_empty(&self) -> bool {
         self.chunk_data.is_empty
     }
@@ -330,7 +1010,7 @@ impl<C: VoxelWorldConfig + Send + Sync + 'static, I: Hash + Copy + Eq> ChunkTask
         self.chunk_data.is_full
     }
 
-    pub fn voxels_hash(&self) -> u64 {
-        self.chunk_data.voxels_hash
+    pub fn mesh_cache_key(&self) -> u128 {
+        self.chunk_data.mesh_cache_key()
     }
 }
@@ -4,8 +4,7 @@ use std::{
 };
 
 use crate::{
-    chunk::{self, ChunkData, CHUNK_SIZE_F},
-    voxel::VOXEL_SIZE,
+    chunk::{self, ChunkData},
     voxel_world::ChunkWillSpawn,
 };
 use bevy::{
@@ -14,10 +13,11 @@ use bevy::{
     utils::hashbrown::HashMap,
 };
 
+/// The chunk data is kept behind an `Arc`, so that it can be handed out without copying it
 #[derive(Deref, DerefMut)]
 pub struct ChunkMapData<I> {
     #[deref]
-    data: HashMap<IVec3, chunk::ChunkData<I>>,
+    data: HashMap<IVec3, Arc<chunk::ChunkData<I>>>,
     bounds: Aabb3d,
 }
 
@@ -35,12 +35,23 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
         position: &IVec3,
         read_lock: &RwLockReadGuard<ChunkMapData<I>>,
     ) -> Option<chunk::ChunkData<I>> {
+        read_lock
+            .data
+            .get(position)
+            .map(|chunk_data| chunk::ChunkData::clone(chunk_data))
+    }
+
+    /// Same as `get`, but returns the shared chunk data instead of a copy of it
+    pub fn get_arc(
+        position: &IVec3,
+        read_lock: &RwLockReadGuard<'_, ChunkMapData<I>>,
+    ) -> Option<Arc<chunk::ChunkData<I>>> {
         read_lock.data.get(position).cloned()
     }
 
     pub fn contains_chunk(
         position: &IVec3,
-        read_lock: &RwLockReadGuard<ChunkMapData<I>>,
+        read_lock: &RwLockReadGuard<'_, ChunkMapData<I>>,
     ) -> bool {
         read_lock.data.contains_key(position)
     }
@@ -48,21 +59,26 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
     /// Get the current bounding box of loaded chunks in this map.
     ///
     /// Expressed in **chunk coordinates**. Bounds are **inclusive**.
-    pub fn get_bounds(read_lock: &RwLockReadGuard<ChunkMapData<I>>) -> Aabb3d {
+    pub fn get_bounds(read_lock: &RwLockReadGuard<'_, ChunkMapData<I>>) -> Aabb3d {
         read_lock.bounds
     }
 
     /// Get the current bounding box of loaded chunks in this map.
     ///
     /// Expressed in **world units**. Bounds are **inclusive**.
-    pub fn get_world_bounds(read_lock: &RwLockReadGuard<ChunkMapData<I>>) -> Aabb3d {
+    pub fn get_world_bounds(
+        read_lock: &RwLockReadGuard<'_, ChunkMapData<I>>,
+        chunk_size: u32,
+        voxel_size: f32,
+    ) -> Aabb3d {
+        let chunk_size = chunk_size as f32;
         let mut world_bounds = ChunkMap::<C, I>::get_bounds(read_lock);
-        world_bounds.min *= CHUNK_SIZE_F * VOXEL_SIZE;
-        world_bounds.max = (world_bounds.max + Vec3A::ONE) * CHUNK_SIZE_F * VOXEL_SIZE;
+        world_bounds.min *= chunk_size * voxel_size;
+        world_bounds.max = (world_bounds.max + Vec3A::ONE) * chunk_size * voxel_size;
         world_bounds
     }
 
-    pub fn get_read_lock(&self) -> RwLockReadGuard<ChunkMapData<I>> {
+    pub fn get_read_lock(&self) -> RwLockReadGuard<'_, ChunkMapData<I>> {
         self.map.read().unwrap()
     }
 
@@ -75,7 +91,7 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
         insert_buffer: &mut ChunkMapInsertBuffer<C, I>,
         update_buffer: &mut ChunkMapUpdateBuffer<C, I>,
         remove_buffer: &mut ChunkMapRemoveBuffer<C>,
-        ev_chunk_will_spawn: &mut EventWriter<ChunkWillSpawn<C>>,
+        mut ev_chunk_will_spawn: Option<&mut EventWriter<ChunkWillSpawn<C>>>,
     ) {
         if insert_buffer.is_empty()
             && update_buffer.is_empty()
@@ -88,10 +104,10 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
             for (position, chunk_data) in insert_buffer.iter() {
                 write_lock.data.insert(
                     *position,
-                    ChunkData {
+                    Arc::new(ChunkData {
                         position: *position,
                         ..chunk_data.clone()
-                    },
+                    }),
                 );
 
                 let position_f = Vec3A::from(position.as_vec3());
@@ -106,10 +122,10 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
             for (position, chunk_data, evt) in update_buffer.iter() {
                 write_lock.data.insert(
                     *position,
-                    ChunkData {
+                    Arc::new(ChunkData {
                         position: *position,
                         ..chunk_data.clone()
-                    },
+                    }),
                 );
 
                 let position_f = Vec3A::from(position.as_vec3());
@@ -119,7 +135,9 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
                     write_lock.bounds.max = position_f.max(write_lock.bounds.max);
                 }
 
-                ev_chunk_will_spawn.send((*evt).clone());
+                if let Some(ev_chunk_will_spawn) = ev_chunk_will_spawn.as_mut() {
+                    ev_chunk_will_spawn.send((*evt).clone());
+                }
             }
             update_buffer.clear();
 
@@ -127,21 +145,41 @@ impl<C: Send + Sync + 'static, I: Copy> ChunkMap<C, I> {
             for position in remove_buffer.iter() {
                 write_lock.data.remove(position);
 
-                need_rebuild_aabb = write_lock.bounds.min.floor().as_ivec3() == *position
+                need_rebuild_aabb |= write_lock.bounds.min.floor().as_ivec3()
+                    == *position
                     || write_lock.bounds.max.floor().as_ivec3() == *position;
             }
             remove_buffer.clear();
 
             if need_rebuild_aabb {
-                let mut tmp_vec = Vec::with_capacity(write_lock.data.len());
-                for v in write_lock.data.keys() {
-                    tmp_vec.push(Vec3A::from(v.as_vec3()));
-                }
-                write_lock.bounds =
-                    Aabb3d::from_point_cloud(Isometry3d::IDENTITY, tmp_vec.drain(0..));
+                write_lock.recompute_bounds();
             }
         }
     }
+
+    /// Rebuild the bounding box of loaded chunks from the chunks currently in the map.
+    ///
+    /// The bounds are only rebuilt automatically when a chunk at one of the corners of the
+    /// bounding box is removed, so they can end up larger than needed after many removals.
+    pub fn recompute_bounds(&self) {
+        self.map.write().unwrap().recompute_bounds();
+    }
+}
+
+impl<I> ChunkMapData<I> {
+    fn recompute_bounds(&mut self) {
+        if self.data.is_empty() {
+            self.bounds = Aabb3d::new(Vec3::ZERO, Vec3::ZERO);
+            return;
+        }
+
+        self.bounds = Aabb3d::from_point_cloud(
+            Isometry3d::IDENTITY,
+            self.data
+                .keys()
+                .map(|position| Vec3A::from(position.as_vec3())),
+        );
+    }
 }
 
 impl<C, I> Default for ChunkMap<C, I> {
@@ -1,19 +1,42 @@
 use std::hash::Hash;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::chunk::VoxelArray;
-use crate::meshing::generate_chunk_mesh;
-use crate::voxel::WorldVoxel;
-use bevy::prelude::*;
+use crate::chunk::{ChunkNeighbours, VoxelArray, CHUNK_SIZE_U};
+use crate::meshing::{
+    generate_chunk_mesh_with_options, generate_minimal_chunk_mesh, ChunkMeshOptions,
+};
+use crate::voxel::{WorldVoxel, VOXEL_SIZE};
+use bevy::{
+    prelude::*,
+    render::{mesh::MeshVertexAttribute, primitives::Aabb},
+};
+use ndshape::RuntimeShape;
 
 pub type VoxelLookupFn<I = u8> = Box<dyn FnMut(IVec3) -> WorldVoxel<I> + Send + Sync>;
 pub type VoxelLookupDelegate<I = u8> =
     Box<dyn Fn(IVec3) -> VoxelLookupFn<I> + Send + Sync>;
 
+/// A voxel lookup that can be called from several threads at once, for
+/// `VoxelWorldConfig::shared_voxel_lookup_delegate`
+pub type SharedVoxelLookupFn<I = u8> = Arc<dyn Fn(IVec3) -> WorldVoxel<I> + Send + Sync>;
+pub type SharedVoxelLookupDelegate<I = u8> =
+    Box<dyn Fn(IVec3) -> SharedVoxelLookupFn<I> + Send + Sync>;
+
 pub type TextureIndexMapperFn<I = u8> = Arc<dyn Fn(I) -> [u32; 3] + Send + Sync>;
 
+pub type MaterialAlphaFn<I = u8> = Arc<dyn Fn(I) -> f32 + Send + Sync>;
+
+pub type VoxelLightFn<I = u8> = Arc<dyn Fn(IVec3, &WorldVoxel<I>) -> u8 + Send + Sync>;
+
 pub type ChunkMeshingFn<I, UB> = Box<
-    dyn FnMut(Arc<VoxelArray<I>>, TextureIndexMapperFn<I>) -> (Mesh, Option<UB>)
+    dyn FnMut(
+            Arc<VoxelArray<I>>,
+            RuntimeShape<u32, 3>,
+            TextureIndexMapperFn<I>,
+            Option<ChunkNeighbours<I>>,
+        ) -> (Mesh, Option<UB>, Option<Aabb>)
         + Send
         + Sync,
 >;
@@ -29,6 +52,12 @@ pub enum ChunkDespawnStrategy {
 
     /// Only despawn chunks that are further than `spawning_distance` away from the camera.
     FarAway,
+
+    /// Keep up to `keep` chunks spawned, and only despawn the chunks that have been out of range
+    /// or out of view for the longest time when there are more. This stops chunks from being
+    /// despawned and spawned again over and over when the camera moves back and forth across
+    /// the spawning distance, at the cost of keeping more chunks around.
+    LeastRecentlySeen { keep: usize },
 }
 
 #[derive(Default, PartialEq, Eq)]
@@ -40,9 +69,150 @@ pub enum ChunkSpawnStrategy {
 
     /// Spawn chunks that are within `spawning_distance` of the camera, regardless of whether
     /// they are in the viewport or not. Will only have an effect if the despawn strategy is
-    /// `FarAway`. If this strategy is used a flood fill will be used to find unspawned chunks
-    /// and therefore it might make sense to lower the `spawning_rays` option.
+    /// `FarAway` or `LeastRecentlySeen`. If this strategy is used a flood fill will be used to
+    /// find unspawned chunks and therefore it might make sense to lower the `spawning_rays`
+    /// option.
     Close,
+
+    /// Spawn chunks that are within `spawning_distance` of the camera and intersect the view
+    /// frustum of the camera. Unlike `CloseAndInView`, which finds chunks by casting rays through
+    /// random points in the viewport, this visits every chunk in the frustum, so the same chunks
+    /// are found every frame without relying on `spawning_rays`. Chunks closest to the camera
+    /// are spawned first when `max_spawn_per_frame` is reached.
+    Frustum,
+}
+
+/// How the points in the viewport that spawning rays are cast through get picked, with
+/// `ChunkSpawnStrategy::CloseAndInView` and `ChunkSpawnStrategy::Close`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawningRayPattern {
+    /// Cast each ray through a random point in the viewport. Parts of the viewport can go
+    /// without rays for a few frames, so chunks at the edges of the screen may spawn late.
+    #[default]
+    Random,
+
+    /// Split the viewport into a grid of about `spawning_rays` cells, with the number of rows
+    /// and columns following the aspect ratio of the viewport, and cast one ray through a random
+    /// point in each cell. Every part of the viewport is covered every frame, so there is less
+    /// pop-in when the camera moves quickly.
+    JitteredGrid,
+}
+
+/// How chunks that have already been generated get their voxel data updated after an edit.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkRegenerateStrategy {
+    /// Generate all voxels of the chunk again, calling the voxel lookup delegate for every voxel
+    /// that hasn't been modified.
+    #[default]
+    Repopulate,
+
+    /// Start from the voxels the chunk was last generated with, and only apply the voxels that
+    /// were edited since. This skips the voxel lookup delegate entirely, which is a lot faster for
+    /// frequent small edits, but changes to what the delegate returns are not picked up until the
+    /// chunk is spawned again.
+    Reuse,
+}
+
+/// How voxels modified with `set_voxel` combine with the voxels from the voxel lookup delegate
+/// when a chunk is generated.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModificationPrecedence {
+    /// Modified voxels replace the generated ones, and the voxel lookup delegate is not called for
+    /// them at all.
+    #[default]
+    Override,
+
+    /// The voxel lookup delegate is called for every voxel, and modified voxels are only used
+    /// where it returns `WorldVoxel::Unset`. This lets the delegate keep control over some voxels,
+    /// like structures placed by a decoration pass, while edits fill in the rest.
+    ///
+    /// `ChunkRegenerateStrategy::Reuse` needs the delegate to be skipped, so with this precedence
+    /// chunks are always repopulated. `VoxelWorld::get_voxel` still returns modified voxels as
+    /// they were set, while the chunk data holds the voxel that won.
+    Fallback,
+}
+
+/// Which neighbours of a spawned chunk are queued for spawning by the flood fill used with
+/// `ChunkSpawnStrategy::Close`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloodFillConnectivity {
+    /// All 26 neighbours, including the ones that only share an edge or a corner.
+    #[default]
+    Full,
+
+    /// Only the 6 neighbours that share a face. This spawns fewer chunks in worlds like caves or
+    /// tunnels, where loading shouldn't spread through diagonal connections.
+    Faces,
+}
+
+impl FloodFillConnectivity {
+    /// Returns the offsets from a chunk position to the neighbours that get queued
+    pub fn neighbour_offsets(&self) -> Vec<IVec3> {
+        match self {
+            FloodFillConnectivity::Full => (-1..=1)
+                .flat_map(|x| {
+                    (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z)))
+                })
+                .filter(|offset| *offset != IVec3::ZERO)
+                .collect(),
+            FloodFillConnectivity::Faces => vec![
+                IVec3::NEG_X,
+                IVec3::X,
+                IVec3::NEG_Y,
+                IVec3::Y,
+                IVec3::NEG_Z,
+                IVec3::Z,
+            ],
+        }
+    }
+}
+
+/// Where the origin of a chunk's `Transform` is placed.
+///
+/// The voxel arrays passed to the mesher are padded with one voxel on each side, so the default
+/// mesher emits vertex positions where the voxel at world position `p` spans from
+/// `p - chunk_min + 1` to `p - chunk_min + 2` in the local space of the chunk, where `chunk_min` is
+/// `chunk_position * chunk_size`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkOriginConvention {
+    /// The chunk transform is placed one voxel below the min corner of the chunk, at
+    /// `chunk_min - 1`, to cancel out the padding. With the default mesher, the voxel at world
+    /// position `p` spans from `p` to `p + 1` in world space.
+    #[default]
+    PaddedMinCorner,
+
+    /// The chunk transform is placed at the min corner of the chunk, at `chunk_min`, like in
+    /// most other voxel crates. Use this with a custom meshing delegate that emits vertex
+    /// positions relative to the first voxel after the padding. With the default mesher,
+    /// voxels are rendered offset by one voxel on each axis.
+    MinCorner,
+}
+
+impl ChunkOriginConvention {
+    /// Returns the translation of the transform for the chunk at the given chunk position
+    pub fn chunk_translation(&self, chunk_position: IVec3, chunk_size: u32) -> Vec3 {
+        let chunk_min = chunk_position.as_vec3() * chunk_size as f32;
+        match self {
+            ChunkOriginConvention::PaddedMinCorner => chunk_min - 1.0,
+            ChunkOriginConvention::MinCorner => chunk_min,
+        }
+    }
+}
+
+/// Which vertex attributes the default mesher writes to chunk meshes.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkMeshAttributes {
+    /// Positions, normals, UVs, texture indexes and vertex colors, as expected by the default
+    /// material.
+    #[default]
+    Full,
+
+    /// Only positions and normals. Skips computing UVs, texture indexes, ambient occlusion and
+    /// alpha, for worlds that are rendered with a custom material that doesn't need them.
+    PositionsAndNormals,
+
+    /// Only positions, for example for depth-only or shadow-only geometry.
+    PositionsOnly,
 }
 
 /// `bevy_voxel_world` configuation structs need to implement this trait
@@ -56,22 +226,92 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
     /// If you are not using this feature, you can set this to `()`.
     type ChunkUserBundle: Bundle + Clone;
 
+    /// The size of a chunk in voxels, along each axis.
+    ///
+    /// Smaller chunks are faster to remesh when edited, larger chunks mean fewer entities and
+    /// draw calls. Note that `spawning_distance` is measured in chunks, so it should usually be
+    /// adjusted along with this.
+    fn chunk_size(&self) -> u32 {
+        CHUNK_SIZE_U
+    }
+
+    /// The size of a voxel in world units. Chunk transforms are scaled by this, and raycasts
+    /// return positions in world units, while voxel positions such as in `set_voxel` and
+    /// `get_voxel` are still given on the voxel grid.
+    fn voxel_size(&self) -> f32 {
+        VOXEL_SIZE
+    }
+
+    /// Where the origin of the chunk transforms is placed. This only affects where chunk meshes
+    /// are rendered, not how voxel positions map to chunks. See `ChunkOriginConvention`.
+    fn chunk_origin_convention(&self) -> ChunkOriginConvention {
+        ChunkOriginConvention::default()
+    }
+
     /// Distance in chunks to spawn chunks around the camera
     fn spawning_distance(&self) -> u32 {
         10
     }
 
+    /// When greater than zero, chunks are also spawned where the camera is heading, as far as it
+    /// would move in this many seconds at its current velocity, up to `spawning_distance`. This
+    /// keeps fast moving cameras from outrunning the chunk spawning.
+    fn spawn_lookahead_seconds(&self) -> f32 {
+        0.0
+    }
+
+    /// Limits the world to the chunks between the two given chunk positions, both inclusive.
+    /// Chunks outside are never spawned, and `VoxelWorld::get_voxel` returns `WorldVoxel::Unset`
+    /// for voxels outside. Useful for arena style games with a fixed size world.
+    fn world_chunk_bounds(&self) -> Option<(IVec3, IVec3)> {
+        None
+    }
+
+    /// The chunk `y` positions that chunks are spawned at. Chunks above or below are never
+    /// spawned or generated, and spawned chunks that end up outside are despawned. Unlike
+    /// `world_chunk_bounds`, this doesn't affect voxel lookups or writes, so it is a cheap way
+    /// to skip the empty chunks above and below the terrain of a world.
+    fn spawn_y_range(&self) -> RangeInclusive<i32> {
+        i32::MIN..=i32::MAX
+    }
+
+    /// When false, the `ChunkWillSpawn`, `ChunkWillRemesh`, `ChunkWillDespawn`,
+    /// `ChunkWillUpdate`, `ChunkInvalidated` and `ChunkMeshReady` events are not sent for this
+    /// world, and the changed voxels per chunk aren't collected for `ChunkWillUpdate`. Turning
+    /// them off saves some work every frame in worlds where nothing reads them, like headless
+    /// servers with a large spawning distance.
+    fn emit_chunk_events(&self) -> bool {
+        true
+    }
+
     /// Strategy for despawning chunks
     fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
         ChunkDespawnStrategy::default()
     }
 
-    /// Strategy for spawning chunks
-    /// This is only used if the despawn strategy is `FarAway`
+    /// Strategy for spawning chunks. `ChunkSpawnStrategy::Close` only has an effect if the
+    /// despawn strategy is `FarAway` or `LeastRecentlySeen`, since chunks outside of the
+    /// viewport are despawned again otherwise.
     fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
         ChunkSpawnStrategy::default()
     }
 
+    /// Which neighbours are queued by the flood fill when the spawn strategy is `Close`
+    fn flood_fill_connectivity(&self) -> FloodFillConnectivity {
+        FloodFillConnectivity::default()
+    }
+
+    /// How the voxel data of edited chunks is updated before they are remeshed
+    fn chunk_regenerate_strategy(&self) -> ChunkRegenerateStrategy {
+        ChunkRegenerateStrategy::default()
+    }
+
+    /// How modified voxels combine with the voxel lookup delegate when generating chunks.
+    /// See `ModificationPrecedence`.
+    fn modification_precedence(&self) -> ModificationPrecedence {
+        ModificationPrecedence::default()
+    }
+
     /// Maximum number of chunks that can get queued for spawning in a given frame.
     /// In some scenarios, reducing this number can help with performance, due to less
     /// thread contention.
@@ -79,6 +319,21 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
         10000
     }
 
+    /// Maximum number of chunk meshes that get inserted in a given frame. Lowering this spreads
+    /// the work over several frames when many chunks finish at once, for example after a
+    /// teleport, with the chunks closest to the camera getting their meshes first.
+    fn max_meshes_per_frame(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Maximum number of chunk generation and meshing tasks that get started in a given frame.
+    /// Lowering this keeps the task pool from being flooded when many chunks need remeshing at
+    /// once, for example after a teleport. The chunks closest to the camera are started first,
+    /// and the rest wait for later frames.
+    fn max_remesh_tasks_per_frame(&self) -> usize {
+        usize::MAX
+    }
+
     /// Number of rays to cast when spawning chunks. Higher values will result in more
     /// chunks being spawned per frame, but will also increase cpu load, and can lead to
     /// thread contention.
@@ -92,11 +347,67 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
         25
     }
 
+    /// How the points that spawning rays are cast through get spread over the viewport.
+    /// See `SpawningRayPattern`. Cameras with an orthographic projection always use
+    /// `SpawningRayPattern::JitteredGrid`, over the area that the projection covers.
+    fn spawning_ray_pattern(&self) -> SpawningRayPattern {
+        SpawningRayPattern::default()
+    }
+
+    /// Seed for the random points in the viewport that spawning rays are cast through. With a
+    /// seed set, chunks are spawned in the same order every run, given the same camera
+    /// movement, which is useful for reproducible tests. Uses a random seed by default.
+    fn spawn_seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// Maximum number of times generation of a chunk is retried after it has failed. A chunk
+    /// generation fails if the voxel lookup function panics. When all retries are used up, a
+    /// `ChunkGenerationFailed` event is sent and the chunk is left ungenerated.
+    fn max_generation_retries(&self) -> u32 {
+        3
+    }
+
+    /// Time to wait before retrying a failed chunk generation. The backoff is doubled after each
+    /// failed attempt.
+    fn generation_retry_backoff(&self) -> Duration {
+        Duration::from_millis(250)
+    }
+
+    /// Generate collision geometry for chunks. When enabled, a `ChunkCollisionData` component
+    /// is inserted on each chunk entity with visible faces, which can be used to attach a
+    /// physics collider. The geometry is computed on the meshing thread.
+    fn generate_collision_data(&self) -> bool {
+        false
+    }
+
+    /// Store the voxel data of loaded chunks palette compressed, which uses a lot less memory for
+    /// chunks made up of only a few different voxels. Reading voxels from a compressed chunk is
+    /// slightly slower, so this can be turned off to keep the raw voxel arrays instead.
+    fn compress_chunk_storage(&self) -> bool {
+        true
+    }
+
+    /// Chunks where at most this many voxels differ from the surrounding air are stored as a
+    /// sparse map of those voxels, instead of a full voxel array. This saves a lot of memory
+    /// in mostly empty worlds, such as floating islands. Set to 0 to turn it off, which is the
+    /// default.
+    fn sparse_chunk_threshold(&self) -> usize {
+        0
+    }
+
     /// Debugging aids
     fn debug_draw_chunks(&self) -> bool {
         false
     }
 
+    /// Insert a `ChunkMeshedAt` component on chunks when they get a mesh, which the
+    /// `VoxelWorldDebugDrawPlugin` uses to outline recently meshed chunks in a color that fades
+    /// over a few seconds. This shows how chunks stream in around the camera over time.
+    fn debug_chunk_age_tint(&self) -> bool {
+        false
+    }
+
     /// A function that maps voxel materials to texture coordinates.
     /// The input is the material index, and the output is a slice of three indexes into an array texture.
     /// The three values correspond to the top, sides and bottom of the voxel. For example,
@@ -106,6 +417,88 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
         Arc::new(|_mat| [0, 0, 0])
     }
 
+    /// The alpha value of the given material, between 0.0 and 1.0. This is written to the alpha
+    /// channel of the vertex colors by the default mesher, which the default material multiplies
+    /// with the texture color.
+    ///
+    /// Values below 1.0 make the default material blend, see `material_alpha_mode`.
+    fn material_alpha(&self, _material: Self::MaterialIndex) -> f32 {
+        1.0
+    }
+
+    /// The alpha mode of the default material. When `None`, the material starts out opaque and
+    /// switches to `AlphaMode::Blend` as soon as a chunk is meshed with a material that
+    /// `material_alpha` makes translucent. The set of material indices a world uses isn't known
+    /// up front, so this is worked out from the chunk meshes. Return an alpha mode to use it from
+    /// the start instead, for example `AlphaMode::Mask` for cutout materials.
+    fn material_alpha_mode(&self) -> Option<AlphaMode> {
+        None
+    }
+
+    /// The vertex color brightness for each ambient occlusion level, from the most occluded
+    /// corner of a face to an unoccluded one. The default material multiplies the voxel textures
+    /// by the vertex colors, so this sets how dark the corners between voxels get.
+    fn ao_curve(&self) -> [f32; 4] {
+        [0.1, 0.3, 0.5, 1.0]
+    }
+
+    /// When false, the vertex colors of chunk meshes are not darkened by ambient occlusion, and
+    /// `ao_curve` is not used. The meshes keep the color attribute either way.
+    fn ao_enabled(&self) -> bool {
+        true
+    }
+
+    /// When true, the default material projects the voxel textures onto the chunk meshes along
+    /// the world axes, blended by the surface normal, instead of using the mesh UVs. This keeps
+    /// textures from stretching on merged quads from greedy meshing and on smooth meshes, which
+    /// have no clean quad UVs. Textures repeat once per voxel, and the texture layer is still
+    /// picked from `texture_index_mapper`, by whether a surface faces up, down or sideways.
+    fn triplanar_texturing(&self) -> bool {
+        false
+    }
+
+    /// When set, chunk meshes get a `UV_1` attribute with the `UV_0` coordinates multiplied by
+    /// this factor. Custom materials can use it to tile detail or overlay textures, such as grime
+    /// or moss, at a different scale than the voxel textures. Off by default, since it makes the
+    /// meshes larger.
+    fn detail_uv_tiling(&self) -> Option<f32> {
+        None
+    }
+
+    /// When set, chunk meshes get an `ATTRIBUTE_SURFACE_DISTANCE` attribute with the signed
+    /// distance to the nearest surface around each vertex, for custom shaders. Distances are
+    /// measured in voxels within the padded chunk, and capped at this value. Computing them
+    /// happens on the meshing thread, but adds to the time it takes to mesh a chunk.
+    fn surface_distance_range(&self) -> Option<u32> {
+        None
+    }
+
+    /// When true, `voxel_light` is sampled for the voxels in front of each face during meshing,
+    /// and written to chunk meshes as `ATTRIBUTE_VOXEL_LIGHT`. The default material darkens the
+    /// voxel textures by it, so baked block light or skylight can be fed in per voxel.
+    ///
+    /// The light differs from place to place, so chunks with baked light never share a cached
+    /// mesh with other chunks.
+    fn bake_voxel_light(&self) -> bool {
+        false
+    }
+
+    /// The light level of the voxel at the given world position, where 0 is dark and 255 is fully
+    /// lit. Only used when `bake_voxel_light` is true. Faces are lit by the voxels in front of
+    /// them, which are usually air, and the light is smoothed across each vertex.
+    ///
+    /// This is called from the meshing thread, and needs to be thread-safe.
+    fn voxel_light(&self, _pos: IVec3, _voxel: &WorldVoxel<Self::MaterialIndex>) -> u8 {
+        u8::MAX
+    }
+
+    /// Which vertex attributes the default mesher writes. Anything other than
+    /// `ChunkMeshAttributes::Full` is meant for use with a custom material, since the default
+    /// material shader reads the UVs, texture indexes and vertex colors.
+    fn chunk_mesh_attributes(&self) -> ChunkMeshAttributes {
+        ChunkMeshAttributes::default()
+    }
+
     /// A function that returns a function that returns true if a voxel exists at the given position
     ///
     /// The delegate will be called every time a new chunk needs to be computed. The delegate should
@@ -115,25 +508,116 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
         Box::new(|_| Box::new(|_| WorldVoxel::Unset))
     }
 
+    /// Like `voxel_lookup_delegate`, but the returned function takes `&self` and can be called
+    /// from several threads at once, so it can't keep a cache between calls the way a
+    /// `VoxelLookupFn` can. When set, it is used instead of `voxel_lookup_delegate`.
+    ///
+    /// With the `parallel_gen` feature, the voxels of each chunk are then generated in parallel
+    /// on the rayon thread pool, which helps when the lookup is expensive, like sampling several
+    /// layers of 3D noise per voxel. Without the feature, this behaves like
+    /// `voxel_lookup_delegate`.
+    fn shared_voxel_lookup_delegate(
+        &self,
+    ) -> Option<SharedVoxelLookupDelegate<Self::MaterialIndex>> {
+        None
+    }
+
+    /// Voxels at or below this height are always solid, using `bedrock_material`, regardless of
+    /// what the voxel lookup delegate returns. Edits to these voxels through `set_voxel` or
+    /// `import_modified` are ignored, so the world gets an indestructible floor.
+    fn bedrock_y(&self) -> Option<i32> {
+        None
+    }
+
+    /// The material of the voxels at or below `bedrock_y`
+    fn bedrock_material(&self) -> Self::MaterialIndex {
+        Self::MaterialIndex::default()
+    }
+
+    /// A baseline for the voxels that the voxel lookup delegate and the modified voxels leave
+    /// unset, as a height and a material. Unset voxels below the height are generated solid
+    /// with the material, and unset voxels at or above it are generated as air.
+    ///
+    /// With the default voxel lookup delegate, which leaves every voxel unset, this gives a flat
+    /// world with its surface at the given height, without writing a delegate.
+    fn default_solid_below(&self) -> Option<(i32, Self::MaterialIndex)> {
+        None
+    }
+
+    /// When true, the entities of despawned chunks are not despawned, but stripped of all their
+    /// components and kept in a pool, to be reused for the next chunks that spawn. This saves
+    /// allocating entities when the camera moves back and forth, but it means that the entity of
+    /// a despawned chunk stays alive, and can come back as a different chunk.
+    fn pool_chunk_entities(&self) -> bool {
+        false
+    }
+
+    /// Called for every spawned chunk when the configuration resource is changed at runtime,
+    /// with the configuration from before the change. Chunks for which this returns true are
+    /// regenerated and remeshed, so a change that only affects part of the world, such as a
+    /// seed for one region, doesn't have to regenerate everything.
+    ///
+    /// Meshes are cached by voxel data, so chunks whose voxels stay the same keep their mesh.
+    /// By default, all chunks are regenerated.
+    fn config_change_affects_chunk(&self, _chunk_pos: IVec3, _old: &Self) -> bool {
+        true
+    }
+
     /// A function that returns a function that computes the mesh for a chunk
     ///
     /// The delegate will be called every time a new chunk needs to be computed. The delegate should
     /// return a function that returns a Mesh. This function needs to be thread-safe, since chunk computation
     /// happens on a separate thread.
     ///
-    /// The input to the function is the voxel array for the chunk, the position of the chunk and the texture
-    /// index mapper function
+    /// The input to the function is the voxel array for the chunk, the padded shape of the voxel array,
+    /// the texture index mapper function and the six neighbouring chunks. The position of the chunk is
+    /// passed to the delegate. The neighbours are `None` unless `chunk_meshing_needs_neighbours` is
+    /// enabled.
     fn chunk_meshing_delegate(
         &self,
     ) -> ChunkMeshingDelegate<Self::MaterialIndex, Self::ChunkUserBundle> {
         None
     }
 
+    /// When true, the `chunk_meshing_delegate` gets the six neighbouring chunks. They are taken
+    /// from the chunk map when the chunk is queued for meshing, and chunks that aren't loaded are
+    /// left out. The chunk is meshed again when one of its neighbours has generated for the first
+    /// time, and a chunk only reuses the cached mesh of another chunk when both have the same
+    /// neighbours. Leave this off for delegates that ignore the neighbours.
+    fn chunk_meshing_needs_neighbours(&self) -> bool {
+        false
+    }
+
+    /// Extra vertex attributes that the meshing delegate writes to chunk meshes, for custom
+    /// materials to read. `rendering::world_vertex_layout` adds them to `vertex_layout()`, at
+    /// shader locations from `rendering::FIRST_EXTRA_SHADER_LOCATION` onwards, in this order.
+    ///
+    /// The default material ignores attributes it doesn't know about.
+    fn extra_vertex_attributes(&self) -> Vec<MeshVertexAttribute> {
+        Vec::new()
+    }
+
     /// A tuple of the path to the texture and the number of indexes in the texture. `None` if no texture is used.
     fn voxel_texture(&self) -> Option<(String, u32)> {
         None
     }
 
+    /// A tuple of the path to a normal map texture and the number of indexes in it, laid out
+    /// like `voxel_texture`, with a layer for each layer of the voxel texture. The default
+    /// material uses it to perturb the surface normals along the mesh UVs, so it is not applied
+    /// when `triplanar_texturing` is on. `None` keeps the surfaces flat.
+    fn voxel_normal_texture(&self) -> Option<(String, u32)> {
+        None
+    }
+
+    /// A tuple of the path to an occlusion, roughness and metallic texture and the number of
+    /// indexes in it, laid out like `voxel_texture`. Occlusion is read from the red channel,
+    /// roughness from green and metallic from blue. `None` uses the roughness and metallic of
+    /// the default material, with no extra occlusion.
+    fn voxel_orm_texture(&self) -> Option<(String, u32)> {
+        None
+    }
+
     /// Custom material will not get initialized if this returns false. When this is false,
     /// `VoxelWorldMaterialHandle` needs to be manually added with a reference to the material handle.
     ///
@@ -146,14 +630,49 @@ pub trait VoxelWorldConfig: Resource + Default + Clone {
     fn init_root(&self, mut _commands: Commands, _root: Entity) {}
 }
 
-pub fn default_chunk_meshing_delegate<I: PartialEq + Copy, UB: Bundle>(
+pub fn default_chunk_meshing_delegate<I: PartialEq + Copy + 'static, UB: Bundle>(
+    _pos: IVec3,
+) -> ChunkMeshingFn<I, UB> {
+    default_chunk_meshing_delegate_with_options(ChunkMeshOptions::default())
+}
+
+/// Same as `default_chunk_meshing_delegate`, but with the material alpha and ambient occlusion
+/// taken from `options`. See `custom_meshing::ChunkMeshOptions`.
+pub fn default_chunk_meshing_delegate_with_options<
+    I: PartialEq + Copy + 'static,
+    UB: Bundle,
+>(
+    options: ChunkMeshOptions<I>,
+) -> ChunkMeshingFn<I, UB> {
+    Box::new(
+        move |voxels: Arc<VoxelArray<I>>,
+              shape: RuntimeShape<u32, 3>,
+              texture_index_mapper: TextureIndexMapperFn<I>,
+              _neighbours: Option<ChunkNeighbours<I>>| {
+            let mesh = generate_chunk_mesh_with_options(
+                voxels,
+                shape,
+                texture_index_mapper,
+                &options,
+            );
+            (mesh, None, None)
+        },
+    )
+}
+
+/// A meshing delegate that only writes positions, and normals if `with_normals` is true, to the
+/// chunk meshes. See `VoxelWorldConfig::chunk_mesh_attributes`.
+pub fn minimal_chunk_meshing_delegate<I: PartialEq + Copy + 'static, UB: Bundle>(
     pos: IVec3,
+    with_normals: bool,
 ) -> ChunkMeshingFn<I, UB> {
     Box::new(
         move |voxels: Arc<VoxelArray<I>>,
-              texture_index_mapper: TextureIndexMapperFn<I>| {
-            let mesh = generate_chunk_mesh(voxels, pos, texture_index_mapper);
-            (mesh, None)
+              shape: RuntimeShape<u32, 3>,
+              _texture_index_mapper: TextureIndexMapperFn<I>,
+              _neighbours: Option<ChunkNeighbours<I>>| {
+            let mesh = generate_minimal_chunk_mesh(voxels, pos, shape, with_normals);
+            (mesh, None, None)
         },
     )
 }
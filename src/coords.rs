@@ -0,0 +1,61 @@
+///
+/// Coordinate conversions
+/// Helpers for going between world voxel positions, chunk positions and positions within a
+/// chunk. The functions without a suffix assume the default chunk size, and the `_for_size`
+/// variants take the `chunk_size` of the world.
+///
+use bevy::prelude::*;
+use ndshape::Shape;
+
+use crate::chunk::{padded_chunk_shape_uniform, CHUNK_SIZE_U};
+
+/// The position of the chunk that contains the voxel at `world`
+#[inline]
+pub fn chunk_pos_of(world: IVec3) -> IVec3 {
+    chunk_pos_of_for_size(world, CHUNK_SIZE_U)
+}
+
+/// Same as `chunk_pos_of`, for chunks of the given size
+#[inline]
+pub fn chunk_pos_of_for_size(world: IVec3, chunk_size: u32) -> IVec3 {
+    world.div_euclid(IVec3::splat(chunk_size as i32))
+}
+
+/// The position of the voxel at `world` within its chunk, from 0 to the chunk size minus one
+/// along each axis. This doesn't include the padding of the chunk voxel data, see
+/// `padded_index`.
+#[inline]
+pub fn local_in_chunk(world: IVec3) -> UVec3 {
+    local_in_chunk_for_size(world, CHUNK_SIZE_U)
+}
+
+/// Same as `local_in_chunk`, for chunks of the given size
+#[inline]
+pub fn local_in_chunk_for_size(world: IVec3, chunk_size: u32) -> UVec3 {
+    world.rem_euclid(IVec3::splat(chunk_size as i32)).as_uvec3()
+}
+
+/// The world position of the voxel at the min corner of the chunk at `chunk`
+#[inline]
+pub fn world_of_chunk(chunk: IVec3) -> IVec3 {
+    world_of_chunk_for_size(chunk, CHUNK_SIZE_U)
+}
+
+/// Same as `world_of_chunk`, for chunks of the given size
+#[inline]
+pub fn world_of_chunk_for_size(chunk: IVec3, chunk_size: u32) -> IVec3 {
+    chunk * chunk_size as i32
+}
+
+/// The index of the voxel at `local`, as returned by `local_in_chunk`, in the padded voxel data
+/// of a chunk
+#[inline]
+pub fn padded_index(local: UVec3) -> usize {
+    padded_index_for_size(local, CHUNK_SIZE_U)
+}
+
+/// Same as `padded_index`, for chunks of the given size
+#[inline]
+pub fn padded_index_for_size(local: UVec3, chunk_size: u32) -> usize {
+    padded_chunk_shape_uniform(chunk_size).linearize((local + 1).to_array()) as usize
+}
@@ -1,12 +1,11 @@
 use bevy::{ecs::system::SystemParam, prelude::*};
-use std::sync::{Arc, RwLock};
-
-use crate::{
-    chunk::{Chunk, CHUNK_SIZE_F},
-    configuration::VoxelWorldConfig,
-    prelude::VoxelWorld,
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
+use crate::{chunk::Chunk, configuration::VoxelWorldConfig, prelude::VoxelWorld};
+
 #[derive(Default)]
 pub struct VoxelWorldDebugDrawPlugin<C: VoxelWorldConfig> {
     _marker: std::marker::PhantomData<C>,
@@ -16,7 +15,14 @@ impl<C: VoxelWorldConfig> Plugin for VoxelWorldDebugDrawPlugin<C> {
     fn build(&self, app: &mut App) {
         app.init_gizmo_group::<ChunkGizmos>()
             .add_systems(Startup, setup::<C>)
-            .add_systems(Update, (draw_voxel_gizmos::<C>, draw_ray_gizmos::<C>));
+            .add_systems(
+                Update,
+                (
+                    draw_voxel_gizmos::<C>,
+                    draw_ray_gizmos::<C>,
+                    draw_chunk_age_tint::<C>,
+                ),
+            );
     }
 }
 
@@ -176,7 +182,8 @@ pub fn debug_draw_chunks<C: VoxelWorldConfig>(
     voxel_world: VoxelWorld<C>,
 ) {
     for (chunk, transform) in chunks.iter() {
-        let size = Vec3::ONE * CHUNK_SIZE_F;
+        let chunk_size = chunk.chunk_size() as f32;
+        let size = Vec3::ONE * chunk_size;
         let color = Srgba::new(0.0, 1.0, 0.0, 1.0);
 
         let Some(chunk_data) = voxel_world.get_chunk_data(chunk.position) else {
@@ -190,8 +197,50 @@ pub fn debug_draw_chunks<C: VoxelWorldConfig>(
         gizmos.cuboid(
             Transform::from(*transform)
                 .with_scale(size)
-                .with_translation(transform.translation() + (CHUNK_SIZE_F / 2.0) + 1.0),
+                .with_translation(transform.translation() + (chunk_size / 2.0) + 1.0),
             color,
         );
     }
 }
+
+/// How long, in seconds, recently meshed chunks stay outlined by the debug draw plugin
+pub const CHUNK_AGE_TINT_SECONDS: f32 = 3.0;
+
+/// Elapsed time at which the chunk last got a mesh. Only inserted when
+/// `VoxelWorldConfig::debug_chunk_age_tint` is enabled.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChunkMeshedAt(pub Duration);
+
+/// Outlines recently meshed chunks, fading from magenta to gray as they age
+fn draw_chunk_age_tint<C: VoxelWorldConfig>(
+    mut gizmos: Gizmos<ChunkGizmos>,
+    chunks: Query<(&Chunk<C>, &GlobalTransform, &ChunkMeshedAt)>,
+    time: Res<Time>,
+) {
+    for (chunk, transform, meshed_at) in chunks.iter() {
+        let age = time.elapsed().saturating_sub(meshed_at.0).as_secs_f32();
+        if age > CHUNK_AGE_TINT_SECONDS {
+            continue;
+        }
+
+        let t = age / CHUNK_AGE_TINT_SECONDS;
+        let color = Srgba::new(1.0 - 0.5 * t, 0.5 * t, 1.0 - 0.5 * t, 1.0);
+        let chunk_size = chunk.chunk_size() as f32;
+        gizmos.cuboid(chunk_outline_transform(transform, chunk_size), color);
+    }
+}
+
+/// The transform of a unit cuboid that outlines a chunk with the given chunk transform. The
+/// chunk transform carries the voxel size as its scale, so the outline is sized and placed in
+/// voxel units and then transformed along with the chunk.
+pub(crate) fn chunk_outline_transform(
+    transform: &GlobalTransform,
+    chunk_size: f32,
+) -> Transform {
+    let chunk_transform = transform.compute_transform();
+    Transform {
+        translation: transform.transform_point(Vec3::splat(chunk_size / 2.0 + 1.0)),
+        scale: chunk_transform.scale * chunk_size,
+        ..chunk_transform
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::configuration::VoxelWorldConfig;
+
+/// Counters of the work done by a voxel world, to see whether it keeps up with the camera and
+/// how well the mesh cache works for the terrain.
+///
+/// The counters are totals since the world was set up. Every frame, the amounts for that frame
+/// are also reported to Bevy's `DiagnosticsStore`, under the paths returned by
+/// `diagnostic_path` for each of the measurement names on this type, so they show up in
+/// diagnostics overlays and `LogDiagnosticsPlugin`.
+#[derive(Resource)]
+pub struct VoxelWorldDiagnostics<C> {
+    counters: Arc<DiagnosticCounters>,
+    _marker: PhantomData<C>,
+}
+
+impl<C> Default for VoxelWorldDiagnostics<C> {
+    fn default() -> Self {
+        Self {
+            counters: Arc::new(DiagnosticCounters::default()),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C: VoxelWorldConfig> VoxelWorldDiagnostics<C> {
+    /// Chunks spawned in the frame
+    pub const CHUNKS_SPAWNED: &'static str = "chunks_spawned";
+
+    /// Chunks despawned in the frame
+    pub const CHUNKS_DESPAWNED: &'static str = "chunks_despawned";
+
+    /// Chunks that started generating and meshing in the frame
+    pub const CHUNKS_REMESHED: &'static str = "chunks_remeshed";
+
+    /// Percentage of the chunks finished in the frame that found their mesh in the mesh cache
+    pub const MESH_CACHE_HIT_RATE: &'static str = "mesh_cache_hit_rate";
+
+    /// Average time, in milliseconds, that the chunks finished in the frame spent generating
+    /// and meshing
+    pub const CHUNK_TASK_TIME: &'static str = "chunk_task_time";
+
+    /// The path of one of the measurements of this world in the `DiagnosticsStore`. The path
+    /// includes the full name of the config type, with `::` replaced by `.`, so that each world
+    /// has its own diagnostics, including worlds that use the same generic config type with
+    /// different parameters.
+    pub fn diagnostic_path(measurement: &str) -> DiagnosticPath {
+        let world = std::any::type_name::<C>()
+            .replace("::", ".")
+            .replace([' ', '/'], "");
+        DiagnosticPath::new(format!("voxel_world/{world}/{measurement}"))
+    }
+
+    pub fn chunks_spawned(&self) -> u64 {
+        self.counters.chunks_spawned.load(Ordering::Relaxed)
+    }
+
+    pub fn chunks_despawned(&self) -> u64 {
+        self.counters.chunks_despawned.load(Ordering::Relaxed)
+    }
+
+    /// Number of generate and mesh tasks that have been started for chunks
+    pub fn chunks_remeshed(&self) -> u64 {
+        self.counters.chunks_remeshed.load(Ordering::Relaxed)
+    }
+
+    /// Number of finished chunks that got their mesh from the mesh cache, because a mesh for
+    /// the same voxels was already in it
+    pub fn mesh_cache_hits(&self) -> u64 {
+        self.counters.mesh_cache_hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of finished chunks that got a newly built mesh
+    pub fn mesh_cache_misses(&self) -> u64 {
+        self.counters.mesh_cache_misses.load(Ordering::Relaxed)
+    }
+
+    /// The share of chunks that found their mesh in the mesh cache, from 0 to 1. Empty and full
+    /// chunks have no mesh and are not counted. `None` before any chunk has been meshed.
+    pub fn mesh_cache_hit_rate(&self) -> Option<f32> {
+        hit_rate(self.mesh_cache_hits(), self.mesh_cache_misses())
+    }
+
+    /// Rolling average of the time that chunk tasks spend generating and meshing a chunk
+    pub fn average_task_time(&self) -> Duration {
+        Duration::from_nanos(self.counters.average_task_nanos.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn count_spawned(&self) {
+        self.counters.chunks_spawned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_despawned(&self) {
+        self.counters
+            .chunks_despawned
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_remeshed(&self) {
+        self.counters
+            .chunks_remeshed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a finished chunk that got its mesh from the mesh cache if `hit` is true, or a newly
+    /// built mesh otherwise
+    pub(crate) fn count_mesh_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.counters.mesh_cache_hits
+        } else {
+            &self.counters.mesh_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Shared counters that chunk tasks record their timings to
+    pub(crate) fn counters(&self) -> Arc<DiagnosticCounters> {
+        self.counters.clone()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DiagnosticCounters {
+    chunks_spawned: AtomicU64,
+    chunks_despawned: AtomicU64,
+    chunks_remeshed: AtomicU64,
+    mesh_cache_hits: AtomicU64,
+    mesh_cache_misses: AtomicU64,
+    tasks_finished: AtomicU64,
+    task_nanos: AtomicU64,
+    average_task_nanos: AtomicU64,
+}
+
+impl DiagnosticCounters {
+    /// Record the time a finished chunk task took. Whether the chunk found its mesh in the mesh
+    /// cache is counted when the mesh is inserted, since the cache can change in between.
+    pub(crate) fn record_task(&self, time: Duration) {
+        self.tasks_finished.fetch_add(1, Ordering::Relaxed);
+        self.task_nanos
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> Option<f32> {
+    (hits + misses > 0).then(|| hits as f32 / (hits + misses) as f32)
+}
+
+/// The counter totals at the end of the previous frame
+#[derive(Default)]
+pub(crate) struct PreviousTotals {
+    chunks_spawned: u64,
+    chunks_despawned: u64,
+    chunks_remeshed: u64,
+    mesh_cache_hits: u64,
+    mesh_cache_misses: u64,
+    tasks_finished: u64,
+    task_nanos: u64,
+}
+
+/// How much each frame's average task time moves the rolling average
+const TASK_TIME_SMOOTHING: f64 = 0.1;
+
+pub(crate) fn register_diagnostics<C: VoxelWorldConfig>(app: &mut App) {
+    type D<C> = VoxelWorldDiagnostics<C>;
+
+    for measurement in [
+        D::<C>::CHUNKS_SPAWNED,
+        D::<C>::CHUNKS_DESPAWNED,
+        D::<C>::CHUNKS_REMESHED,
+    ] {
+        app.register_diagnostic(Diagnostic::new(D::<C>::diagnostic_path(measurement)));
+    }
+    app.register_diagnostic(
+        Diagnostic::new(D::<C>::diagnostic_path(D::<C>::MESH_CACHE_HIT_RATE))
+            .with_suffix("%"),
+    );
+    app.register_diagnostic(
+        Diagnostic::new(D::<C>::diagnostic_path(D::<C>::CHUNK_TASK_TIME))
+            .with_suffix("ms"),
+    );
+}
+
+/// Reports the counts of the last frame to the `DiagnosticsStore`, and updates the rolling
+/// average task time
+pub(crate) fn publish_diagnostics<C: VoxelWorldConfig>(
+    voxel_world_diagnostics: Res<VoxelWorldDiagnostics<C>>,
+    mut diagnostics: Diagnostics,
+    mut previous: Local<PreviousTotals>,
+) {
+    type D<C> = VoxelWorldDiagnostics<C>;
+    let counters = &voxel_world_diagnostics.counters;
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+    let totals = PreviousTotals {
+        chunks_spawned: load(&counters.chunks_spawned),
+        chunks_despawned: load(&counters.chunks_despawned),
+        chunks_remeshed: load(&counters.chunks_remeshed),
+        mesh_cache_hits: load(&counters.mesh_cache_hits),
+        mesh_cache_misses: load(&counters.mesh_cache_misses),
+        tasks_finished: load(&counters.tasks_finished),
+        task_nanos: load(&counters.task_nanos),
+    };
+
+    diagnostics.add_measurement(&D::<C>::diagnostic_path(D::<C>::CHUNKS_SPAWNED), || {
+        (totals.chunks_spawned - previous.chunks_spawned) as f64
+    });
+    diagnostics
+        .add_measurement(&D::<C>::diagnostic_path(D::<C>::CHUNKS_DESPAWNED), || {
+            (totals.chunks_despawned - previous.chunks_despawned) as f64
+        });
+    diagnostics
+        .add_measurement(&D::<C>::diagnostic_path(D::<C>::CHUNKS_REMESHED), || {
+            (totals.chunks_remeshed - previous.chunks_remeshed) as f64
+        });
+
+    if let Some(rate) = hit_rate(
+        totals.mesh_cache_hits - previous.mesh_cache_hits,
+        totals.mesh_cache_misses - previous.mesh_cache_misses,
+    ) {
+        diagnostics.add_measurement(
+            &D::<C>::diagnostic_path(D::<C>::MESH_CACHE_HIT_RATE),
+            || rate as f64 * 100.0,
+        );
+    }
+
+    let tasks_finished = totals.tasks_finished - previous.tasks_finished;
+    if tasks_finished > 0 {
+        let frame_average =
+            (totals.task_nanos - previous.task_nanos) as f64 / tasks_finished as f64;
+        diagnostics
+            .add_measurement(&D::<C>::diagnostic_path(D::<C>::CHUNK_TASK_TIME), || {
+                frame_average / 1_000_000.0
+            });
+
+        let average = load(&counters.average_task_nanos) as f64;
+        let average = if previous.tasks_finished == 0 {
+            frame_average
+        } else {
+            average + (frame_average - average) * TASK_TIME_SMOOTHING
+        };
+        counters
+            .average_task_nanos
+            .store(average as u64, Ordering::Relaxed);
+    }
+
+    *previous = totals;
+}
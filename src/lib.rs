@@ -1,7 +1,9 @@
 mod chunk;
 mod chunk_map;
 mod configuration;
+pub mod coords;
 mod debug_draw;
+mod diagnostics;
 mod mesh_cache;
 mod meshing;
 mod plugin;
@@ -12,26 +14,48 @@ mod voxel_world;
 mod voxel_world_internal;
 
 pub mod prelude {
-    pub use crate::chunk::{Chunk, NeedsDespawn};
+    pub use crate::chunk::{
+        Chunk, ChunkCollisionData, ChunkGenerationRetry, NeedsDespawn,
+    };
     pub use crate::configuration::*;
+    pub use crate::diagnostics::VoxelWorldDiagnostics;
     pub use crate::plugin::VoxelWorldPlugin;
     pub use crate::voxel::{VoxelFace, WorldVoxel, VOXEL_SIZE};
     pub use crate::voxel_world::{
-        get_chunk_voxel_position, VoxelRaycastResult, VoxelWorld, VoxelWorldCamera,
+        get_chunk_voxel_position, get_chunk_voxel_position_for_size, ChunkStateSnapshot,
+        VoxelClipboard, VoxelRaycastResult, VoxelWorld, VoxelWorldAnchor,
+        VoxelWorldCamera, VoxelWorldReader,
     };
     pub use crate::voxel_world::{
-        ChunkWillDespawn, ChunkWillRemesh, ChunkWillSpawn, ChunkWillUpdate,
+        ChunkGenerationFailed, ChunkInvalidated, ChunkMeshReady, ChunkWillDespawn,
+        ChunkWillRemesh, ChunkWillSpawn, ChunkWillUpdate, InvalidationReason,
     };
 }
 
 pub mod custom_meshing {
+    pub use crate::chunk::padded_chunk_shape_uniform;
+    pub use crate::chunk::ChunkNeighbours;
     pub use crate::chunk::PaddedChunkShape;
     pub use crate::chunk::CHUNK_SIZE_F;
     pub use crate::chunk::CHUNK_SIZE_I;
     pub use crate::chunk::CHUNK_SIZE_U;
+    pub use crate::meshing::add_detail_uvs;
+    pub use crate::meshing::add_surface_distances;
+    pub use crate::meshing::add_voxel_light;
     pub use crate::meshing::generate_chunk_mesh;
+    pub use crate::meshing::generate_chunk_mesh_for_shape;
+    pub use crate::meshing::generate_chunk_mesh_greedy;
+    pub use crate::meshing::generate_chunk_mesh_smooth;
+    pub use crate::meshing::generate_chunk_mesh_with_options;
+    pub use crate::meshing::generate_minimal_chunk_mesh;
     pub use crate::meshing::mesh_from_quads;
+    pub use crate::meshing::mesh_from_quads_for_shape;
+    pub use crate::meshing::resample_voxels_majority;
+    pub use crate::meshing::resample_voxels_nearest;
+    pub use crate::meshing::surface_distance_field;
+    pub use crate::meshing::ChunkMeshOptions;
     pub use crate::meshing::VoxelArray;
+    pub use crate::meshing::DEFAULT_AO_CURVE;
 }
 
 pub mod debug {
@@ -41,13 +65,21 @@ pub mod debug {
 pub mod rendering {
     pub use crate::plugin::VoxelWorldMaterialHandle;
     pub use crate::voxel_material::vertex_layout;
+    pub use crate::voxel_material::world_vertex_layout;
+    pub use crate::voxel_material::ATTRIBUTE_SURFACE_DISTANCE;
     pub use crate::voxel_material::ATTRIBUTE_TEX_INDEX;
+    pub use crate::voxel_material::ATTRIBUTE_VOXEL_LIGHT;
+    pub use crate::voxel_material::FIRST_EXTRA_SHADER_LOCATION;
     pub use crate::voxel_material::VOXEL_TEXTURE_SHADER_HANDLE;
+    pub use crate::voxel_material::VOXEL_TRIPLANAR_SHADER_HANDLE;
 }
 
 pub mod traversal_alg {
     pub use crate::voxel_traversal::*;
 }
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
 #[cfg(test)]
 mod test;
@@ -3,7 +3,7 @@ use std::{
     sync::{Arc, RwLock, Weak},
 };
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{prelude::*, render::primitives::Aabb, utils::HashMap};
 use weak_table::WeakValueHashMap;
 
 use crate::prelude::VoxelWorldConfig;
@@ -13,12 +13,15 @@ use crate::prelude::VoxelWorldConfig;
 #[derive(Component)]
 pub(crate) struct MeshRef(pub Arc<Handle<Mesh>>);
 
-type WeakMeshMap = WeakValueHashMap<u64, Weak<Handle<Mesh>>>;
+type WeakMeshMap = WeakValueHashMap<u128, Weak<Handle<Mesh>>>;
 
 // TODO: Refactor this out of MeshCache. This is a bit of an afterthough, since user bundles are added via
 // the meshing delegate function, which only runs if there's no chached mesh for the chunk. Hence the need
 // to cache the user bundle here as well.
-type UserBundleMap<UB> = HashMap<u64, UB>;
+type UserBundleMap<UB> = HashMap<u128, UB>;
+
+// Custom bounds returned by the meshing delegate, cached for the same reason as the user bundles
+type AabbMap = HashMap<u128, Aabb>;
 
 /// MeshCache uses a weak map to keep track of mesh handles generated for a certain configuration of voxels.
 /// Using this map, we can avoid generating the same mesh multiple times, and reusing mesh handles
@@ -27,6 +30,7 @@ type UserBundleMap<UB> = HashMap<u64, UB>;
 pub(crate) struct MeshCache<C: VoxelWorldConfig> {
     mesh_handles: Arc<RwLock<WeakMeshMap>>,
     user_bundes: Arc<RwLock<UserBundleMap<C::ChunkUserBundle>>>,
+    aabbs: Arc<RwLock<AabbMap>>,
     _marker: std::marker::PhantomData<C>,
 }
 
@@ -36,21 +40,26 @@ impl<C: VoxelWorldConfig> MeshCache<C> {
             return;
         }
 
-        if let (Ok(mut mesh_handles), Ok(mut user_bundles)) =
-            (self.mesh_handles.try_write(), self.user_bundes.try_write())
-        {
-            for (voxels, mesh, user_bundle) in insert_buffer.drain(..) {
+        if let (Ok(mut mesh_handles), Ok(mut user_bundles), Ok(mut aabbs)) = (
+            self.mesh_handles.try_write(),
+            self.user_bundes.try_write(),
+            self.aabbs.try_write(),
+        ) {
+            for (voxels, mesh, user_bundle, aabb) in insert_buffer.drain(..) {
                 mesh_handles.insert(voxels, mesh);
                 if let Some(user_bundle) = user_bundle {
                     user_bundles.insert(voxels, user_bundle);
                 }
+                if let Some(aabb) = aabb {
+                    aabbs.insert(voxels, aabb);
+                }
             }
             mesh_handles.remove_expired();
             //user_bundles.remove_expired();
         }
     }
 
-    pub fn get_mesh_handle(&self, voxels_hash: &u64) -> Option<Arc<Handle<Mesh>>> {
+    pub fn get_mesh_handle(&self, voxels_hash: &u128) -> Option<Arc<Handle<Mesh>>> {
         self.mesh_handles.read().unwrap().get(voxels_hash)
     }
 
@@ -58,9 +67,13 @@ impl<C: VoxelWorldConfig> MeshCache<C> {
         self.mesh_handles.clone()
     }
 
-    pub fn get_user_bundle(&self, voxels_hash: &u64) -> Option<C::ChunkUserBundle> {
+    pub fn get_user_bundle(&self, voxels_hash: &u128) -> Option<C::ChunkUserBundle> {
         self.user_bundes.read().unwrap().get(voxels_hash).cloned()
     }
+
+    pub fn get_aabb(&self, voxels_hash: &u128) -> Option<Aabb> {
+        self.aabbs.read().unwrap().get(voxels_hash).copied()
+    }
 }
 
 impl<C: VoxelWorldConfig> Default for MeshCache<C> {
@@ -68,6 +81,7 @@ impl<C: VoxelWorldConfig> Default for MeshCache<C> {
         Self {
             mesh_handles: Arc::new(RwLock::new(WeakMeshMap::with_capacity(2000))),
             user_bundes: Arc::new(RwLock::new(UserBundleMap::with_capacity(2000))),
+            aabbs: Arc::new(RwLock::new(AabbMap::default())),
             _marker: std::marker::PhantomData,
         }
     }
@@ -77,7 +91,13 @@ type MeshHandleRef = Arc<Handle<Mesh>>;
 
 #[derive(Resource, Deref, DerefMut)]
 pub(crate) struct MeshCacheInsertBuffer<C: VoxelWorldConfig>(
-    #[deref] Vec<(u64, MeshHandleRef, Option<C::ChunkUserBundle>)>,
+    #[deref]
+    Vec<(
+        u128,
+        MeshHandleRef,
+        Option<C::ChunkUserBundle>,
+        Option<Aabb>,
+    )>,
     PhantomData<C>,
 );
 
@@ -86,3 +106,12 @@ impl<C: VoxelWorldConfig> Default for MeshCacheInsertBuffer<C> {
         Self(Vec::with_capacity(1000), PhantomData)
     }
 }
+
+/// Meshes queued with `VoxelWorld::preload_mesh`, along with the hash of the voxels they were
+/// generated from. These get added to the mesh cache before chunks are remeshed.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct MeshPreloadBuffer<C>(#[deref] Vec<(u128, Mesh)>, PhantomData<C>);
+
+/// Holds strong references to preloaded meshes, since the mesh cache only holds weak references
+#[derive(Resource, Deref, DerefMut, Default)]
+pub(crate) struct PreloadedMeshes<C>(#[deref] Vec<MeshHandleRef>, PhantomData<C>);
@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
 use block_mesh::{
-    visible_block_faces, OrientedBlockFace, UnitQuadBuffer, Voxel, VoxelVisibility,
+    greedy_quads, visible_block_faces, GreedyQuadsBuffer, OrientedBlockFace,
+    UnitQuadBuffer, UnorientedQuad, UnorientedUnitQuad, Voxel, VoxelVisibility,
     RIGHT_HANDED_Y_UP_CONFIG,
 };
 
@@ -13,47 +14,687 @@ use bevy::{
         render_resource::PrimitiveTopology,
     },
 };
-use ndshape::ConstShape;
+use ndshape::{RuntimeShape, Shape};
 
 use crate::{
-    chunk::{PaddedChunkShape, CHUNK_SIZE_U},
-    prelude::TextureIndexMapperFn,
+    chunk::{padded_chunk_shape_uniform, CHUNK_SIZE_U},
+    prelude::{MaterialAlphaFn, TextureIndexMapperFn, VoxelLightFn},
     voxel::WorldVoxel,
-    voxel_material::ATTRIBUTE_TEX_INDEX,
+    voxel_material::{
+        ATTRIBUTE_SURFACE_DISTANCE, ATTRIBUTE_TEX_INDEX, ATTRIBUTE_VOXEL_LIGHT,
+    },
 };
 
-pub type VoxelArray<I> = Arc<[WorldVoxel<I>; PaddedChunkShape::SIZE as usize]>;
+pub type VoxelArray<I> = Arc<crate::chunk::VoxelArray<I>>;
+
+/// The vertex color brightness for each ambient occlusion level, from the most occluded corner
+/// to an unoccluded one. Used by the meshing functions that don't take an `ao_curve`.
+pub const DEFAULT_AO_CURVE: [f32; 4] = [0.1, 0.3, 0.5, 1.0];
 
 /// Generate a mesh for the given chunks, or None of the chunk is empty
+///
+/// Expects a chunk of the default size. Use `generate_chunk_mesh_for_shape` for worlds with a
+/// custom `chunk_size`.
 pub fn generate_chunk_mesh<I: PartialEq + Copy>(
+    voxels: VoxelArray<I>,
+    pos: IVec3,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+) -> Mesh {
+    generate_chunk_mesh_for_shape(
+        voxels,
+        pos,
+        padded_chunk_shape_uniform(CHUNK_SIZE_U),
+        texture_index_mapper,
+        Arc::new(|_| 1.0),
+    )
+}
+
+/// Generate a mesh for the given chunk, where `shape` is the padded shape of the voxel array.
+/// The alpha returned by `material_alpha` is written to the alpha channel of the vertex colors.
+pub fn generate_chunk_mesh_for_shape<I: PartialEq + Copy>(
     voxels: VoxelArray<I>,
     _pos: IVec3,
+    shape: RuntimeShape<u32, 3>,
     texture_index_mapper: TextureIndexMapperFn<I>,
+    material_alpha: MaterialAlphaFn<I>,
+) -> Mesh {
+    generate_unit_quad_mesh(
+        voxels,
+        shape,
+        texture_index_mapper,
+        material_alpha,
+        DEFAULT_AO_CURVE,
+    )
+}
+
+/// Options for the default mesher, for `generate_chunk_mesh_with_options` and
+/// `default_chunk_meshing_delegate_with_options`.
+#[derive(Clone)]
+pub struct ChunkMeshOptions<I> {
+    /// The alpha written to the alpha channel of the vertex colors for each material. See
+    /// `VoxelWorldConfig::material_alpha`.
+    pub material_alpha: MaterialAlphaFn<I>,
+
+    /// The vertex color brightness for each ambient occlusion level, from the most occluded
+    /// corner to an unoccluded one. Use `[1.0; 4]` to turn the darkening off. See
+    /// `VoxelWorldConfig::ao_curve`.
+    pub ao_curve: [f32; 4],
+}
+
+impl<I: 'static> Default for ChunkMeshOptions<I> {
+    fn default() -> Self {
+        Self {
+            material_alpha: Arc::new(|_| 1.0),
+            ao_curve: DEFAULT_AO_CURVE,
+        }
+    }
+}
+
+/// Same as `generate_chunk_mesh_for_shape`, but with the material alpha and ambient occlusion
+/// taken from `options`.
+pub fn generate_chunk_mesh_with_options<I: PartialEq + Copy>(
+    voxels: VoxelArray<I>,
+    shape: RuntimeShape<u32, 3>,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+    options: &ChunkMeshOptions<I>,
+) -> Mesh {
+    generate_unit_quad_mesh(
+        voxels,
+        shape,
+        texture_index_mapper,
+        options.material_alpha.clone(),
+        options.ao_curve,
+    )
+}
+
+fn generate_unit_quad_mesh<I: PartialEq + Copy>(
+    voxels: VoxelArray<I>,
+    shape: RuntimeShape<u32, 3>,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+    material_alpha: MaterialAlphaFn<I>,
+    ao_curve: [f32; 4],
 ) -> Mesh {
     let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
     let mut buffer = UnitQuadBuffer::new();
+    let [x, y, z] = shape.as_array();
 
     visible_block_faces(
-        &*voxels,
-        &PaddedChunkShape {},
+        &voxels,
+        &shape,
         [0; 3],
-        [CHUNK_SIZE_U + 1; 3],
+        [x - 1, y - 1, z - 1],
         &faces,
         &mut buffer,
     );
+    for_each_translucent_boundary_face(&voxels, &shape, &faces, |face, minimum| {
+        buffer.groups[face].push(UnorientedUnitQuad { minimum })
+    });
+
+    let num_quads = buffer.num_quads();
+    build_mesh(
+        buffer.groups,
+        num_quads,
+        faces,
+        &voxels,
+        &shape,
+        texture_index_mapper,
+        material_alpha,
+        ao_curve,
+        |quad, normal| face_aos(&quad.minimum, normal, &voxels, &shape),
+    )
+}
+
+/// Generate a mesh with only positions, and normals if `with_normals` is true, for the given
+/// chunk, where `shape` is the padded shape of the voxel array.
+///
+/// This skips the work of computing UVs, texture indexes and vertex colors, so the mesh can't be
+/// rendered with the default material.
+pub fn generate_minimal_chunk_mesh<I: PartialEq + Copy>(
+    voxels: VoxelArray<I>,
+    _pos: IVec3,
+    shape: RuntimeShape<u32, 3>,
+    with_normals: bool,
+) -> Mesh {
+    let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+    let mut buffer = UnitQuadBuffer::new();
+    let [x, y, z] = shape.as_array();
+
+    visible_block_faces(
+        &voxels,
+        &shape,
+        [0; 3],
+        [x - 1, y - 1, z - 1],
+        &faces,
+        &mut buffer,
+    );
+    for_each_translucent_boundary_face(&voxels, &shape, &faces, |face, minimum| {
+        buffer.groups[face].push(UnorientedUnitQuad { minimum })
+    });
+
+    let num_quads = buffer.num_quads();
+    let mut indices = Vec::with_capacity(num_quads * 6);
+    let mut positions = Vec::with_capacity(num_quads * 4);
+    let mut normals = Vec::with_capacity(if with_normals { num_quads * 4 } else { 0 });
+
+    for (group, face) in buffer.groups.into_iter().zip(faces.into_iter()) {
+        for quad in group.into_iter() {
+            let quad: UnorientedQuad = quad.into();
+            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
+            positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
+            if with_normals {
+                normals.extend_from_slice(&face.quad_mesh_normals());
+            }
+        }
+    }
+
+    let mut render_mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    render_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        VertexAttributeValues::Float32x3(positions),
+    );
+    if with_normals {
+        render_mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            VertexAttributeValues::Float32x3(normals),
+        );
+    }
+    render_mesh.insert_indices(Indices::U32(indices));
+
+    render_mesh
+}
+
+/// Generate a smooth mesh for the given chunk using surface nets, where `shape` is the padded
+/// shape of the voxel array.
+///
+/// `WorldVoxel::Solid` voxels are treated as inside, and all other voxels as outside. A vertex is
+/// placed in every cell of eight neighbouring voxels that is crossed by the surface, and the
+/// vertices are connected into quads. Normals are smoothed across the surface, and the texture
+/// index of each vertex is picked from one of the solid voxels of its cell.
+///
+/// The UV coordinates are projected along the main axis of the normal, in voxel units, so the
+/// texture sampler needs a repeating address mode for textures to tile.
+pub fn generate_chunk_mesh_smooth<I: PartialEq + Copy>(
+    voxels: VoxelArray<I>,
+    _pos: IVec3,
+    shape: RuntimeShape<u32, 3>,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+) -> Mesh {
+    let [x_size, y_size, z_size] = shape.as_array();
+    let cells = [x_size - 1, y_size - 1, z_size - 1];
+    let cell_index =
+        |cell: [u32; 3]| (cell[0] + cells[0] * (cell[1] + cells[1] * cell[2])) as usize;
+    let voxel_at = |position: [u32; 3]| voxels[shape.linearize(position) as usize];
+    let corner_offset = |corner: usize| {
+        [
+            corner as u32 & 1,
+            (corner as u32 >> 1) & 1,
+            (corner as u32 >> 2) & 1,
+        ]
+    };
+
+    // Place a vertex in every cell that has both inside and outside corners
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut materials: Vec<I> = Vec::new();
+    let mut cell_vertices = vec![u32::MAX; (cells[0] * cells[1] * cells[2]) as usize];
+
+    for z in 0..cells[2] {
+        for y in 0..cells[1] {
+            for x in 0..cells[0] {
+                let corners = std::array::from_fn::<_, 8, _>(|corner| {
+                    let offset = corner_offset(corner);
+                    voxel_at([x + offset[0], y + offset[1], z + offset[2]])
+                });
+                let Some(material) = corners.iter().find_map(|voxel| match voxel {
+                    WorldVoxel::Solid(material) => Some(*material),
+                    _ => None,
+                }) else {
+                    continue;
+                };
+                if corners.iter().all(|voxel| voxel.is_solid()) {
+                    continue;
+                }
+
+                // Average the midpoints of the cell edges that cross the surface
+                let mut crossing_sum = Vec3::ZERO;
+                let mut crossing_count = 0.0;
+                for corner in 0..8 {
+                    for axis in 0..3 {
+                        let neighbour = corner | (1 << axis);
+                        if neighbour == corner
+                            || corners[corner].is_solid() == corners[neighbour].is_solid()
+                        {
+                            continue;
+                        }
+                        let a = UVec3::from(corner_offset(corner)).as_vec3();
+                        let b = UVec3::from(corner_offset(neighbour)).as_vec3();
+                        crossing_sum += (a + b) * 0.5;
+                        crossing_count += 1.0;
+                    }
+                }
+
+                // Voxel centers are at half coordinates, same as in the blocky meshes
+                cell_vertices[cell_index([x, y, z])] = positions.len() as u32;
+                positions.push(
+                    Vec3::new(x as f32, y as f32, z as f32)
+                        + 0.5
+                        + crossing_sum / crossing_count,
+                );
+                materials.push(material);
+            }
+        }
+    }
+
+    // Add a quad for every voxel edge that crosses the surface. Only edges starting in the
+    // unpadded part of the chunk are included, so that neighbouring chunks don't overlap.
+    let mut indices: Vec<u32> = Vec::new();
+    for z in 1..z_size - 1 {
+        for y in 1..y_size - 1 {
+            for x in 1..x_size - 1 {
+                let position = [x, y, z];
+                let inside = voxel_at(position).is_solid();
+
+                for axis in 0..3 {
+                    let mut next = position;
+                    next[axis] += 1;
+                    if voxel_at(next).is_solid() == inside {
+                        continue;
+                    }
+
+                    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let vertex = |du: u32, dv: u32| {
+                        let mut cell = position;
+                        cell[u] -= du;
+                        cell[v] -= dv;
+                        cell_vertices[cell_index(cell)]
+                    };
+
+                    // Counter-clockwise when seen from the outside
+                    let quad = [vertex(1, 1), vertex(0, 1), vertex(0, 0), vertex(1, 0)];
+                    if inside {
+                        indices.extend([
+                            quad[0], quad[1], quad[2], quad[0], quad[2], quad[3],
+                        ]);
+                    } else {
+                        indices.extend([
+                            quad[3], quad[2], quad[1], quad[3], quad[1], quad[0],
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    // Smooth normals, from the area weighted normals of the surrounding triangles
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+
+    let mut tex_coords = Vec::with_capacity(positions.len());
+    let mut material_types = Vec::with_capacity(positions.len());
+    for ((position, normal), material) in
+        positions.iter().zip(normals.iter_mut()).zip(materials)
+    {
+        *normal = normal.normalize_or_zero();
+
+        let abs = normal.abs();
+        let (face_normal, tex_coord) = if abs.x >= abs.y && abs.x >= abs.z {
+            (
+                IVec3::X * normal.x.signum() as i32,
+                [position.z, position.y],
+            )
+        } else if abs.y >= abs.z {
+            (
+                IVec3::Y * normal.y.signum() as i32,
+                [position.x, position.z],
+            )
+        } else {
+            (
+                IVec3::Z * normal.z.signum() as i32,
+                [position.x, position.y],
+            )
+        };
+        tex_coords.push(tex_coord);
+        material_types.push(face_texture_index(
+            texture_index_mapper(material),
+            &face_normal,
+        ));
+    }
+
+    let mut render_mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    render_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_COLOR,
+        VertexAttributeValues::Float32x4(vec![[1.0; 4]; positions.len()]),
+    );
+    render_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        VertexAttributeValues::Float32x3(
+            positions.into_iter().map(Vec3::to_array).collect(),
+        ),
+    );
+    render_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        VertexAttributeValues::Float32x3(
+            normals.into_iter().map(Vec3::to_array).collect(),
+        ),
+    );
+    render_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        VertexAttributeValues::Float32x2(tex_coords),
+    );
+    render_mesh.insert_attribute(
+        ATTRIBUTE_TEX_INDEX,
+        VertexAttributeValues::Uint32x3(material_types),
+    );
+    render_mesh.insert_indices(Indices::U32(indices));
 
-    mesh_from_quads(buffer, faces, voxels, texture_index_mapper)
+    render_mesh
+}
+
+/// Add a `UV_1` attribute to the mesh, with the `UV_0` coordinates multiplied by `tiling`. Does
+/// nothing if the mesh has no `UV_0` attribute. See `VoxelWorldConfig::detail_uv_tiling`.
+pub fn add_detail_uvs(mesh: &mut Mesh, tiling: f32) {
+    let Some(VertexAttributeValues::Float32x2(tex_coords)) =
+        mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+    else {
+        return;
+    };
+    let detail_tex_coords = tex_coords
+        .iter()
+        .map(|[u, v]| [u * tiling, v * tiling])
+        .collect::<Vec<_>>();
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_1,
+        VertexAttributeValues::Float32x2(detail_tex_coords),
+    );
+}
+
+/// Compute the signed distance from each voxel in `voxels` to the nearest surface, measured in
+/// voxel steps along the axes. Solid voxels get a positive distance and all other voxels a
+/// negative one, so voxels on either side of a face are at 1 and -1. Distances are capped at
+/// `max_distance`, and only take the voxels within the array into account.
+pub fn surface_distance_field<I: PartialEq + Copy>(
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    max_distance: u32,
+) -> Vec<f32> {
+    let size = UVec3::from(shape.as_array()).as_ivec3();
+    let neighbours = move |index: usize| {
+        let position = IVec3::from(shape.delinearize(index as u32).map(|v| v as i32));
+        [
+            IVec3::X,
+            IVec3::NEG_X,
+            IVec3::Y,
+            IVec3::NEG_Y,
+            IVec3::Z,
+            IVec3::NEG_Z,
+        ]
+        .into_iter()
+        .map(move |offset| position + offset)
+        .filter(move |neighbour| {
+            neighbour.cmpge(IVec3::ZERO).all() && neighbour.cmplt(size).all()
+        })
+        .map(move |neighbour| shape.linearize(neighbour.as_uvec3().to_array()) as usize)
+    };
+
+    let solid = voxels
+        .iter()
+        .map(|voxel| voxel.is_solid())
+        .collect::<Vec<_>>();
+    let mut distances = vec![u32::MAX; voxels.len()];
+    let mut queue = VecDeque::new();
+
+    // Start from the voxels right next to a surface, and flood outwards from there
+    for index in 0..voxels.len() {
+        if neighbours(index).any(|neighbour| solid[neighbour] != solid[index]) {
+            distances[index] = 1;
+            queue.push_back(index);
+        }
+    }
+    while let Some(index) = queue.pop_front() {
+        let distance = distances[index];
+        if distance >= max_distance {
+            continue;
+        }
+        for neighbour in neighbours(index) {
+            if solid[neighbour] == solid[index] && distances[neighbour] == u32::MAX {
+                distances[neighbour] = distance + 1;
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    distances
+        .into_iter()
+        .zip(solid)
+        .map(|(distance, solid)| {
+            let distance = distance.min(max_distance) as f32;
+            if solid {
+                distance
+            } else {
+                -distance
+            }
+        })
+        .collect()
+}
+
+/// Add an `ATTRIBUTE_SURFACE_DISTANCE` attribute to the mesh, with the average of
+/// `surface_distance_field` over the eight voxels around each vertex. Vertices in open, convex
+/// areas get lower values than vertices in creases and on large flat surfaces, which custom
+/// shaders can use for soft shadows or thickness effects. See
+/// `VoxelWorldConfig::surface_distance_range`.
+///
+/// Expects the mesh positions to be in the padded voxel space of `shape`, as with the default
+/// meshers. Does nothing if the mesh has no positions.
+pub fn add_surface_distances<I: PartialEq + Copy>(
+    mesh: &mut Mesh,
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    max_distance: u32,
+) {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return;
+    };
+    let field = surface_distance_field(voxels, shape, max_distance);
+    let max_index = UVec3::from(shape.as_array()).as_ivec3() - 1;
+
+    let distances = positions
+        .iter()
+        .map(|position| {
+            let corner = Vec3::from(*position).round().as_ivec3();
+            let total = (0..8)
+                .map(|i| {
+                    let offset = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                    let voxel = (corner - offset).clamp(IVec3::ZERO, max_index);
+                    field[shape.linearize(voxel.as_uvec3().to_array()) as usize]
+                })
+                .sum::<f32>();
+            total / 8.0
+        })
+        .collect::<Vec<_>>();
+    mesh.insert_attribute(
+        ATTRIBUTE_SURFACE_DISTANCE,
+        VertexAttributeValues::Float32(distances),
+    );
+}
+
+/// Add an `ATTRIBUTE_VOXEL_LIGHT` attribute to the mesh, with the average light of the four
+/// voxels in front of the face around each vertex, scaled to between 0 and 1. See
+/// `VoxelWorldConfig::voxel_light`.
+///
+/// `padded_origin` is the world position of the first voxel in the padded voxel array. Expects
+/// the mesh positions to be in the padded voxel space of `shape`, as with the default meshers.
+/// Does nothing if the mesh has no positions or normals.
+pub fn add_voxel_light<I: PartialEq + Copy>(
+    mesh: &mut Mesh,
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    padded_origin: IVec3,
+    voxel_light: VoxelLightFn<I>,
+) {
+    let (
+        Some(VertexAttributeValues::Float32x3(positions)),
+        Some(VertexAttributeValues::Float32x3(normals)),
+    ) = (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+    )
+    else {
+        return;
+    };
+    let max_index = UVec3::from(shape.as_array()).as_ivec3() - 1;
+
+    let lights = positions
+        .iter()
+        .zip(normals)
+        .map(|(position, normal)| {
+            let corner = Vec3::from(*position).round().as_ivec3();
+            let normal = Vec3::from(*normal).round().as_ivec3();
+            // One layer of voxels in front of the face, and the four voxels sharing the vertex
+            // within that layer
+            let layer = corner + normal.min(IVec3::ZERO);
+            let total = (0..4)
+                .map(|i| {
+                    let (a, b) = (i & 1, (i >> 1) & 1);
+                    let offset = match normal.abs() {
+                        IVec3::X => IVec3::new(0, a, b),
+                        IVec3::Y => IVec3::new(a, 0, b),
+                        _ => IVec3::new(a, b, 0),
+                    };
+                    let voxel = (layer - offset).clamp(IVec3::ZERO, max_index);
+                    let index = shape.linearize(voxel.as_uvec3().to_array()) as usize;
+                    voxel_light(padded_origin + voxel, &voxels[index]) as u32
+                })
+                .sum::<u32>();
+            total as f32 / (4.0 * u8::MAX as f32)
+        })
+        .collect::<Vec<_>>();
+    mesh.insert_attribute(
+        ATTRIBUTE_VOXEL_LIGHT,
+        VertexAttributeValues::Float32(lights),
+    );
 }
 
 /// Create a Bevy Mesh from a block_mesh::UnitQuadBuffer
+///
+/// Expects a chunk of the default size. Use `mesh_from_quads_for_shape` for worlds with a
+/// custom `chunk_size`.
 pub fn mesh_from_quads<I: PartialEq + Copy>(
     quads: UnitQuadBuffer,
     faces: [OrientedBlockFace; 6],
     voxels: VoxelArray<I>,
     texture_index_mapper: Arc<dyn Fn(I) -> [u32; 3] + Send + Sync>,
 ) -> Mesh {
-    let num_indices = quads.num_quads() * 6;
-    let num_vertices = quads.num_quads() * 4;
+    mesh_from_quads_for_shape(
+        quads,
+        faces,
+        voxels,
+        padded_chunk_shape_uniform(CHUNK_SIZE_U),
+        texture_index_mapper,
+        Arc::new(|_| 1.0),
+    )
+}
+
+/// Create a Bevy Mesh from a block_mesh::UnitQuadBuffer, where `shape` is the padded shape of
+/// the voxel array
+pub fn mesh_from_quads_for_shape<I: PartialEq + Copy>(
+    quads: UnitQuadBuffer,
+    faces: [OrientedBlockFace; 6],
+    voxels: VoxelArray<I>,
+    shape: RuntimeShape<u32, 3>,
+    texture_index_mapper: Arc<dyn Fn(I) -> [u32; 3] + Send + Sync>,
+    material_alpha: MaterialAlphaFn<I>,
+) -> Mesh {
+    let num_quads = quads.num_quads();
+    build_mesh(
+        quads.groups,
+        num_quads,
+        faces,
+        &voxels,
+        &shape,
+        texture_index_mapper,
+        material_alpha,
+        DEFAULT_AO_CURVE,
+        |quad, normal| face_aos(&quad.minimum, normal, &voxels, &shape),
+    )
+}
+
+/// Generate a mesh for the given chunk using greedy meshing, where `shape` is the padded shape
+/// of the voxel array.
+///
+/// Adjacent faces of voxels with the same material are merged into larger quads, which gives far
+/// fewer vertices than `generate_chunk_mesh_for_shape`. Ambient occlusion can not be represented
+/// on merged quads, so all vertex colors are fully lit, with the alpha from `material_alpha`.
+///
+/// The UV coordinates span the whole merged quad, so the texture sampler needs a repeating
+/// address mode for textures to tile across it.
+pub fn generate_chunk_mesh_greedy<I: PartialEq + Eq + Default + Copy>(
+    voxels: VoxelArray<I>,
+    _pos: IVec3,
+    shape: RuntimeShape<u32, 3>,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+    material_alpha: MaterialAlphaFn<I>,
+) -> Mesh {
+    let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+    let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+    let [x, y, z] = shape.as_array();
+
+    greedy_quads(
+        &voxels,
+        &shape,
+        [0; 3],
+        [x - 1, y - 1, z - 1],
+        &faces,
+        &mut buffer,
+    );
+    for_each_translucent_boundary_face(&voxels, &shape, &faces, |face, minimum| {
+        buffer.quads.groups[face].push(UnorientedQuad {
+            minimum,
+            width: 1,
+            height: 1,
+        })
+    });
+
+    let num_quads = buffer.quads.num_quads();
+    build_mesh(
+        buffer.quads.groups,
+        num_quads,
+        faces,
+        &voxels,
+        &shape,
+        texture_index_mapper,
+        material_alpha,
+        DEFAULT_AO_CURVE,
+        |_, _| [3; 4],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_mesh<I: PartialEq + Copy, Q: Copy + Into<UnorientedQuad>>(
+    groups: [Vec<Q>; 6],
+    num_quads: usize,
+    faces: [OrientedBlockFace; 6],
+    voxels: &VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    texture_index_mapper: TextureIndexMapperFn<I>,
+    material_alpha: MaterialAlphaFn<I>,
+    ao_curve: [f32; 4],
+    quad_aos: impl Fn(&UnorientedQuad, &IVec3) -> [u32; 4],
+) -> Mesh {
+    let num_indices = num_quads * 6;
+    let num_vertices = num_quads * 4;
 
     let mut indices = Vec::with_capacity(num_indices);
     let mut positions = Vec::with_capacity(num_vertices);
@@ -61,37 +702,43 @@ pub fn mesh_from_quads<I: PartialEq + Copy>(
     let mut tex_coords = Vec::with_capacity(num_vertices);
     let mut material_types = Vec::with_capacity(num_vertices);
     let mut aos = Vec::with_capacity(num_vertices);
+    let mut alphas = Vec::with_capacity(num_vertices);
 
-    for (group, face) in quads.groups.into_iter().zip(faces.into_iter()) {
+    for (group, face) in groups.into_iter().zip(faces.into_iter()) {
         for quad in group.into_iter() {
+            let quad: UnorientedQuad = quad.into();
             let normal = IVec3::from([
                 face.signed_normal().x,
                 face.signed_normal().y,
                 face.signed_normal().z,
             ]);
 
-            let ao = face_aos(&quad.minimum, &normal, &voxels);
+            let ao = quad_aos(&quad, &normal);
             aos.extend_from_slice(&ao);
 
             // TODO: Fix AO anisotropy
             indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
 
-            positions.extend_from_slice(&face.quad_mesh_positions(&quad.into(), 1.0));
+            positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
 
             normals.extend_from_slice(&face.quad_mesh_normals());
 
             tex_coords.extend_from_slice(&face.tex_coords(
                 RIGHT_HANDED_Y_UP_CONFIG.u_flip_face,
                 true,
-                &quad.into(),
+                &quad,
             ));
 
-            let voxel_index = PaddedChunkShape::linearize(quad.minimum) as usize;
-            let material_type = match voxels[voxel_index] {
-                WorldVoxel::Solid(mt) => texture_index_mapper(mt),
-                _ => [0, 0, 0],
+            let voxel_index = shape.linearize(quad.minimum) as usize;
+            let (material_type, alpha) = match voxels[voxel_index] {
+                WorldVoxel::Solid(mt) | WorldVoxel::Translucent(mt) => (
+                    face_texture_index(texture_index_mapper(mt), &normal),
+                    material_alpha(mt),
+                ),
+                _ => ([0, 0, 0], 1.0),
             };
             material_types.extend(std::iter::repeat(material_type).take(4));
+            alphas.extend(std::iter::repeat(alpha).take(4));
         }
     }
 
@@ -122,12 +769,9 @@ pub fn mesh_from_quads<I: PartialEq + Copy>(
         let colors: Vec<[f32; 4]> = positions
             .iter()
             .enumerate()
-            .map(|(i, _)| match aos[i] {
-                0 => [0.1, 0.1, 0.1, 1.0],
-                1 => [0.3, 0.3, 0.3, 1.0],
-                2 => [0.5, 0.5, 0.5, 1.0],
-                3 => [1.0, 1.0, 1.0, 1.0],
-                _ => [1.0, 1.0, 1.0, 1.0],
+            .map(|(i, _)| {
+                let brightness = ao_curve.get(aos[i] as usize).copied().unwrap_or(1.0);
+                [brightness, brightness, brightness, alphas[i]]
             })
             .collect();
         render_mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
@@ -138,6 +782,181 @@ pub fn mesh_from_quads<I: PartialEq + Copy>(
     render_mesh
 }
 
+/// Merge the given meshes into a single mesh, with the vertices of each mesh transformed by its
+/// `Transform`.
+///
+/// Positions, normals, UVs, vertex colors and texture indexes are kept. An attribute that is
+/// missing from any of the meshes is left out of the merged mesh. Meshes without positions are
+/// skipped.
+pub(crate) fn merge_meshes<'a>(
+    parts: impl IntoIterator<Item = (&'a Mesh, Transform)>,
+) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Option<Vec<[f32; 3]>> = Some(Vec::new());
+    let mut tex_coords: Option<Vec<[f32; 2]>> = Some(Vec::new());
+    let mut colors: Option<Vec<[f32; 4]>> = Some(Vec::new());
+    let mut material_types: Option<Vec<[u32; 3]>> = Some(Vec::new());
+    let mut indices: Vec<u32> = Vec::new();
+
+    for (mesh, transform) in parts {
+        let Some(VertexAttributeValues::Float32x3(part_positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            continue;
+        };
+
+        let offset = positions.len() as u32;
+        positions.extend(
+            part_positions
+                .iter()
+                .map(|p| transform.transform_point(Vec3::from_array(*p)).to_array()),
+        );
+
+        extend_attribute(
+            &mut normals,
+            match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+                Some(VertexAttributeValues::Float32x3(part_normals)) => Some(
+                    part_normals
+                        .iter()
+                        .map(|n| (transform.rotation * Vec3::from_array(*n)).to_array())
+                        .collect(),
+                ),
+                _ => None,
+            },
+        );
+        extend_attribute(
+            &mut tex_coords,
+            match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+                Some(VertexAttributeValues::Float32x2(part_tex_coords)) => {
+                    Some(part_tex_coords.clone())
+                }
+                _ => None,
+            },
+        );
+        extend_attribute(
+            &mut colors,
+            match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+                Some(VertexAttributeValues::Float32x4(part_colors)) => {
+                    Some(part_colors.clone())
+                }
+                _ => None,
+            },
+        );
+        extend_attribute(
+            &mut material_types,
+            match mesh.attribute(ATTRIBUTE_TEX_INDEX) {
+                Some(VertexAttributeValues::Uint32x3(part_material_types)) => {
+                    Some(part_material_types.clone())
+                }
+                _ => None,
+            },
+        );
+
+        match mesh.indices() {
+            Some(part_indices) => {
+                indices.extend(part_indices.iter().map(|i| i as u32 + offset))
+            }
+            None => indices.extend(offset..positions.len() as u32),
+        }
+    }
+
+    let mut merged_mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    merged_mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        VertexAttributeValues::Float32x3(positions),
+    );
+    if let Some(normals) = normals {
+        merged_mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            VertexAttributeValues::Float32x3(normals),
+        );
+    }
+    if let Some(tex_coords) = tex_coords {
+        merged_mesh.insert_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            VertexAttributeValues::Float32x2(tex_coords),
+        );
+    }
+    if let Some(colors) = colors {
+        merged_mesh.insert_attribute(
+            Mesh::ATTRIBUTE_COLOR,
+            VertexAttributeValues::Float32x4(colors),
+        );
+    }
+    if let Some(material_types) = material_types {
+        merged_mesh.insert_attribute(
+            ATTRIBUTE_TEX_INDEX,
+            VertexAttributeValues::Uint32x3(material_types),
+        );
+    }
+    merged_mesh.insert_indices(Indices::U32(indices));
+
+    merged_mesh
+}
+
+/// Appends `values` to `merged`, or drops the merged attribute entirely if `values` is missing
+fn extend_attribute<T>(merged: &mut Option<Vec<T>>, values: Option<Vec<T>>) {
+    match (merged.as_mut(), values) {
+        (Some(merged), Some(values)) => merged.extend(values),
+        _ => *merged = None,
+    }
+}
+
+/// Picks the texture index for a face from the top, sides and bottom indexes returned by the
+/// texture index mapper. The picked index is repeated for all three components of the
+/// `ATTRIBUTE_TEX_INDEX` attribute, so that it doesn't matter which one a shader reads.
+fn face_texture_index(texture_indexes: [u32; 3], face_normal: &IVec3) -> [u32; 3] {
+    let index = match face_normal.y {
+        1 => texture_indexes[0],
+        -1 => texture_indexes[2],
+        _ => texture_indexes[1],
+    };
+    [index; 3]
+}
+
+/// Calls `f` with the face index and voxel position of every face between two translucent voxels
+/// of different materials. `block_mesh` never meshes faces between two translucent voxels, so
+/// these are added separately.
+fn for_each_translucent_boundary_face<I: PartialEq + Copy>(
+    voxels: &[WorldVoxel<I>],
+    shape: &RuntimeShape<u32, 3>,
+    faces: &[OrientedBlockFace; 6],
+    mut f: impl FnMut(usize, [u32; 3]),
+) {
+    let [x_size, y_size, z_size] = shape.as_array();
+
+    for z in 1..z_size - 1 {
+        for y in 1..y_size - 1 {
+            for x in 1..x_size - 1 {
+                let WorldVoxel::Translucent(material) =
+                    voxels[shape.linearize([x, y, z]) as usize]
+                else {
+                    continue;
+                };
+
+                for (face_index, face) in faces.iter().enumerate() {
+                    let normal = face.signed_normal();
+                    let neighbour = [
+                        x.wrapping_add_signed(normal.x),
+                        y.wrapping_add_signed(normal.y),
+                        z.wrapping_add_signed(normal.z),
+                    ];
+                    if let WorldVoxel::Translucent(neighbour_material) =
+                        voxels[shape.linearize(neighbour) as usize]
+                    {
+                        if neighbour_material != material {
+                            f(face_index, [x, y, z]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn ao_value(side1: bool, corner: bool, side2: bool) -> u32 {
     match (side1, corner, side2) {
         (true, _, true) => 0,
@@ -171,70 +990,162 @@ fn face_aos<I: PartialEq + Copy>(
     voxel_pos: &[u32; 3],
     face_normal: &IVec3,
     voxels: &VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
 ) -> [u32; 4] {
     let [x, y, z] = *voxel_pos;
 
     match *face_normal {
         IVec3::NEG_X => side_aos([
-            voxels[PaddedChunkShape::linearize([x - 1, y, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z - 1]) as usize],
         ]),
         IVec3::X => side_aos([
-            voxels[PaddedChunkShape::linearize([x + 1, y, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z - 1]) as usize],
         ]),
         IVec3::NEG_Y => side_aos([
-            voxels[PaddedChunkShape::linearize([x, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z - 1]) as usize],
         ]),
         IVec3::Y => side_aos([
-            voxels[PaddedChunkShape::linearize([x, y + 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z - 1]) as usize],
         ]),
         IVec3::NEG_Z => side_aos([
-            voxels[PaddedChunkShape::linearize([x - 1, y, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y + 1, z - 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y, z - 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x, y + 1, z - 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z - 1]) as usize],
         ]),
         IVec3::Z => side_aos([
-            voxels[PaddedChunkShape::linearize([x - 1, y, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y - 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x + 1, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x, y + 1, z + 1]) as usize],
-            voxels[PaddedChunkShape::linearize([x - 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y - 1, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y, z + 1]) as usize],
+            voxels[shape.linearize([x + 1, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x, y + 1, z + 1]) as usize],
+            voxels[shape.linearize([x - 1, y + 1, z + 1]) as usize],
         ]),
         _ => unreachable!(),
     }
 }
+
+/// Downsample `voxels` by an integer `factor` along each axis, for meshing a chunk at a lower
+/// level of detail. Each voxel of the result covers a block of `factor` voxels per axis in
+/// `voxels`, and takes the value of the voxel at the min corner of that block. Blocks at the far
+/// edges are cut short when the size of `shape` is not a multiple of `factor`.
+///
+/// Returns the downsampled voxels along with their shape.
+pub fn resample_voxels_nearest<I: PartialEq + Copy>(
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    factor: u32,
+) -> (Vec<WorldVoxel<I>>, RuntimeShape<u32, 3>) {
+    resample_voxels(voxels, shape, factor, |block| block[0])
+}
+
+/// Like `resample_voxels_nearest`, but each voxel of the result takes the most common material
+/// in its block, which keeps thin features and mixed surfaces closer to the full detail chunk.
+/// The result is only air when more than half of the block is air or unset. Ties between
+/// materials go to the one found first, in the linear order of `shape`.
+pub fn resample_voxels_majority<I: PartialEq + Copy>(
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    factor: u32,
+) -> (Vec<WorldVoxel<I>>, RuntimeShape<u32, 3>) {
+    resample_voxels(voxels, shape, factor, |block| {
+        let empty = block
+            .iter()
+            .filter(|voxel| matches!(voxel, WorldVoxel::Air | WorldVoxel::Unset))
+            .count();
+        if empty * 2 > block.len() {
+            return if block.contains(&WorldVoxel::Air) {
+                WorldVoxel::Air
+            } else {
+                WorldVoxel::Unset
+            };
+        }
+
+        let mut counts: Vec<(WorldVoxel<I>, usize)> = Vec::new();
+        for voxel in block
+            .iter()
+            .filter(|voxel| voxel.is_solid() || voxel.is_translucent())
+        {
+            match counts.iter_mut().find(|(counted, _)| counted == voxel) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*voxel, 1)),
+            }
+        }
+        counts
+            .iter()
+            .fold(
+                None,
+                |best: Option<&(WorldVoxel<I>, usize)>, entry| match best {
+                    Some(best) if best.1 >= entry.1 => Some(best),
+                    _ => Some(entry),
+                },
+            )
+            .map(|(voxel, _)| *voxel)
+            .unwrap_or(WorldVoxel::Air)
+    })
+}
+
+fn resample_voxels<I: PartialEq + Copy>(
+    voxels: &crate::chunk::VoxelArray<I>,
+    shape: &RuntimeShape<u32, 3>,
+    factor: u32,
+    pick: impl Fn(&[WorldVoxel<I>]) -> WorldVoxel<I>,
+) -> (Vec<WorldVoxel<I>>, RuntimeShape<u32, 3>) {
+    let factor = factor.max(1);
+    let size = UVec3::from(shape.as_array());
+    let resampled_size = (size + UVec3::splat(factor - 1)) / factor;
+    let resampled_shape = RuntimeShape::<u32, 3>::new(resampled_size.to_array());
+
+    let mut block = Vec::with_capacity((factor * factor * factor) as usize);
+    let resampled = (0..resampled_shape.size())
+        .map(|index| {
+            let min = UVec3::from(resampled_shape.delinearize(index)) * factor;
+            let max = (min + UVec3::splat(factor)).min(size);
+            block.clear();
+            for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        block.push(voxels[shape.linearize([x, y, z]) as usize]);
+                    }
+                }
+            }
+            pick(&block)
+        })
+        .collect();
+
+    (resampled, resampled_shape)
+}
@@ -1,16 +1,20 @@
 use bevy::{
     asset::load_internal_asset,
-    image::{CompressedImageFormats, ImageSampler, ImageType},
+    ecs::schedule::{InternedScheduleLabel, ScheduleLabel},
+    image::{CompressedImageFormats, ImageLoaderSettings, ImageSampler, ImageType},
     pbr::ExtendedMaterial,
     prelude::*,
     render::render_asset::RenderAssetUsages,
+    tasks::{AsyncComputeTaskPool, TaskPoolBuilder},
 };
 
 use crate::{
     configuration::{DefaultWorld, VoxelWorldConfig},
+    diagnostics::{publish_diagnostics, register_diagnostics},
     voxel_material::{
-        prepare_texture, LoadingTexture, StandardVoxelMaterial, TextureLayers,
-        VOXEL_TEXTURE_SHADER_HANDLE,
+        blend_translucent_chunk_meshes, prepare_texture, LoadingTexture, PendingTextures,
+        StandardVoxelMaterial, VOXEL_TEXTURE_SHADER_HANDLE,
+        VOXEL_TRIPLANAR_SHADER_HANDLE,
     },
     voxel_world::*,
     voxel_world_internal::Internals,
@@ -30,6 +34,8 @@ where
 {
     spawn_meshes: bool,
     use_custom_material: bool,
+    schedule: Option<InternedScheduleLabel>,
+    task_pool_threads: Option<usize>,
     config: C,
     material: M,
 }
@@ -43,6 +49,8 @@ where
             config,
             spawn_meshes: true,
             use_custom_material: false,
+            schedule: None,
+            task_pool_threads: None,
             material: StandardMaterial::default(),
         }
     }
@@ -51,6 +59,8 @@ where
         Self {
             spawn_meshes: false,
             use_custom_material: false,
+            schedule: None,
+            task_pool_threads: None,
             config: C::default(),
             material: StandardMaterial::default(),
         }
@@ -75,10 +85,35 @@ where
         VoxelWorldPlugin {
             spawn_meshes: self.spawn_meshes,
             use_custom_material: true,
+            schedule: self.schedule,
+            task_pool_threads: self.task_pool_threads,
             config: self.config,
             material,
         }
     }
+
+    /// Run the internal voxel world systems in the given schedule instead of the defaults.
+    ///
+    /// By default, chunk spawning, despawning and remeshing runs in `PreUpdate`, and meshes are
+    /// spawned in `Update`. With a custom schedule, all of these run in that schedule instead,
+    /// which is useful for running the voxel world on a fixed timestep, eg. in `FixedUpdate`.
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = Some(schedule.intern());
+        self
+    }
+
+    /// Set up the `AsyncComputeTaskPool`, which chunks are generated and meshed on, with the given
+    /// number of threads. More threads help worlds that stream in a lot of chunks at once, while
+    /// fewer threads leave more room for the rest of the app on machines with few cores.
+    ///
+    /// The pool can only be set up once, and `DefaultPlugins` and `MinimalPlugins` set it up when
+    /// they are added. For this to take effect, add this plugin before them, or size the pool
+    /// through `TaskPoolPlugin` instead. A warning is logged if the pool already exists with a
+    /// different number of threads.
+    pub fn configure_task_pool(mut self, threads: usize) -> Self {
+        self.task_pool_threads = Some(threads);
+        self
+    }
 }
 
 impl Default for VoxelWorldPlugin<DefaultWorld, StandardMaterial> {
@@ -86,6 +121,8 @@ impl Default for VoxelWorldPlugin<DefaultWorld, StandardMaterial> {
         Self {
             spawn_meshes: true,
             use_custom_material: false,
+            schedule: None,
+            task_pool_threads: None,
             config: DefaultWorld,
             material: StandardMaterial::default(),
         }
@@ -98,14 +135,44 @@ where
     M: Material,
 {
     fn build(&self, app: &mut App) {
+        let (chunk_schedule, mesh_schedule) = match self.schedule {
+            Some(schedule) => (schedule, schedule),
+            None => (PreUpdate.intern(), Update.intern()),
+        };
+
+        if let Some(threads) = self.task_pool_threads {
+            let task_pool = AsyncComputeTaskPool::get_or_init(|| {
+                TaskPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name("Async Compute Task Pool".to_string())
+                    .build()
+            });
+            if task_pool.thread_num() != threads {
+                warn!(
+                    "AsyncComputeTaskPool was already set up with {} threads, so the {threads} threads configured for the voxel world are not used",
+                    task_pool.thread_num()
+                );
+            }
+        }
+
         app.init_resource::<C>()
             .add_systems(PreStartup, Internals::<C>::setup)
             .add_systems(
-                PreUpdate,
+                chunk_schedule,
                 (
                     (
-                        (Internals::<C>::spawn_chunks, Internals::<C>::retire_chunks)
-                            .chain(),
+                        (
+                            (
+                                Internals::<C>::track_camera_velocity,
+                                Internals::<C>::spawn_chunks,
+                                Internals::<C>::flush_chunk_load_buffer,
+                                Internals::<C>::retire_chunks,
+                            )
+                                .chain(),
+                            Internals::<C>::retry_failed_chunks,
+                            Internals::<C>::remesh_changed_config_chunks,
+                            Internals::<C>::flush_mesh_preload_buffer,
+                        ),
                         Internals::<C>::remesh_dirty_chunks,
                     )
                         .chain(),
@@ -123,11 +190,28 @@ where
             .add_event::<ChunkWillSpawn<C>>()
             .add_event::<ChunkWillDespawn<C>>()
             .add_event::<ChunkWillRemesh<C>>()
-            .add_event::<ChunkWillUpdate<C>>();
+            .add_event::<ChunkWillUpdate<C>>()
+            .add_event::<ChunkInvalidated<C>>()
+            .add_event::<ChunkMeshReady<C>>()
+            .add_event::<ChunkGenerationFailed<C>>();
+
+        register_diagnostics::<C>(app);
+        app.add_systems(
+            chunk_schedule,
+            publish_diagnostics::<C>
+                .after(Internals::<C>::remesh_dirty_chunks)
+                .after(Internals::<C>::flush_chunk_map_buffers),
+        );
 
         // Spawning of meshes is optional, mainly to simplify testing.
         // This makes voxel_world work with a MinimalPlugins setup.
         if self.spawn_meshes {
+            load_internal_asset!(
+                app,
+                VOXEL_TRIPLANAR_SHADER_HANDLE,
+                "shaders/voxel_triplanar.wgsl",
+                Shader::from_wgsl
+            );
             load_internal_asset!(
                 app,
                 VOXEL_TEXTURE_SHADER_HANDLE,
@@ -135,7 +219,12 @@ where
                 Shader::from_wgsl
             );
 
-            app.add_systems(Update, Internals::<C>::spawn_meshes);
+            app.add_systems(
+                mesh_schedule,
+                Internals::<C>::spawn_meshes
+                    .after(Internals::<C>::flush_chunk_map_buffers)
+                    .after(Internals::<C>::flush_mesh_cache_buffers),
+            );
         }
 
         if !self.use_custom_material && self.spawn_meshes {
@@ -150,9 +239,8 @@ where
                 >::default());
             }
 
-            let mut preloaded_texture = true;
             let texture_conf = self.config.voxel_texture();
-            let mut texture_layers = 0;
+            let mut pending_textures = PendingTextures::default();
 
             // Use built-in default texture if no texture is specified.
             let image_handle = if texture_conf.is_none() {
@@ -170,11 +258,26 @@ where
                 image_assets.add(image)
             } else {
                 let (img_path, layers) = texture_conf.unwrap();
-                texture_layers = layers;
                 let asset_server = app.world().get_resource::<AssetServer>().unwrap();
-                preloaded_texture = false;
-                asset_server.load(img_path)
+                let handle = asset_server.load(img_path);
+                pending_textures.push((handle.clone(), layers));
+                handle
+            };
+
+            // Normal and ORM maps hold data rather than colors, so they are loaded as linear
+            let mut load_linear_texture = |texture_conf: Option<(String, u32)>| {
+                let (img_path, layers) = texture_conf?;
+                let asset_server = app.world().get_resource::<AssetServer>().unwrap();
+                let handle: Handle<Image> = asset_server.load_with_settings(
+                    img_path,
+                    |settings: &mut ImageLoaderSettings| settings.is_srgb = false,
+                );
+                pending_textures.push((handle.clone(), layers));
+                Some(handle)
             };
+            let normal_texture = load_linear_texture(self.config.voxel_normal_texture());
+            let metallic_roughness_texture =
+                load_linear_texture(self.config.voxel_orm_texture());
 
             let mut material_assets = app
                 .world_mut()
@@ -186,23 +289,33 @@ where
                     reflectance: 0.05,
                     metallic: 0.05,
                     perceptual_roughness: 0.95,
+                    alpha_mode: self
+                        .config
+                        .material_alpha_mode()
+                        .unwrap_or(AlphaMode::Opaque),
                     ..default()
                 },
                 extension: StandardVoxelMaterial {
                     voxels_texture: image_handle.clone(),
+                    normal_texture,
+                    metallic_roughness_texture,
+                    triplanar: self.config.triplanar_texturing(),
                 },
             });
 
             app.insert_resource(LoadingTexture {
-                is_loaded: preloaded_texture,
+                is_loaded: pending_textures.is_empty(),
                 handle: image_handle,
             });
             app.insert_resource(VoxelWorldMaterialHandle { handle: mat_handle });
-            app.insert_resource(TextureLayers(texture_layers));
+            app.insert_resource(pending_textures);
 
             app.insert_resource(self.config.clone());
 
             app.add_systems(Update, prepare_texture);
+            if self.config.material_alpha_mode().is_none() {
+                app.add_systems(Update, blend_translucent_chunk_meshes::<C>);
+            }
 
             app.add_systems(
                 Update,
@@ -5,6 +5,7 @@
     view_transformations::position_world_to_clip
 }
 #import bevy_render::instance_index::get_instance_index
+#import bevy_voxel_world::triplanar::triplanar_sample
 
 #ifdef PREPASS_PIPELINE
 #import bevy_pbr::{
@@ -24,6 +25,18 @@ var mat_array_texture: texture_2d_array<f32>;
 @group(2) @binding(101)
 var mat_array_texture_sampler: sampler;
 
+@group(2) @binding(102)
+var normal_array_texture: texture_2d_array<f32>;
+
+@group(2) @binding(103)
+var normal_array_texture_sampler: sampler;
+
+@group(2) @binding(104)
+var orm_array_texture: texture_2d_array<f32>;
+
+@group(2) @binding(105)
+var orm_array_texture_sampler: sampler;
+
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
 #ifdef VERTEX_POSITIONS
@@ -52,7 +65,10 @@ struct Vertex {
     @builtin(vertex_index) index: u32,
 #endif
 
-    @location(8) tex_idx: vec3<u32>
+    @location(8) tex_idx: vec3<u32>,
+#ifdef VOXEL_LIGHT
+    @location(9) light: f32,
+#endif
 };
 
 struct CustomVertexOutput {
@@ -76,6 +92,13 @@ struct CustomVertexOutput {
 #endif
 
     @location(8) tex_idx: vec3<u32>,
+#ifdef VOXEL_LIGHT
+    @location(9) light: f32,
+#endif
+#ifdef VOXEL_TRIPLANAR
+    // The position in the mesh, which is in voxel units
+    @location(10) local_position: vec3<f32>,
+#endif
 }
 
 @vertex
@@ -111,9 +134,34 @@ fn vertex(vertex: Vertex) -> CustomVertexOutput {
 
     out.tex_idx = vertex.tex_idx;
 
+#ifdef VOXEL_LIGHT
+    out.light = vertex.light;
+#endif
+
+#ifdef VOXEL_TRIPLANAR
+    out.local_position = vertex.position;
+#endif
+
     return out;
 }
 
+// Applies a tangent space normal to the surface normal, with the tangent frame worked out from
+// the screen space derivatives of the position and UVs, since chunk meshes have no tangents
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, tangent_normal: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+
+    let dp2perp = cross(dp2, normal);
+    let dp1perp = cross(normal, dp1);
+    let tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    let bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    let scale = inverseSqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+
+    return normalize(mat3x3<f32>(tangent * scale, bitangent * scale, normal) * tangent_normal);
+}
+
 @fragment
 fn fragment(
     in: CustomVertexOutput,
@@ -137,9 +185,35 @@ fn fragment(
         tex_face = 2;
     }
 
+#ifdef VOXEL_TRIPLANAR
+    pbr_input.material.base_color = triplanar_sample(mat_array_texture, mat_array_texture_sampler, in.local_position, in.world_normal, in.tex_idx);
+#else
     pbr_input.material.base_color = textureSample(mat_array_texture, mat_array_texture_sampler, in.uv, in.tex_idx[tex_face]);
+#endif
     pbr_input.material.base_color = pbr_input.material.base_color * in.color;
 
+#ifdef VOXEL_ORM_MAP
+#ifdef VOXEL_TRIPLANAR
+    let orm = triplanar_sample(orm_array_texture, orm_array_texture_sampler, in.local_position, in.world_normal, in.tex_idx);
+#else
+    let orm = textureSample(orm_array_texture, orm_array_texture_sampler, in.uv, in.tex_idx[tex_face]);
+#endif
+    pbr_input.diffuse_occlusion = pbr_input.diffuse_occlusion * orm.r;
+    pbr_input.material.perceptual_roughness = orm.g;
+    pbr_input.material.metallic = orm.b;
+#endif
+
+#ifdef VOXEL_NORMAL_MAP
+#ifndef VOXEL_TRIPLANAR
+    let tangent_normal = textureSample(normal_array_texture, normal_array_texture_sampler, in.uv, in.tex_idx[tex_face]).xyz * 2.0 - 1.0;
+    pbr_input.N = perturb_normal(pbr_input.N, in.world_position.xyz, in.uv, tangent_normal);
+#endif
+#endif
+
+#ifdef VOXEL_LIGHT
+    pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.rgb * in.light, pbr_input.material.base_color.a);
+#endif
+
     pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
 
 #ifdef PREPASS_PIPELINE
@@ -0,0 +1,28 @@
+#define_import_path bevy_voxel_world::triplanar
+
+// Samples a texture array with triplanar projection. `position` is in voxel units, so the
+// texture repeats once per voxel. The layers are the ones from the texture index mapper: the top
+// layer is used for surfaces facing up, the bottom layer for surfaces facing down, and the side
+// layer for the projections along x and z.
+fn triplanar_sample(
+    array_texture: texture_2d_array<f32>,
+    array_sampler: sampler,
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    layers: vec3<u32>,
+) -> vec4<f32> {
+    // Sharpen the blend, so that most of the surface uses a single projection
+    var weights = pow(abs(normal), vec3<f32>(4.0));
+    weights = weights / (weights.x + weights.y + weights.z);
+
+    var y_layer = layers[0];
+    if normal.y < 0.0 {
+        y_layer = layers[2];
+    }
+
+    let x_sample = textureSample(array_texture, array_sampler, fract(vec2<f32>(position.z, -position.y)), layers[1]);
+    let y_sample = textureSample(array_texture, array_sampler, fract(position.xz), y_layer);
+    let z_sample = textureSample(array_texture, array_sampler, fract(vec2<f32>(position.x, -position.y)), layers[1]);
+
+    return x_sample * weights.x + y_sample * weights.y + z_sample * weights.z;
+}
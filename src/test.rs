@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use crate::chunk_map::ChunkMapUpdateBuffer;
 use crate::mesh_cache::MeshCacheInsertBuffer;
 use crate::prelude::*;
+use crate::test_util::*;
 use crate::voxel_traversal::voxel_line_traversal;
 use crate::{
     chunk::{ChunkData, FillType},
@@ -103,11 +104,11 @@ fn set_voxel_can_be_found_by_2d_coordinate() {
     app.update();
 }
 
-// ChunkWillSpawn event now fires from the mesh spawning system, which cannot run in tests.
-#[ignore]
 #[test]
 fn chunk_will_spawn_events() {
-    let mut app = _test_setup_app();
+    let mut app = spawn_test_app::<DefaultWorld>();
+
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 1, 1000));
 
     app.add_systems(
         Update,
@@ -189,8 +190,12 @@ fn chunk_will_update_event() {
     app.add_systems(
         Update,
         |mut ev_chunk_will_update: EventReader<ChunkWillUpdate<DefaultWorld>>| {
-            let count = ev_chunk_will_update.read().count();
-            assert!(count > 0)
+            let events = ev_chunk_will_update.read().collect::<Vec<_>>();
+            assert!(!events.is_empty());
+            for event in events {
+                assert_eq!(event.chunk_key, IVec3::ZERO);
+                assert_eq!(event.changed, vec![IVec3::ZERO]);
+            }
         },
     );
 
@@ -230,13 +235,18 @@ fn raycast_finds_voxel() {
                 IVec3::new(0, 0, 0),
                 ChunkData {
                     position: IVec3::new(0, 0, 0),
-                    voxels: Some(std::sync::Arc::new([WorldVoxel::Unset; 39304])),
+                    voxels: Some(std::sync::Arc::from(vec![WorldVoxel::Unset; 39304])),
+                    palette_voxels: None,
+                    sparse_voxels: None,
                     voxels_hash: 0,
+                    mesh_variant: None,
                     is_full: false,
                     is_empty: false,
                     fill_type: FillType::Mixed,
+                    materials: Vec::new(),
                     entity: Entity::PLACEHOLDER,
                     has_generated: false,
+                    chunk_size: 32,
                 },
                 ChunkWillSpawn::<DefaultWorld>::new(
                     IVec3::new(0, 0, 0),
@@ -266,6 +276,7 @@ fn raycast_finds_voxel() {
                 position: Vec3::ZERO,
                 normal: Some(Vec3::new(0.0, 0.0, 1.0)),
                 voxel: test_voxel,
+                voxel_coords: IVec3::ZERO,
             }
         )
     });
@@ -526,3 +537,5680 @@ fn can_get_chunk_data() {
 
     app.update();
 }
+
+#[test]
+fn can_export_and_import_modified_voxels() {
+    let mut app = _test_setup_app();
+
+    app.add_systems(Update, |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        voxel_world.set_voxel(IVec3::new(0, 0, 0), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(-5, 3, 7), WorldVoxel::Air);
+    });
+
+    app.update();
+
+    let exported = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let exported_write = exported.clone();
+
+    app.add_systems(Update, move |voxel_world: VoxelWorld<DefaultWorld>| {
+        *exported_write.lock().unwrap() = voxel_world.export_modified();
+    });
+
+    app.update();
+
+    let mut data = exported.lock().unwrap().clone();
+    data.sort_by_key(|(pos, _)| pos.to_array());
+    assert_eq!(
+        data,
+        vec![
+            (IVec3::new(-5, 3, 7), WorldVoxel::Air),
+            (IVec3::new(0, 0, 0), WorldVoxel::Solid(1)),
+        ]
+    );
+
+    // Import into a fresh world before any chunk has been spawned
+    let mut app = _test_setup_app();
+
+    app.add_systems(Startup, move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        voxel_world.import_modified(data.clone());
+    });
+
+    app.update();
+
+    app.add_systems(Update, |voxel_world: VoxelWorld<DefaultWorld>| {
+        assert_eq!(
+            voxel_world.get_voxel(IVec3::new(0, 0, 0)),
+            WorldVoxel::Solid(1)
+        );
+        assert_eq!(voxel_world.get_voxel(IVec3::new(-5, 3, 7)), WorldVoxel::Air);
+    });
+
+    app.update();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn exported_modified_voxels_survive_a_serde_round_trip() {
+    let data = vec![
+        (IVec3::new(-5, 3, 7), WorldVoxel::Air),
+        (IVec3::new(0, 0, 0), WorldVoxel::Solid(1)),
+        (IVec3::new(2, -9, 4), WorldVoxel::Translucent(2)),
+        (IVec3::new(1, 1, 1), WorldVoxel::Unset),
+    ];
+
+    let json = serde_json::to_string(&data).unwrap();
+    let decoded: Vec<(IVec3, WorldVoxel<u8>)> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, data);
+
+    let mut app = _test_setup_app();
+
+    app.add_systems(Startup, move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        voxel_world.import_modified(decoded.clone());
+    });
+
+    app.update();
+
+    app.add_systems(Update, |voxel_world: VoxelWorld<DefaultWorld>| {
+        assert_eq!(
+            voxel_world.get_voxel(IVec3::new(0, 0, 0)),
+            WorldVoxel::Solid(1)
+        );
+        assert_eq!(
+            voxel_world.get_voxel(IVec3::new(2, -9, 4)),
+            WorldVoxel::Translucent(2)
+        );
+    });
+
+    app.update();
+}
+
+#[test]
+fn force_chunk_into_map_generates_chunk() {
+    let mut app = spawn_test_app::<DefaultWorld>();
+
+    let chunk_pos = IVec3::new(100, 0, 100);
+    force_chunk_into_map::<DefaultWorld>(&mut app, chunk_pos);
+
+    app.update();
+
+    app.add_systems(Update, move |voxel_world: VoxelWorld<DefaultWorld>| {
+        let chunk_data = voxel_world.get_chunk_data(chunk_pos).unwrap();
+        assert!(chunk_data.has_generated());
+        assert!(chunk_data.is_empty());
+    });
+
+    app.update();
+}
+
+#[derive(Resource, Clone, Default)]
+struct SmallChunkWorld;
+
+impl VoxelWorldConfig for SmallChunkWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn chunk_size(&self) -> u32 {
+        16
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct LargeChunkWorld;
+
+impl VoxelWorldConfig for LargeChunkWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn chunk_size(&self) -> u32 {
+        64
+    }
+}
+
+fn set_and_check_voxels_in_chunks<C: VoxelWorldConfig<MaterialIndex = u8>>(
+    app: &mut App,
+) {
+    let positions = [
+        IVec3::new(0, 0, 0),
+        IVec3::new(17, -3, 40),
+        IVec3::new(-1, -1, -1),
+        IVec3::new(70, 5, -65),
+    ];
+
+    app.add_systems(Update, move |mut voxel_world: VoxelWorld<C>| {
+        for pos in positions {
+            voxel_world.set_voxel(pos, WorldVoxel::Solid(1));
+        }
+    });
+
+    // The writes are flushed at the start of the next frame, before the chunks are generated
+    app.update();
+    app.update();
+
+    let chunk_size = app.world().resource::<C>().chunk_size();
+    for pos in positions {
+        let (chunk_pos, _) = get_chunk_voxel_position_for_size(pos, chunk_size);
+        force_chunk_into_map::<C>(app, chunk_pos);
+    }
+
+    app.update();
+
+    app.add_systems(Update, move |voxel_world: VoxelWorld<C>| {
+        for pos in positions {
+            let (chunk_pos, vox_pos) = get_chunk_voxel_position_for_size(pos, chunk_size);
+            let chunk_data = voxel_world.get_chunk_data(chunk_pos).unwrap();
+            assert_eq!(chunk_data.chunk_size(), chunk_size);
+            assert_eq!(chunk_data.get_voxel(vox_pos), WorldVoxel::Solid(1));
+            assert_eq!(voxel_world.get_voxel(pos), WorldVoxel::Solid(1));
+        }
+    });
+
+    app.update();
+}
+
+#[test]
+fn worlds_with_different_chunk_sizes() {
+    let mut app = spawn_test_app::<SmallChunkWorld>();
+    app.add_plugins(VoxelWorldPlugin::<LargeChunkWorld>::minimal());
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((
+            Camera3d::default(),
+            Transform::from_xyz(10.0, 10.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            VoxelWorldCamera::<LargeChunkWorld>::default(),
+        ));
+    });
+
+    set_and_check_voxels_in_chunks::<SmallChunkWorld>(&mut app);
+    set_and_check_voxels_in_chunks::<LargeChunkWorld>(&mut app);
+}
+
+#[test]
+fn systems_can_run_in_fixed_update() {
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<DefaultWorld>::minimal().in_schedule(FixedUpdate),
+    ));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((
+            Camera3d::default(),
+            Transform::from_xyz(10.0, 10.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            VoxelWorldCamera::<DefaultWorld>::default(),
+        ));
+    });
+
+    let chunk_count = |app: &mut App| {
+        app.world_mut()
+            .query::<&Chunk<DefaultWorld>>()
+            .iter(app.world())
+            .count()
+    };
+
+    // Time is not advancing, so no fixed ticks should run
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_eq!(chunk_count(&mut app), 0);
+
+    // Advance time so that the fixed timestep runs
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        50,
+    )));
+    for _ in 0..5 {
+        app.update();
+    }
+    assert!(chunk_count(&mut app) > 0);
+}
+
+#[test]
+fn collision_vertices_for_single_voxel() {
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos == IVec3::ZERO {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Unset
+        }
+    });
+
+    let (positions, triangles) = chunk_task.chunk_data.collision_vertices();
+
+    // A single cube has 8 unique corners and two triangles on each of its 6 sides
+    assert_eq!(positions.len(), 8);
+    assert_eq!(triangles.len(), 12);
+    for position in positions {
+        assert!(
+            position.cmpge(Vec3::ONE).all() && position.cmple(Vec3::splat(2.0)).all()
+        );
+    }
+
+    let empty_task = generated_task::<DefaultWorld>(IVec3::ZERO, |_| WorldVoxel::Unset);
+    let (positions, triangles) = empty_task.chunk_data.collision_vertices();
+    assert!(positions.is_empty() && triangles.is_empty());
+
+    let full_task = generated_task::<DefaultWorld>(IVec3::ZERO, |_| WorldVoxel::Solid(0));
+    let (positions, triangles) = full_task.chunk_data.collision_vertices();
+    assert!(positions.is_empty() && triangles.is_empty());
+}
+
+#[derive(Resource, Clone, Default)]
+struct CollisionWorld;
+
+impl VoxelWorldConfig for CollisionWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        1
+    }
+
+    fn generate_collision_data(&self) -> bool {
+        true
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn collision_data_is_inserted_on_chunks() {
+    let mut app = spawn_test_app::<CollisionWorld>();
+
+    assert!(run_until(&mut app, 1000, |app| {
+        app.world_mut()
+            .query::<(&Chunk<CollisionWorld>, &ChunkCollisionData)>()
+            .iter(app.world())
+            .any(|(_, collision_data)| !collision_data.is_empty())
+    }));
+}
+
+#[test]
+fn material_alpha_reaches_vertex_colors() {
+    use crate::meshing::generate_chunk_mesh_for_shape;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match pos {
+        IVec3 { x: 0, y: 0, z: 0 } => WorldVoxel::Solid(0),
+        IVec3 { x: 5, y: 0, z: 0 } => WorldVoxel::Solid(1),
+        _ => WorldVoxel::Unset,
+    });
+
+    let mesh = generate_chunk_mesh_for_shape(
+        chunk_task.chunk_data.voxels.clone().unwrap(),
+        IVec3::ZERO,
+        crate::chunk::padded_chunk_shape_uniform(32),
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+        std::sync::Arc::new(|mat: u8| if mat == 1 { 0.5 } else { 1.0 }),
+    );
+
+    let Some(VertexAttributeValues::Float32x4(colors)) =
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+    else {
+        panic!("mesh has no vertex colors");
+    };
+    let Some(VertexAttributeValues::Uint32x3(tex_indices)) =
+        mesh.attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+    else {
+        panic!("mesh has no texture indices");
+    };
+
+    assert_eq!(colors.len(), 48);
+    for (color, tex_index) in colors.iter().zip(tex_indices) {
+        let expected_alpha = if tex_index[0] == 1 { 0.5 } else { 1.0 };
+        assert_eq!(color[3], expected_alpha);
+    }
+}
+
+#[test]
+fn default_material_blends_once_a_translucent_chunk_is_meshed() {
+    use crate::mesh_cache::MeshRef;
+    use crate::meshing::generate_chunk_mesh_for_shape;
+    use crate::plugin::VoxelWorldMaterialHandle;
+    use crate::voxel_material::{blend_translucent_chunk_meshes, StandardVoxelMaterial};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::pbr::ExtendedMaterial;
+
+    type VoxelMaterial = ExtendedMaterial<StandardMaterial, StandardVoxelMaterial>;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match pos {
+        IVec3 { x: 0, y: 0, z: 0 } => WorldVoxel::Solid(0),
+        IVec3 { x: 5, y: 0, z: 0 } => WorldVoxel::Translucent(1),
+        _ => WorldVoxel::Unset,
+    });
+    let mesh_with_alpha = |alpha: f32| {
+        generate_chunk_mesh_for_shape(
+            chunk_task.chunk_data.voxels.clone().unwrap(),
+            IVec3::ZERO,
+            crate::chunk::padded_chunk_shape_uniform(32),
+            std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+            std::sync::Arc::new(move |mat: u8| if mat == 1 { alpha } else { 1.0 }),
+        )
+    };
+
+    let mut app = App::new();
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<VoxelMaterial>>();
+    let handle =
+        app.world_mut()
+            .resource_mut::<Assets<VoxelMaterial>>()
+            .add(VoxelMaterial {
+                base: StandardMaterial::default(),
+                extension: StandardVoxelMaterial {
+                    voxels_texture: Handle::default(),
+                    normal_texture: None,
+                    metallic_roughness_texture: None,
+                    triplanar: false,
+                },
+            });
+    app.insert_resource(VoxelWorldMaterialHandle {
+        handle: handle.clone(),
+    });
+
+    let mesh_chunk = |app: &mut App, mesh: Mesh| {
+        let mesh_handle = app.world_mut().resource_mut::<Assets<Mesh>>().add(mesh);
+        let entity = app.world_mut().spawn_empty().id();
+        app.world_mut().entity_mut(entity).insert((
+            Chunk::<DefaultWorld>::new(IVec3::ZERO, entity, 32),
+            MeshRef(std::sync::Arc::new(mesh_handle)),
+        ));
+        app.world_mut()
+            .run_system_once(blend_translucent_chunk_meshes::<DefaultWorld>)
+            .unwrap();
+        app.world()
+            .resource::<Assets<VoxelMaterial>>()
+            .get(&handle)
+            .unwrap()
+            .base
+            .alpha_mode
+    };
+
+    // Translucent voxels that are fully opaque keep the material opaque
+    assert_eq!(
+        mesh_chunk(&mut app, mesh_with_alpha(1.0)),
+        AlphaMode::Opaque
+    );
+    assert_eq!(mesh_chunk(&mut app, mesh_with_alpha(0.5)), AlphaMode::Blend);
+}
+
+#[test]
+fn is_texture_loaded_reflects_loading_state() {
+    use crate::voxel_material::LoadingTexture;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let is_texture_loaded = |app: &mut App| {
+        app.world_mut()
+            .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+                voxel_world.is_texture_loaded()
+            })
+            .unwrap()
+    };
+
+    let mut app = _test_setup_app();
+    app.update();
+
+    // No texture to wait for
+    assert!(is_texture_loaded(&mut app));
+
+    app.insert_resource(LoadingTexture {
+        is_loaded: false,
+        handle: Handle::default(),
+    });
+    app.update();
+    assert!(!is_texture_loaded(&mut app));
+
+    // Mark the texture as loaded, like prepare_texture does once the image is available
+    app.world_mut().resource_mut::<LoadingTexture>().is_loaded = true;
+    app.update();
+    assert!(is_texture_loaded(&mut app));
+}
+
+#[test]
+fn raycast_all_finds_all_voxels_in_order() {
+    let mut app = _test_setup_app();
+
+    app.add_systems(Update, |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        voxel_world.set_voxel(IVec3::new(0, 0, 0), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(0, 0, 5), WorldVoxel::Solid(2));
+        voxel_world.set_voxel(IVec3::new(0, 0, 10), WorldVoxel::Solid(3));
+    });
+
+    app.update();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.add_systems(Update, |voxel_world: VoxelWorld<DefaultWorld>| {
+        let ray = Ray3d {
+            origin: Vec3::new(0.5, 0.5, 70.0),
+            direction: -Dir3::Z,
+        };
+
+        let results = voxel_world.raycast_all(ray, 100.0, &|(_pos, _vox)| true);
+        let expected = [(10.0, 3), (5.0, 2), (0.0, 1)]
+            .map(|(z, mat)| VoxelRaycastResult {
+                position: Vec3::new(0.0, 0.0, z),
+                normal: Some(Vec3::Z),
+                voxel: WorldVoxel::Solid(mat),
+                voxel_coords: IVec3::new(0, 0, z as i32),
+            })
+            .to_vec();
+        assert_eq!(results, expected);
+
+        // Only the closest voxel is within range
+        let results = voxel_world.raycast_all(ray, 62.0, &|(_pos, _vox)| true);
+        assert_eq!(results, expected[..1]);
+
+        // Filtered voxels are skipped
+        let results = voxel_world.raycast_all(ray, 100.0, &|(pos, _vox)| pos.z != 5.0);
+        assert_eq!(results, [expected[0].clone(), expected[2].clone()]);
+    });
+
+    app.update();
+}
+
+#[derive(Resource, Clone, Default)]
+struct FlakyWorld {
+    attempts: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl VoxelWorldConfig for FlakyWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        1
+    }
+
+    fn generation_retry_backoff(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        let attempts = self.attempts.clone();
+        Box::new(move |chunk_pos| {
+            // Generation of the chunk at the origin fails the first two times
+            let fail = chunk_pos == IVec3::ZERO
+                && attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2;
+            Box::new(move |pos| {
+                if fail {
+                    panic!("transient generation failure");
+                }
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn failed_chunk_generation_is_retried() {
+    let mut app = spawn_test_app::<FlakyWorld>();
+
+    let generated = run_until(&mut app, 1000, |app| {
+        let chunk_map = app
+            .world()
+            .resource::<crate::chunk_map::ChunkMap<FlakyWorld, u8>>();
+        chunk_map
+            .get_read_lock()
+            .get(&IVec3::ZERO)
+            .is_some_and(|chunk_data| chunk_data.has_generated())
+    });
+
+    assert!(generated);
+    let attempts = app.world().resource::<FlakyWorld>().attempts.clone();
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+    let retrying = app
+        .world_mut()
+        .query::<&ChunkGenerationRetry>()
+        .iter(app.world())
+        .count();
+    assert_eq!(retrying, 0);
+}
+
+#[test]
+fn greedy_meshing_merges_faces_of_same_material() {
+    use crate::custom_meshing::generate_chunk_mesh_greedy;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match pos {
+        // Two voxels of the same material get merged into a single box
+        IVec3 {
+            x: 0..=1,
+            y: 0,
+            z: 0,
+        } => WorldVoxel::Solid(0),
+        // Two voxels of different materials keep their shared boundary
+        IVec3 { x: 5, y: 0, z: 0 } => WorldVoxel::Solid(1),
+        IVec3 { x: 6, y: 0, z: 0 } => WorldVoxel::Solid(2),
+        _ => WorldVoxel::Unset,
+    });
+
+    let mesh = generate_chunk_mesh_greedy(
+        chunk_task.chunk_data.voxels.clone().unwrap(),
+        IVec3::ZERO,
+        crate::chunk::padded_chunk_shape_uniform(32),
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+        std::sync::Arc::new(|mat: u8| if mat == 2 { 0.5 } else { 1.0 }),
+    );
+
+    let Some(VertexAttributeValues::Uint32x3(tex_indices)) =
+        mesh.attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+    else {
+        panic!("mesh has no texture indices");
+    };
+    let Some(VertexAttributeValues::Float32x4(colors)) =
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+    else {
+        panic!("mesh has no vertex colors");
+    };
+
+    // 6 quads for the merged box, and 5 quads for each of the two different voxels
+    assert_eq!(tex_indices.len(), (6 + 5 + 5) * 4);
+    assert_eq!(colors.len(), tex_indices.len());
+    for mat in 0..3 {
+        let quads = tex_indices.iter().filter(|idx| idx[0] == mat).count() / 4;
+        assert_eq!(quads, if mat == 0 { 6 } else { 5 });
+    }
+    for (color, tex_index) in colors.iter().zip(tex_indices) {
+        let expected_alpha = if tex_index[0] == 2 { 0.5 } else { 1.0 };
+        assert_eq!(*color, [1.0, 1.0, 1.0, expected_alpha]);
+    }
+}
+
+#[test]
+fn surface_distances_are_larger_inside_flat_surfaces() {
+    use crate::chunk::padded_chunk_shape_uniform;
+    use crate::custom_meshing::{add_surface_distances, generate_chunk_mesh};
+    use crate::rendering::ATTRIBUTE_SURFACE_DISTANCE;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match pos {
+        IVec3 {
+            x: 4..=19,
+            y: 0..=9,
+            z: 4..=19,
+        } => WorldVoxel::Solid(0),
+        _ => WorldVoxel::Air,
+    });
+
+    let voxels = chunk_task.chunk_data.voxels.clone().unwrap();
+    let mut mesh = generate_chunk_mesh(
+        voxels.clone(),
+        IVec3::ZERO,
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+    );
+    add_surface_distances(&mut mesh, &voxels, &padded_chunk_shape_uniform(32), 8);
+
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("mesh has no positions");
+    };
+    let Some(VertexAttributeValues::Float32(distances)) =
+        mesh.attribute(ATTRIBUTE_SURFACE_DISTANCE)
+    else {
+        panic!("mesh has no surface distances");
+    };
+    assert_eq!(positions.len(), distances.len());
+
+    // Mesh positions are in padded voxel space, so the top of the box is at y = 11
+    let distance_at = |position: [f32; 3]| {
+        positions
+            .iter()
+            .zip(distances)
+            .find(|(vertex, _)| **vertex == position)
+            .map(|(_, distance)| *distance)
+            .unwrap()
+    };
+    let interior = distance_at([12.0, 11.0, 12.0]);
+    let edge = distance_at([5.0, 11.0, 12.0]);
+    let corner = distance_at([5.0, 11.0, 5.0]);
+    assert!(interior > edge);
+    assert!(edge > corner);
+}
+
+#[test]
+fn voxel_light_is_smoothed_across_vertices() {
+    use crate::chunk::padded_chunk_shape_uniform;
+    use crate::custom_meshing::{add_voxel_light, generate_chunk_mesh};
+    use crate::rendering::ATTRIBUTE_VOXEL_LIGHT;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos.y < 10 {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+
+    let voxels = chunk_task.chunk_data.voxels.clone().unwrap();
+    let mut mesh = generate_chunk_mesh(
+        voxels.clone(),
+        IVec3::ZERO,
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+    );
+    // Dark on one side of x = 8, lit on the other
+    add_voxel_light(
+        &mut mesh,
+        &voxels,
+        &padded_chunk_shape_uniform(32),
+        IVec3::splat(-1),
+        std::sync::Arc::new(|pos: IVec3, voxel: &WorldVoxel<u8>| {
+            assert!(voxel.is_air());
+            if pos.x < 8 {
+                0
+            } else {
+                255
+            }
+        }),
+    );
+
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("mesh has no positions");
+    };
+    let Some(VertexAttributeValues::Float32(lights)) =
+        mesh.attribute(ATTRIBUTE_VOXEL_LIGHT)
+    else {
+        panic!("mesh has no voxel light");
+    };
+    assert_eq!(positions.len(), lights.len());
+
+    // Mesh positions are in padded voxel space, so world x = 8 is at x = 9
+    let light_at = |position: [f32; 3]| {
+        positions
+            .iter()
+            .zip(lights)
+            .find(|(vertex, _)| **vertex == position)
+            .map(|(_, light)| *light)
+            .unwrap()
+    };
+    assert_eq!(light_at([3.0, 11.0, 12.0]), 0.0);
+    assert_eq!(light_at([9.0, 11.0, 12.0]), 0.5);
+    assert_eq!(light_at([20.0, 11.0, 12.0]), 1.0);
+}
+
+#[derive(Resource, Clone, Default)]
+struct LayeredWorld;
+
+impl VoxelWorldConfig for LayeredWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        1
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| match pos.y {
+                0.. => WorldVoxel::Unset,
+                -1 => WorldVoxel::Solid(1),
+                -2 => WorldVoxel::Solid(2),
+                -3 => WorldVoxel::Air,
+                _ => WorldVoxel::Solid(3),
+            })
+        })
+    }
+}
+
+#[test]
+fn raycast_xray_samples_all_voxels() {
+    let mut app = spawn_test_app::<LayeredWorld>();
+
+    force_chunk_into_map::<LayeredWorld>(&mut app, IVec3::new(0, -1, 0));
+    app.update();
+
+    app.add_systems(Update, |voxel_world: VoxelWorld<LayeredWorld>| {
+        let ray = Ray3d {
+            origin: Vec3::new(0.5, 1.5, 0.5),
+            direction: -Dir3::Y,
+        };
+
+        let samples = voxel_world.raycast_xray(ray, 6.0);
+        let sequence = samples
+            .iter()
+            .map(|sample| (sample.voxel_pos().y, sample.voxel))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            sequence,
+            vec![
+                (1, WorldVoxel::Unset),
+                (0, WorldVoxel::Unset),
+                (-1, WorldVoxel::Solid(1)),
+                (-2, WorldVoxel::Solid(2)),
+                (-3, WorldVoxel::Air),
+                (-4, WorldVoxel::Solid(3)),
+            ]
+        );
+    });
+
+    app.update();
+}
+
+#[test]
+fn translucent_voxels_show_neighbouring_faces() {
+    use crate::meshing::generate_chunk_mesh_for_shape;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task =
+        generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match (pos.x, pos.y, pos.z) {
+            // Translucent voxels of different materials
+            (0, 0, 0) => WorldVoxel::Translucent(1),
+            (1, 0, 0) => WorldVoxel::Translucent(2),
+            // Translucent voxels of the same material
+            (5, 0, 0) | (6, 0, 0) => WorldVoxel::Translucent(3),
+            // A solid voxel next to a translucent voxel
+            (10, 0, 0) => WorldVoxel::Solid(0),
+            (11, 0, 0) => WorldVoxel::Translucent(4),
+            _ => WorldVoxel::Unset,
+        });
+
+    let mesh = generate_chunk_mesh_for_shape(
+        chunk_task.chunk_data.voxels.clone().unwrap(),
+        IVec3::ZERO,
+        crate::chunk::padded_chunk_shape_uniform(32),
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+        std::sync::Arc::new(|_| 1.0),
+    );
+
+    let Some(VertexAttributeValues::Uint32x3(tex_indices)) =
+        mesh.attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+    else {
+        panic!("mesh has no texture indices");
+    };
+
+    let quad_count =
+        |mat: u32| tex_indices.iter().filter(|idx| idx[0] == mat).count() / 4;
+
+    // Faces between different translucent materials are visible from both sides
+    assert_eq!(quad_count(1), 6);
+    assert_eq!(quad_count(2), 6);
+    // Faces between translucent voxels of the same material are hidden
+    assert_eq!(quad_count(3), 10);
+    // The solid voxel can be seen through the translucent one, but not the other way around
+    assert_eq!(quad_count(0), 6);
+    assert_eq!(quad_count(4), 5);
+}
+
+#[derive(Resource, Clone, Default)]
+struct MinCornerWorld;
+
+impl VoxelWorldConfig for MinCornerWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn chunk_origin_convention(&self) -> ChunkOriginConvention {
+        ChunkOriginConvention::MinCorner
+    }
+}
+
+fn assert_chunk_transforms<C: VoxelWorldConfig>(offset: Vec3) {
+    let mut app = spawn_test_app::<C>();
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let chunk_size = app.world().resource::<C>().chunk_size() as f32;
+    let mut chunks = app.world_mut().query::<(&Chunk<C>, &Transform)>();
+    let mut count = 0;
+    for (chunk, transform) in chunks.iter(app.world()) {
+        assert_eq!(
+            transform.translation,
+            chunk.position.as_vec3() * chunk_size + offset
+        );
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn chunk_transform_follows_origin_convention() {
+    assert_chunk_transforms::<DefaultWorld>(Vec3::splat(-1.0));
+    assert_chunk_transforms::<MinCornerWorld>(Vec3::ZERO);
+}
+
+#[test]
+fn texture_index_is_picked_per_face() {
+    use crate::meshing::generate_chunk_mesh_for_shape;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos == IVec3::ZERO {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Unset
+        }
+    });
+
+    let mesh = generate_chunk_mesh_for_shape(
+        chunk_task.chunk_data.voxels.clone().unwrap(),
+        IVec3::ZERO,
+        crate::chunk::padded_chunk_shape_uniform(32),
+        std::sync::Arc::new(|_| [0, 1, 2]),
+        std::sync::Arc::new(|_| 1.0),
+    );
+
+    let Some(VertexAttributeValues::Uint32x3(tex_indices)) =
+        mesh.attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+    else {
+        panic!("mesh has no texture indices");
+    };
+    let Some(VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        panic!("mesh has no normals");
+    };
+
+    assert_eq!(tex_indices.len(), 24);
+    let mut side_vertices = 0;
+    for (tex_index, normal) in tex_indices.iter().zip(normals) {
+        let expected = if normal[1] > 0.0 {
+            0
+        } else if normal[1] < 0.0 {
+            2
+        } else {
+            side_vertices += 1;
+            1
+        };
+        assert_eq!(*tex_index, [expected; 3]);
+    }
+    assert_eq!(side_vertices, 16);
+}
+
+#[derive(Resource, Clone, Default)]
+struct PreloadWorld {
+    meshed_origin_chunks: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl VoxelWorldConfig for PreloadWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        1
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+
+    fn chunk_meshing_delegate(
+        &self,
+    ) -> ChunkMeshingDelegate<Self::MaterialIndex, Self::ChunkUserBundle> {
+        let meshed_origin_chunks = self.meshed_origin_chunks.clone();
+        Some(Box::new(move |pos: IVec3| {
+            let meshed_origin_chunks = meshed_origin_chunks.clone();
+            Box::new(
+                move |voxels: crate::custom_meshing::VoxelArray<u8>,
+                      shape: ndshape::RuntimeShape<u32, 3>,
+                      texture_index_mapper: TextureIndexMapperFn<u8>,
+                      neighbours: Option<crate::custom_meshing::ChunkNeighbours<u8>>| {
+                    if pos == IVec3::ZERO {
+                        meshed_origin_chunks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                    default_chunk_meshing_delegate::<u8, ()>(pos)(
+                        voxels,
+                        shape,
+                        texture_index_mapper,
+                        neighbours,
+                    )
+                },
+            )
+        }))
+    }
+}
+
+#[test]
+fn preloaded_mesh_skips_meshing() {
+    use bevy::render::{
+        render_asset::RenderAssetUsages, render_resource::PrimitiveTopology,
+    };
+
+    let mut app = spawn_test_app::<PreloadWorld>();
+    app.add_plugins(AssetPlugin::default()).init_asset::<Mesh>();
+
+    app.add_systems(Startup, |mut voxel_world: VoxelWorld<PreloadWorld>| {
+        // Generate the voxels of the chunk at the origin, the same way the world will
+        let chunk_task = generated_task::<PreloadWorld>(
+            IVec3::ZERO,
+            (PreloadWorld::default().voxel_lookup_delegate())(IVec3::ZERO),
+        );
+
+        voxel_world.preload_mesh(
+            &chunk_task.chunk_data.voxels.unwrap(),
+            Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            ),
+        );
+    });
+
+    let generated = run_until(&mut app, 1000, |app| {
+        let chunk_map = app
+            .world()
+            .resource::<crate::chunk_map::ChunkMap<PreloadWorld, u8>>();
+        chunk_map
+            .get_read_lock()
+            .get(&IVec3::ZERO)
+            .is_some_and(|chunk_data| chunk_data.has_generated())
+    });
+
+    assert!(generated);
+    let meshed_origin_chunks = app
+        .world()
+        .resource::<PreloadWorld>()
+        .meshed_origin_chunks
+        .clone();
+    assert_eq!(
+        meshed_origin_chunks.load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+}
+
+#[test]
+fn get_voxel_falls_back_to_unset_for_mixed_chunk_without_voxels() {
+    let chunk_data = ChunkData::<u8> {
+        voxels: None,
+        is_empty: false,
+        fill_type: FillType::Mixed,
+        has_generated: true,
+        ..ChunkData::with_entity(Entity::PLACEHOLDER, 32)
+    };
+
+    assert_eq!(chunk_data.get_voxel(UVec3::new(1, 1, 1)), WorldVoxel::Unset);
+}
+
+#[test]
+fn retired_chunks_cancel_their_tasks() {
+    use crate::chunk::ChunkThread;
+
+    let mut app = _test_setup_app();
+    app.update();
+
+    let cancel_flags = app
+        .world_mut()
+        .query::<&ChunkThread<DefaultWorld, u8>>()
+        .iter(app.world())
+        .map(|thread| thread.1.clone())
+        .collect::<Vec<_>>();
+    assert!(!cancel_flags.is_empty());
+    assert!(cancel_flags
+        .iter()
+        .all(|flag| !flag.load(std::sync::atomic::Ordering::Relaxed)));
+
+    // Move the camera far away, so that all spawned chunks get retired
+    let mut camera = app
+        .world_mut()
+        .query_filtered::<&mut GlobalTransform, With<VoxelWorldCamera<DefaultWorld>>>();
+    *camera.single_mut(app.world_mut()) =
+        GlobalTransform::from_translation(Vec3::splat(100_000.0));
+    app.update();
+
+    assert!(cancel_flags
+        .iter()
+        .all(|flag| flag.load(std::sync::atomic::Ordering::Relaxed)));
+}
+
+#[test]
+fn minimal_mesh_has_no_uvs_or_texture_indexes() {
+    use crate::meshing::generate_minimal_chunk_mesh;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos == IVec3::ZERO {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Unset
+        }
+    });
+    let voxels = chunk_task.chunk_data.voxels.clone().unwrap();
+    let shape = crate::chunk::padded_chunk_shape_uniform(32);
+
+    let mesh =
+        generate_minimal_chunk_mesh(voxels.clone(), IVec3::ZERO, shape.clone(), true);
+    assert_eq!(mesh.count_vertices(), 24);
+    assert!(mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_some());
+    assert!(mesh.attribute(Mesh::ATTRIBUTE_UV_0).is_none());
+    assert!(mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_none());
+    assert!(mesh
+        .attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+        .is_none());
+
+    let mesh = generate_minimal_chunk_mesh(voxels, IVec3::ZERO, shape, false);
+    assert_eq!(mesh.count_vertices(), 24);
+    assert!(mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none());
+}
+
+#[test]
+fn load_chunk_spawns_chunk_without_camera() {
+    use crate::chunk::Chunk;
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    let chunk_pos = IVec3::new(3, 0, -2);
+    for _ in 0..2 {
+        app.world_mut()
+            .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+                voxel_world.load_chunk(chunk_pos);
+                voxel_world.load_chunk(chunk_pos);
+            })
+            .unwrap();
+        assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 1, 100));
+    }
+
+    let chunk_count = app
+        .world_mut()
+        .query::<&Chunk<DefaultWorld>>()
+        .iter(app.world())
+        .count();
+    assert_eq!(chunk_count, 1);
+    assert_eq!(loaded_chunk_count::<DefaultWorld>(&app), 1);
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            let chunk_data = voxel_world.get_chunk_data(chunk_pos).unwrap();
+            assert!(chunk_data.has_generated());
+        })
+        .unwrap();
+}
+
+#[test]
+fn recompute_bounds_tightens_stale_bounds() {
+    use crate::chunk_map::{ChunkMap, ChunkMapInsertBuffer, ChunkMapRemoveBuffer};
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    let bounds_max = |app: &App| {
+        let chunk_map = app.world().resource::<ChunkMap<DefaultWorld, u8>>();
+        let read_lock = chunk_map.get_read_lock();
+        ChunkMap::<DefaultWorld, u8>::get_bounds(&read_lock).max
+    };
+
+    for position in [IVec3::ZERO, IVec3::new(4, 0, 0), IVec3::new(0, 4, 0)] {
+        app.world_mut()
+            .resource_mut::<ChunkMapInsertBuffer<DefaultWorld, u8>>()
+            .push((position, ChunkData::with_entity(Entity::PLACEHOLDER, 32)));
+    }
+    app.update();
+    assert_eq!(bounds_max(&app), Vec3A::new(4.0, 4.0, 0.0));
+
+    // The removed chunk is not at a corner of the bounds, so they are not rebuilt
+    app.world_mut()
+        .resource_mut::<ChunkMapRemoveBuffer<DefaultWorld>>()
+        .push(IVec3::new(4, 0, 0));
+    app.update();
+    assert_eq!(bounds_max(&app), Vec3A::new(4.0, 4.0, 0.0));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.recompute_bounds();
+        })
+        .unwrap();
+    assert_eq!(bounds_max(&app), Vec3A::new(0.0, 4.0, 0.0));
+}
+
+#[test]
+fn chunks_by_distance_are_nearest_first() {
+    use crate::chunk_map::ChunkMapInsertBuffer;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    for position in [
+        IVec3::new(5, 0, 0),
+        IVec3::ZERO,
+        IVec3::new(0, -3, 0),
+        IVec3::new(1, 1, 1),
+    ] {
+        app.world_mut()
+            .resource_mut::<ChunkMapInsertBuffer<DefaultWorld, u8>>()
+            .push((position, ChunkData::with_entity(Entity::PLACEHOLDER, 32)));
+    }
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let chunks = voxel_world.chunks_by_distance(Vec3::splat(16.0));
+            let positions = chunks
+                .iter()
+                .map(|(position, _)| *position)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                positions,
+                vec![
+                    IVec3::ZERO,
+                    IVec3::new(1, 1, 1),
+                    IVec3::new(0, -3, 0),
+                    IVec3::new(5, 0, 0),
+                ]
+            );
+            assert_eq!(chunks[0].1, 0.0);
+            assert_eq!(chunks[2].1, 96.0);
+        })
+        .unwrap();
+}
+
+#[test]
+fn world_bounds_and_chunk_count_cover_loaded_chunks() {
+    use crate::chunk_map::ChunkMapInsertBuffer;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    for position in [IVec3::ZERO, IVec3::new(2, -1, 3)] {
+        app.world_mut()
+            .resource_mut::<ChunkMapInsertBuffer<DefaultWorld, u8>>()
+            .push((position, ChunkData::with_entity(Entity::PLACEHOLDER, 32)));
+    }
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(voxel_world.loaded_chunk_count(), 2);
+
+            let bounds = voxel_world.world_bounds();
+            assert_eq!(bounds.min, Vec3A::new(0.0, -32.0, 0.0));
+            assert_eq!(bounds.max, Vec3A::new(96.0, 32.0, 128.0));
+        })
+        .unwrap();
+}
+
+#[test]
+fn loaded_materials_collects_materials_of_all_chunks() {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::utils::HashSet;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(2, 1, 1), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(5, 6, 7), WorldVoxel::Solid(2));
+            voxel_world.set_voxel(IVec3::new(40, 3, 3), WorldVoxel::Solid(5));
+            voxel_world.set_voxel(IVec3::new(50, 3, 3), WorldVoxel::Translucent(9));
+            // In a chunk that doesn't get loaded
+            voxel_world.set_voxel(IVec3::new(200, 3, 3), WorldVoxel::Solid(7));
+        })
+        .unwrap();
+    app.update();
+
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::X);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(
+                voxel_world.loaded_materials(),
+                HashSet::from_iter([1, 2, 5, 9])
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct ReuseWorld {
+    lookups: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl VoxelWorldConfig for ReuseWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn chunk_regenerate_strategy(&self) -> ChunkRegenerateStrategy {
+        ChunkRegenerateStrategy::Reuse
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        let lookups = self.lookups.clone();
+        Box::new(move |_| {
+            let lookups = lookups.clone();
+            Box::new(move |pos| {
+                lookups.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if pos.y < 4 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn reuse_strategy_applies_edits_without_voxel_lookup() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<ReuseWorld>::minimal()));
+    app.add_systems(Update, Internals::<ReuseWorld>::apply_chunk_tasks_headless);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<ReuseWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<ReuseWorld>(&mut app, 1, 100));
+
+    let lookups = app.world().resource::<ReuseWorld>().lookups.clone();
+    let lookups_after_generate = lookups.load(std::sync::atomic::Ordering::Relaxed);
+    assert!(lookups_after_generate > 0);
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<ReuseWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 2, 5), WorldVoxel::Air);
+            voxel_world.set_voxel(IVec3::new(6, 10, 6), WorldVoxel::Solid(3));
+        })
+        .unwrap();
+
+    let chunk_voxel = |app: &mut App, position: IVec3| {
+        app.world_mut()
+            .run_system_once(move |voxel_world: VoxelWorld<ReuseWorld>| {
+                voxel_world
+                    .get_chunk_data(IVec3::ZERO)
+                    .unwrap()
+                    .get_voxel(position.as_uvec3() + 1)
+            })
+            .unwrap()
+    };
+    for _ in 0..100 {
+        app.update();
+        if chunk_voxel(&mut app, IVec3::new(6, 10, 6)) == WorldVoxel::Solid(3) {
+            break;
+        }
+        std::thread::yield_now();
+    }
+
+    assert_eq!(
+        chunk_voxel(&mut app, IVec3::new(6, 10, 6)),
+        WorldVoxel::Solid(3)
+    );
+    assert_eq!(chunk_voxel(&mut app, IVec3::new(5, 2, 5)), WorldVoxel::Air);
+    assert_eq!(
+        chunk_voxel(&mut app, IVec3::new(1, 1, 1)),
+        WorldVoxel::Solid(0)
+    );
+    assert_eq!(
+        lookups.load(std::sync::atomic::Ordering::Relaxed),
+        lookups_after_generate
+    );
+}
+
+#[test]
+fn clear_voxel_falls_back_to_generated_voxel() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<ReuseWorld>::minimal()));
+    app.add_systems(Update, Internals::<ReuseWorld>::apply_chunk_tasks_headless);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<ReuseWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<ReuseWorld>(&mut app, 1, 100));
+
+    let position = IVec3::new(1, 1, 1);
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<ReuseWorld>| {
+            voxel_world.set_voxel(position, WorldVoxel::Air);
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<ReuseWorld>| {
+            assert_eq!(voxel_world.get_voxel(position), WorldVoxel::Air);
+            voxel_world.clear_voxel(position);
+        })
+        .unwrap();
+
+    let mut regenerated = false;
+    for _ in 0..100 {
+        app.update();
+        regenerated = app
+            .world_mut()
+            .run_system_once(move |voxel_world: VoxelWorld<ReuseWorld>| {
+                voxel_world.get_voxel(position) == WorldVoxel::Solid(0)
+            })
+            .unwrap();
+        if regenerated {
+            break;
+        }
+        std::thread::yield_now();
+    }
+    assert!(regenerated);
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<ReuseWorld>| {
+            assert!(voxel_world.export_modified().is_empty());
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct FrustumWorld;
+
+impl VoxelWorldConfig for FrustumWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        4
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Frustum
+    }
+}
+
+#[test]
+fn frustum_strategy_spawns_chunks_in_view() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<FrustumWorld>::minimal()));
+    app.world_mut().spawn((
+        Camera3d::default(),
+        GlobalTransform::from(
+            Transform::from_xyz(10.0, 10.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ),
+        VoxelWorldCamera::<FrustumWorld>::default(),
+    ));
+    app.update();
+
+    let spawned_positions = |app: &mut App| {
+        app.world_mut()
+            .query::<&Chunk<FrustumWorld>>()
+            .iter(app.world())
+            .map(|chunk| chunk.position)
+            .collect::<bevy::utils::HashSet<_>>()
+    };
+
+    let positions = spawned_positions(&mut app);
+    // In front of the camera, along the view direction
+    assert!(positions.contains(&IVec3::new(-2, -2, -2)));
+    // Behind the camera
+    assert!(!positions.contains(&IVec3::new(2, 2, 0)));
+
+    // All chunks in view are found in the first frame
+    app.update();
+    app.update();
+    assert_eq!(spawned_positions(&mut app), positions);
+}
+
+#[derive(Resource, Clone, Default)]
+struct BedrockWorld;
+
+impl VoxelWorldConfig for BedrockWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn bedrock_y(&self) -> Option<i32> {
+        Some(0)
+    }
+
+    fn bedrock_material(&self) -> Self::MaterialIndex {
+        7
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Air))
+    }
+}
+
+#[test]
+fn bedrock_stays_solid_when_dug() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<BedrockWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<BedrockWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<BedrockWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<BedrockWorld>(&mut app, 1, 100));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<BedrockWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 0, 1), WorldVoxel::Air);
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Solid(1));
+            voxel_world.import_modified([(IVec3::new(2, 0, 2), WorldVoxel::Air)]);
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<BedrockWorld>| {
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(1, 0, 1)),
+                WorldVoxel::Solid(7)
+            );
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(1, 1, 1)),
+                WorldVoxel::Solid(1)
+            );
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(2, 0, 2)),
+                WorldVoxel::Solid(7)
+            );
+            assert_eq!(voxel_world.get_voxel(IVec3::new(2, 2, 2)), WorldVoxel::Air);
+        })
+        .unwrap();
+}
+
+#[test]
+fn world_voxels_sort_by_kind_then_material() {
+    let mut voxels = vec![
+        WorldVoxel::Translucent(0),
+        WorldVoxel::Solid(3),
+        WorldVoxel::Air,
+        WorldVoxel::Solid(1),
+        WorldVoxel::Unset,
+        WorldVoxel::Translucent(2),
+        WorldVoxel::Air,
+    ];
+    voxels.sort();
+
+    assert_eq!(
+        voxels,
+        vec![
+            WorldVoxel::Unset,
+            WorldVoxel::Air,
+            WorldVoxel::Air,
+            WorldVoxel::Solid(1),
+            WorldVoxel::Solid(3),
+            WorldVoxel::Translucent(0),
+            WorldVoxel::Translucent(2),
+        ]
+    );
+    assert_eq!(voxels.binary_search(&WorldVoxel::Solid(3)), Ok(4));
+}
+
+#[test]
+fn swap_voxel_returns_previous_voxel() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    let position = IVec3::new(3, 4, 5);
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(position, WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(
+                voxel_world.swap_voxel(position, WorldVoxel::Solid(2)),
+                WorldVoxel::Solid(1)
+            );
+            // The pending write from the first swap is returned
+            assert_eq!(
+                voxel_world.swap_voxel(position, WorldVoxel::Air),
+                WorldVoxel::Solid(2)
+            );
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(voxel_world.get_voxel(position), WorldVoxel::Air);
+        })
+        .unwrap();
+}
+
+#[test]
+fn copy_and_paste_region() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(0, 0, 0), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(1, 0, 0), WorldVoxel::Air);
+            voxel_world.set_voxel(IVec3::new(1, 1, 2), WorldVoxel::Solid(2));
+
+            // Already in place where the air voxel gets pasted
+            voxel_world.set_voxel(IVec3::new(11, 0, 0), WorldVoxel::Solid(3));
+            voxel_world.set_voxel(IVec3::new(21, 0, 0), WorldVoxel::Solid(3));
+            // Already in place where an unset voxel gets pasted
+            voxel_world.set_voxel(IVec3::new(20, 1, 0), WorldVoxel::Solid(3));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            let clip = voxel_world.copy_region(IVec3::new(1, 1, 2), IVec3::ZERO);
+            assert_eq!(clip.size(), UVec3::new(2, 2, 3));
+            assert_eq!(clip.get(UVec3::ZERO), WorldVoxel::Solid(1));
+            assert_eq!(clip.get(UVec3::new(1, 0, 0)), WorldVoxel::Air);
+            assert_eq!(clip.get(UVec3::new(1, 1, 2)), WorldVoxel::Solid(2));
+            assert_eq!(clip.get(UVec3::new(0, 1, 0)), WorldVoxel::Unset);
+
+            voxel_world.paste_region(IVec3::new(10, 0, 0), &clip, true);
+            voxel_world.paste_region(IVec3::new(20, 0, 0), &clip, false);
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(10, 0, 0)),
+                WorldVoxel::Solid(1)
+            );
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(11, 0, 0)),
+                WorldVoxel::Solid(3)
+            );
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(11, 1, 2)),
+                WorldVoxel::Solid(2)
+            );
+
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(20, 0, 0)),
+                WorldVoxel::Solid(1)
+            );
+            assert_eq!(voxel_world.get_voxel(IVec3::new(21, 0, 0)), WorldVoxel::Air);
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(21, 1, 2)),
+                WorldVoxel::Solid(2)
+            );
+            // Unset voxels are never pasted, even when air is
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(20, 1, 0)),
+                WorldVoxel::Solid(3)
+            );
+        })
+        .unwrap();
+}
+
+#[test]
+fn fill_sphere_and_box() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.fill_sphere(IVec3::new(100, 100, 100), 3, WorldVoxel::Solid(1));
+            // Overlaps the sphere, which should not write the same position twice
+            voxel_world.fill_sphere(IVec3::new(100, 100, 100), 2, WorldVoxel::Solid(1));
+        })
+        .unwrap();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            // Number of integer points within a radius of 3
+            assert_eq!(voxel_world.export_modified().len(), 123);
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(103, 100, 100)),
+                WorldVoxel::Solid(1)
+            );
+            assert!(voxel_world
+                .export_modified()
+                .iter()
+                .all(|(position, _)| *position != IVec3::new(103, 103, 103)
+                    && *position != IVec3::new(97, 97, 97)));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.fill_box(IVec3::new(2, 1, 3), IVec3::ZERO, WorldVoxel::Solid(2));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(voxel_world.export_modified().len(), 123 + 24);
+            assert_eq!(voxel_world.get_voxel(IVec3::ZERO), WorldVoxel::Solid(2));
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(2, 1, 3)),
+                WorldVoxel::Solid(2)
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct ZeroSizedChunkWorld;
+
+impl VoxelWorldConfig for ZeroSizedChunkWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn chunk_size(&self) -> u32 {
+        0
+    }
+}
+
+#[test]
+fn too_small_chunk_shape_is_reported() {
+    use crate::voxel_world_internal::configuration_problems;
+
+    assert!(configuration_problems(&DefaultWorld).is_empty());
+
+    let problems = configuration_problems(&ZeroSizedChunkWorld);
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("chunk_size"));
+}
+
+#[derive(Resource, Clone, Default)]
+struct MisconfiguredWorld;
+
+impl VoxelWorldConfig for MisconfiguredWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        100_000
+    }
+
+    fn max_spawn_per_frame(&self) -> usize {
+        0
+    }
+
+    fn max_remesh_tasks_per_frame(&self) -> usize {
+        0
+    }
+
+    fn world_chunk_bounds(&self) -> Option<(IVec3, IVec3)> {
+        Some((IVec3::new(4, -2, 4), IVec3::new(-4, 2, 4)))
+    }
+
+    #[allow(clippy::reversed_empty_ranges)]
+    fn spawn_y_range(&self) -> std::ops::RangeInclusive<i32> {
+        2..=-2
+    }
+}
+
+#[test]
+fn unusable_spawning_settings_are_reported() {
+    use crate::voxel_world_internal::configuration_problems;
+
+    let problems = configuration_problems(&MisconfiguredWorld);
+    let reported = |setting: &str| {
+        problems
+            .iter()
+            .filter(|problem| problem.contains(&format!("VoxelWorldConfig::{setting} ")))
+            .count()
+    };
+
+    assert_eq!(problems.len(), 5, "{problems:#?}");
+    assert_eq!(reported("spawning_distance"), 1);
+    assert_eq!(reported("max_spawn_per_frame"), 1);
+    assert_eq!(reported("max_remesh_tasks_per_frame"), 1);
+    assert_eq!(reported("max_meshes_per_frame"), 0);
+    assert_eq!(reported("world_chunk_bounds"), 1);
+    assert_eq!(reported("spawn_y_range"), 1);
+}
+
+#[derive(Resource, Clone, Default)]
+struct BoundedWorld;
+
+impl VoxelWorldConfig for BoundedWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        6
+    }
+
+    fn max_spawn_per_frame(&self) -> usize {
+        100_000
+    }
+
+    fn world_chunk_bounds(&self) -> Option<(IVec3, IVec3)> {
+        Some((IVec3::splat(-2), IVec3::splat(2)))
+    }
+}
+
+#[test]
+fn chunks_are_not_spawned_outside_world_bounds() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<BoundedWorld>::minimal()));
+    app.world_mut().spawn((
+        GlobalTransform::from_translation(Vec3::splat(16.0)),
+        VoxelWorldAnchor::<BoundedWorld>::default(),
+    ));
+    for _ in 0..5 {
+        app.update();
+    }
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<BoundedWorld>| {
+            voxel_world.load_chunk(IVec3::new(3, 0, 0));
+            voxel_world.set_voxel(IVec3::new(3 * 32, 0, 0), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<BoundedWorld>| {
+            // All 5x5x5 chunks within the bounds are within the spawning distance
+            assert_eq!(voxel_world.loaded_chunk_count(), 125);
+            for (chunk_pos, _) in voxel_world.chunks_by_distance(Vec3::ZERO) {
+                assert!(chunk_pos.abs().max_element() <= 2);
+            }
+
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(3 * 32, 0, 0)),
+                WorldVoxel::Unset
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct AnchorWorld;
+
+impl VoxelWorldConfig for AnchorWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+}
+
+#[test]
+fn chunks_are_spawned_around_all_anchors() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<AnchorWorld>::minimal()));
+
+    let chunk_size = AnchorWorld.chunk_size() as f32;
+    app.world_mut().spawn((
+        GlobalTransform::from_translation(Vec3::splat(chunk_size / 2.0)),
+        VoxelWorldAnchor::<AnchorWorld>::default(),
+    ));
+    let anchor_b = app
+        .world_mut()
+        .spawn((
+            GlobalTransform::from_translation(
+                Vec3::new(2.0, 0.0, 0.0) * chunk_size + chunk_size / 2.0,
+            ),
+            VoxelWorldAnchor::<AnchorWorld>::default(),
+        ))
+        .id();
+    app.update();
+
+    let spawned_positions = |app: &mut App| {
+        app.world_mut()
+            .query::<&Chunk<AnchorWorld>>()
+            .iter(app.world())
+            .map(|chunk| chunk.position)
+            .collect::<Vec<_>>()
+    };
+
+    // The spheres of the anchors overlap, but each chunk is only spawned once
+    let positions = spawned_positions(&mut app);
+    let unique_positions = positions
+        .iter()
+        .copied()
+        .collect::<bevy::utils::HashSet<_>>();
+    assert_eq!(positions.len(), unique_positions.len());
+
+    let within = |origin: IVec3, max_distance_squared: i32| {
+        move |position: &IVec3| position.distance_squared(origin) <= max_distance_squared
+    };
+    let anchor_a_chunk = IVec3::ZERO;
+    let anchor_b_chunk = IVec3::new(2, 0, 0);
+    for position in positions.iter() {
+        assert!(
+            within(anchor_a_chunk, 4)(position) || within(anchor_b_chunk, 4)(position)
+        );
+    }
+    let expected_count = (-4..=6)
+        .flat_map(|x| {
+            (-2..=2).flat_map(move |y| (-2..=2).map(move |z| IVec3::new(x, y, z)))
+        })
+        .filter(|position| {
+            within(anchor_a_chunk, 4)(position) || within(anchor_b_chunk, 4)(position)
+        })
+        .count();
+    assert_eq!(positions.len(), expected_count);
+
+    // Chunks only close to the removed anchor are despawned, the rest are kept alive by the
+    // remaining anchor
+    app.world_mut().despawn(anchor_b);
+    app.update();
+
+    let positions = spawned_positions(&mut app);
+    assert!(!positions.is_empty());
+    assert!(positions.iter().all(within(anchor_a_chunk, 5)));
+}
+
+#[test]
+fn face_flood_fill_skips_diagonal_neighbours() {
+    let offsets = FloodFillConnectivity::Faces.neighbour_offsets();
+    assert_eq!(offsets.len(), 6);
+    for offset in offsets {
+        assert_eq!(offset.abs().element_sum(), 1);
+    }
+
+    let offsets = FloodFillConnectivity::Full.neighbour_offsets();
+    assert_eq!(offsets.len(), 26);
+    assert!(offsets.contains(&IVec3::ONE));
+}
+
+#[derive(Resource, Clone, Default)]
+struct FaceFloodFillWorld;
+
+impl VoxelWorldConfig for FaceFloodFillWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Close
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn world_chunk_bounds(&self) -> Option<(IVec3, IVec3)> {
+        Some((IVec3::new(-4, 0, -4), IVec3::new(4, 0, 4)))
+    }
+
+    fn flood_fill_connectivity(&self) -> FloodFillConnectivity {
+        FloodFillConnectivity::Faces
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct FullFloodFillWorld;
+
+impl VoxelWorldConfig for FullFloodFillWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Close
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn world_chunk_bounds(&self) -> Option<(IVec3, IVec3)> {
+        Some((IVec3::new(-4, 0, -4), IVec3::new(4, 0, 4)))
+    }
+
+    fn flood_fill_connectivity(&self) -> FloodFillConnectivity {
+        FloodFillConnectivity::Full
+    }
+}
+
+/// Spawns chunks for one frame around a camera in chunk (0, 0, 0), with the chunk right behind
+/// the camera already in the map. The world is a single chunk layer, and with a spawning
+/// distance of 2, the chunk behind that one is only connected to the rest by its diagonals.
+fn flood_filled_positions<C: VoxelWorldConfig + Default>() -> Vec<IVec3> {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<C>::minimal()));
+    force_chunk_into_map::<C>(&mut app, IVec3::new(0, 0, 1));
+    app.update();
+
+    // The camera looks down -Z, so no spawning rays reach the chunks behind it
+    app.world_mut().spawn((
+        Camera3d::default(),
+        GlobalTransform::from_xyz(16.0, 16.0, 16.0),
+        VoxelWorldCamera::<C>::default(),
+    ));
+    app.update();
+
+    app.world_mut()
+        .query::<&Chunk<C>>()
+        .iter(app.world())
+        .map(|chunk| chunk.position)
+        .collect()
+}
+
+#[test]
+fn flood_fill_connectivity_decides_which_neighbours_get_spawned() {
+    let behind = IVec3::new(0, 0, 2);
+
+    let positions = flood_filled_positions::<FaceFloodFillWorld>();
+    assert!(positions.contains(&IVec3::new(1, 0, 1)));
+    assert!(positions.contains(&IVec3::new(-1, 0, 1)));
+    assert!(!positions.contains(&behind));
+
+    let positions = flood_filled_positions::<FullFloodFillWorld>();
+    assert!(positions.contains(&behind));
+    assert!(positions.iter().all(|position| position.y == 0));
+}
+
+#[test]
+fn segment_blocked_by_wall() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            for x in 0..4 {
+                for y in 0..4 {
+                    voxel_world.set_voxel(IVec3::new(x, y, 5), WorldVoxel::Solid(1));
+                }
+            }
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let from = Vec3::new(1.5, 1.5, 1.5);
+            assert!(voxel_world.segment_blocked(from, Vec3::new(1.5, 1.5, 10.5)));
+            assert!(!voxel_world.segment_blocked(from, Vec3::new(10.5, 1.5, 1.5)));
+            // The segment ends in front of the wall
+            assert!(!voxel_world.segment_blocked(from, Vec3::new(1.5, 1.5, 4.5)));
+        })
+        .unwrap();
+}
+
+#[test]
+fn segment_blocked_happens_in_the_local_space_of_the_world_root() {
+    use crate::voxel_world_internal::WorldRoot;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    let root_transform = Transform::from_xyz(100.0, 0.0, 0.0);
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 5, 5), WorldVoxel::Solid(1));
+            voxel_world.set_root_transform(root_transform);
+        })
+        .unwrap();
+    app.update();
+
+    // Transforms are not propagated with `MinimalPlugins`, so the `GlobalTransform` of the root
+    // is set directly
+    let root = app
+        .world_mut()
+        .query_filtered::<Entity, With<WorldRoot<DefaultWorld>>>()
+        .single(app.world());
+    *app.world_mut().get_mut::<GlobalTransform>(root).unwrap() =
+        GlobalTransform::from(root_transform);
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            // The voxel has moved along with the root
+            assert!(voxel_world.segment_blocked(
+                Vec3::new(105.5, 5.5, 0.5),
+                Vec3::new(105.5, 5.5, 10.5)
+            ));
+            // Where the voxel would be without the offset, there is nothing
+            assert!(!voxel_world
+                .segment_blocked(Vec3::new(5.5, 5.5, 0.5), Vec3::new(5.5, 5.5, 10.5)));
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct QuarterVoxelWorld;
+
+impl VoxelWorldConfig for QuarterVoxelWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        1
+    }
+
+    fn voxel_size(&self) -> f32 {
+        0.25
+    }
+}
+
+#[test]
+fn raycast_hits_are_scaled_by_voxel_size() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<QuarterVoxelWorld>();
+    force_chunk_into_map::<QuarterVoxelWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<QuarterVoxelWorld>| {
+            voxel_world.set_voxel(IVec3::new(2, 3, 5), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<QuarterVoxelWorld>| {
+            // Voxel (2, 3, 5) spans from (0.5, 0.75, 1.25) to (0.75, 1.0, 1.5) in world units
+            let ray = Ray3d {
+                origin: Vec3::new(0.6, 0.8, 7.0),
+                direction: -Dir3::Z,
+            };
+            let result = voxel_world.raycast(ray, &|_| true).unwrap();
+
+            assert_eq!(result.position, Vec3::new(0.5, 0.75, 1.25));
+            assert_eq!(result.voxel_pos(), IVec3::new(2, 3, 5));
+            assert_eq!(result.normal, Some(Vec3::Z));
+
+            // A ray passing just next to the voxel misses it
+            let ray = Ray3d {
+                origin: Vec3::new(0.8, 0.8, 7.0),
+                direction: -Dir3::Z,
+            };
+            assert!(voxel_world.raycast(ray, &|_| true).is_none());
+        })
+        .unwrap();
+
+    let mut chunks = app
+        .world_mut()
+        .query::<(&Chunk<QuarterVoxelWorld>, &Transform)>();
+    for (chunk, transform) in chunks.iter(app.world()) {
+        assert_eq!(transform.scale, Vec3::splat(0.25));
+        assert_eq!(
+            transform.translation,
+            (chunk.position.as_vec3() * 32.0 - 1.0) * 0.25
+        );
+    }
+}
+
+#[test]
+fn highest_solid_in_column_scans_top_down() {
+    let mut app = spawn_test_app::<DefaultWorld>();
+
+    // Runs before any chunk has been spawned
+    app.add_systems(Startup, |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        voxel_world.set_voxel(IVec3::new(3, 2, 4), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(3, 5, 4), WorldVoxel::Solid(2));
+        voxel_world.set_voxel(IVec3::new(3, 7, 4), WorldVoxel::Air);
+
+        assert_eq!(
+            voxel_world.highest_solid_in_column(3, 4, -10..=10),
+            Some((IVec3::new(3, 5, 4), WorldVoxel::Solid(2)))
+        );
+        assert_eq!(
+            voxel_world.highest_solid_in_column(3, 4, -10..=4),
+            Some((IVec3::new(3, 2, 4), WorldVoxel::Solid(1)))
+        );
+        assert_eq!(voxel_world.highest_solid_in_column(3, 4, 6..=10), None);
+        assert_eq!(voxel_world.highest_solid_in_column(0, 0, -10..=10), None);
+    });
+
+    app.update();
+}
+
+#[derive(Resource, Clone, Default)]
+struct BakeWorld;
+
+impl VoxelWorldConfig for BakeWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y == 0 && pos.x < 40 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn bake_loaded_world_merges_chunk_meshes() {
+    use crate::custom_meshing::generate_chunk_mesh_for_shape;
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    // No camera, so only the forced chunks get loaded
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<BakeWorld>::minimal()));
+    app.add_plugins(AssetPlugin::default()).init_asset::<Mesh>();
+    app.add_systems(Update, Internals::<BakeWorld>::apply_chunk_tasks_headless);
+
+    let chunk_positions = [IVec3::ZERO, IVec3::X];
+    for chunk_pos in chunk_positions {
+        force_chunk_into_map::<BakeWorld>(&mut app, chunk_pos);
+    }
+    app.update();
+
+    // Mesh the chunks by hand, since meshes are never spawned in this app
+    let vertex_counts = app
+        .world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<BakeWorld>| {
+            chunk_positions
+                .iter()
+                .map(|chunk_pos| {
+                    let voxels = voxel_world
+                        .get_chunk_data(*chunk_pos)
+                        .unwrap()
+                        .decoded_voxels()
+                        .unwrap();
+                    let mesh = generate_chunk_mesh_for_shape(
+                        voxels.clone(),
+                        *chunk_pos,
+                        crate::chunk::padded_chunk_shape_uniform(32),
+                        BakeWorld.texture_index_mapper(),
+                        std::sync::Arc::new(|_| 1.0),
+                    );
+                    let vertex_count = mesh.count_vertices();
+                    voxel_world.preload_mesh(&voxels, mesh);
+                    vertex_count
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    app.update();
+
+    // The chunks hold different parts of the floor, so their meshes differ
+    assert_ne!(vertex_counts[0], vertex_counts[1]);
+
+    let baked = app
+        .world_mut()
+        .run_system_once(
+            |voxel_world: VoxelWorld<BakeWorld>, meshes: Res<Assets<Mesh>>| {
+                voxel_world.bake_loaded_world(&meshes)
+            },
+        )
+        .unwrap();
+
+    assert_eq!(baked.count_vertices(), vertex_counts.iter().sum::<usize>());
+    assert_eq!(
+        baked.indices().unwrap().len(),
+        baked.count_vertices() / 4 * 6
+    );
+
+    // The floor ends at x = 40, which is inside the second chunk
+    let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) =
+        baked.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("baked mesh has no positions");
+    };
+    let max_x = positions.iter().map(|p| p[0]).fold(f32::MIN, f32::max);
+    assert_eq!(max_x, 40.0);
+}
+
+#[test]
+fn compressed_chunk_data_decodes_to_same_voxels() {
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| match pos.y {
+        ..=-1 => WorldVoxel::Solid(pos.x.rem_euclid(3) as u8),
+        0 if pos.x == pos.z => WorldVoxel::Translucent(7),
+        _ => WorldVoxel::Air,
+    });
+
+    let raw = chunk_task.chunk_data.clone();
+    let mut compressed = chunk_task.chunk_data;
+    compressed.compress();
+
+    assert!(compressed.voxels.is_none());
+    assert!(compressed.palette_voxels.is_some());
+    assert_eq!(compressed.voxels_hash, raw.voxels_hash);
+    assert_eq!(
+        crate::chunk::voxels_hash(&compressed.decoded_voxels().unwrap()),
+        raw.voxels_hash
+    );
+
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                let position = UVec3::new(x, y, z);
+                assert_eq!(compressed.get_voxel(position), raw.get_voxel(position));
+            }
+        }
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct WideMaterialWorld;
+
+impl VoxelWorldConfig for WideMaterialWorld {
+    type MaterialIndex = u32;
+    type ChunkUserBundle = ();
+
+    fn chunk_size(&self) -> u32 {
+        64
+    }
+}
+
+#[test]
+fn compressed_chunk_data_handles_large_palettes() {
+    // Every voxel is distinct, so the palette has more entries than fit in a u16 index
+    let chunk_task = generated_task::<WideMaterialWorld>(IVec3::ZERO, |pos| {
+        let pos = (pos + IVec3::ONE).as_uvec3();
+        WorldVoxel::Solid(pos.x + pos.y * 66 + pos.z * 66 * 66)
+    });
+
+    let raw = chunk_task.chunk_data.clone();
+    let mut compressed = chunk_task.chunk_data;
+    compressed.compress();
+
+    assert!(compressed.palette_voxels.is_some());
+    assert_eq!(
+        crate::chunk::voxels_hash(&compressed.decoded_voxels().unwrap()),
+        raw.voxels_hash
+    );
+    for position in [UVec3::ZERO, UVec3::new(40, 50, 60), UVec3::splat(65)] {
+        assert_eq!(compressed.get_voxel(position), raw.get_voxel(position));
+    }
+}
+
+#[test]
+fn material_at_feet_returns_surface_material() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(2, 3, 2), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(6, 3, 2), WorldVoxel::Solid(2));
+            voxel_world.set_voxel(IVec3::new(6, 4, 2), WorldVoxel::Translucent(3));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            // Standing on top of the voxel
+            assert_eq!(
+                voxel_world.material_at_feet(Vec3::new(2.5, 4.0, 2.5)),
+                Some(1)
+            );
+            // Wading through the translucent voxel above the solid one
+            assert_eq!(
+                voxel_world.material_at_feet(Vec3::new(6.5, 4.5, 2.5)),
+                Some(3)
+            );
+            // Too high above the ground
+            assert_eq!(voxel_world.material_at_feet(Vec3::new(2.5, 6.0, 2.5)), None);
+            // Nothing below
+            assert_eq!(voxel_world.material_at_feet(Vec3::new(4.5, 4.0, 2.5)), None);
+        })
+        .unwrap();
+}
+
+#[test]
+fn plugin_builds_with_configured_task_pool() {
+    use bevy::tasks::AsyncComputeTaskPool;
+
+    let mut app = App::new();
+    // Added before `MinimalPlugins`, so the pool gets set up by the voxel world plugin, unless
+    // another test has already set it up
+    app.add_plugins(VoxelWorldPlugin::<DefaultWorld>::minimal().configure_task_pool(2));
+    app.add_plugins(MinimalPlugins);
+    app.update();
+
+    assert!(AsyncComputeTaskPool::try_get().is_some());
+}
+
+#[test]
+fn voxels_hash_is_the_same_across_worlds() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let edits = [
+        (IVec3::new(1, 2, 3), WorldVoxel::Solid(2)),
+        (IVec3::new(4, 5, 6), WorldVoxel::Solid(3)),
+        (IVec3::new(7, 0, 1), WorldVoxel::Air),
+    ];
+
+    let chunk_hash = |reverse_edits: bool| {
+        let mut app = spawn_test_app::<DefaultWorld>();
+        app.update();
+
+        let mut edits = edits.to_vec();
+        if reverse_edits {
+            edits.reverse();
+        }
+        app.world_mut()
+            .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+                for (position, voxel) in edits.iter() {
+                    voxel_world.set_voxel(*position, *voxel);
+                }
+            })
+            .unwrap();
+        app.update();
+
+        force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+        app.update();
+
+        app.world_mut()
+            .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+                voxel_world.get_chunk_data(IVec3::ZERO).unwrap().voxels_hash
+            })
+            .unwrap()
+    };
+
+    let first = chunk_hash(false);
+    assert_eq!(first, chunk_hash(false));
+    // The order the edits were made in should not matter
+    assert_eq!(first, chunk_hash(true));
+}
+
+#[test]
+fn get_chunk_data_arc_shares_chunk_data() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let first = voxel_world.get_chunk_data_arc(IVec3::ZERO).unwrap();
+            let second = voxel_world.get_chunk_data_arc(IVec3::ZERO).unwrap();
+            assert!(std::sync::Arc::ptr_eq(&first, &second));
+
+            let copy = voxel_world.get_chunk_data(IVec3::ZERO).unwrap();
+            assert_eq!(first.voxels_hash, copy.voxels_hash);
+            assert_eq!(first.get_entity(), copy.get_entity());
+
+            assert!(voxel_world.get_chunk_data_arc(IVec3::ONE).is_none());
+        })
+        .unwrap();
+}
+
+#[test]
+fn chunk_invalidated_reports_reason() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[derive(Resource, Default)]
+    struct Reasons(Vec<(IVec3, InvalidationReason)>);
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.init_resource::<Reasons>();
+    app.add_systems(
+        Update,
+        (
+            Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+            |mut ev_chunk_invalidated: EventReader<ChunkInvalidated<DefaultWorld>>,
+             mut reasons: ResMut<Reasons>| {
+                for event in ev_chunk_invalidated.read() {
+                    reasons.0.push((event.chunk_key, event.reason));
+                }
+            },
+        ),
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 1, 100));
+    assert_eq!(
+        app.world().resource::<Reasons>().0,
+        vec![(IVec3::ZERO, InvalidationReason::Streamed)]
+    );
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 2, 3), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+    app.update();
+    assert_eq!(
+        app.world().resource::<Reasons>().0,
+        vec![
+            (IVec3::ZERO, InvalidationReason::Streamed),
+            (IVec3::ZERO, InvalidationReason::Edited)
+        ]
+    );
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.regenerate_all();
+        })
+        .unwrap();
+    app.update();
+    app.update();
+    assert_eq!(
+        app.world().resource::<Reasons>().0,
+        vec![
+            (IVec3::ZERO, InvalidationReason::Streamed),
+            (IVec3::ZERO, InvalidationReason::Edited),
+            (IVec3::ZERO, InvalidationReason::Regenerated)
+        ]
+    );
+}
+
+#[derive(Resource, Clone, Default)]
+struct RegionSeedWorld {
+    raised_chunk_x: Option<i32>,
+}
+
+impl VoxelWorldConfig for RegionSeedWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn config_change_affects_chunk(&self, chunk_pos: IVec3, old: &Self) -> bool {
+        Some(chunk_pos.x) == self.raised_chunk_x
+            || Some(chunk_pos.x) == old.raised_chunk_x
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        let raised_chunk_x = self.raised_chunk_x;
+        Box::new(move |chunk_pos| {
+            let raised = Some(chunk_pos.x) == raised_chunk_x;
+            Box::new(move |pos| {
+                if pos.y < 0 || (raised && pos.y < 4) {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn config_change_remeshes_only_affected_chunks() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[derive(Resource, Default)]
+    struct Remeshed(Vec<IVec3>);
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<RegionSeedWorld>::minimal(),
+    ));
+    app.init_resource::<Remeshed>();
+    app.add_systems(
+        Update,
+        (
+            Internals::<RegionSeedWorld>::apply_chunk_tasks_headless,
+            |mut ev_chunk_will_remesh: EventReader<ChunkWillRemesh<RegionSeedWorld>>,
+             mut remeshed: ResMut<Remeshed>| {
+                for event in ev_chunk_will_remesh.read() {
+                    remeshed.0.push(event.chunk_key);
+                }
+            },
+        ),
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<RegionSeedWorld>| {
+            for x in 0..4 {
+                voxel_world.load_chunk(IVec3::new(x, 0, 0));
+            }
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<RegionSeedWorld>(&mut app, 4, 100));
+    app.world_mut().resource_mut::<Remeshed>().0.clear();
+
+    app.world_mut()
+        .resource_mut::<RegionSeedWorld>()
+        .raised_chunk_x = Some(2);
+    app.update();
+    app.update();
+
+    assert_eq!(
+        app.world().resource::<Remeshed>().0,
+        vec![IVec3::new(2, 0, 0)]
+    );
+
+    // The affected chunk picks up the new config once it has been regenerated
+    let raised_voxel = |app: &mut App| {
+        app.world_mut()
+            .run_system_once(|voxel_world: VoxelWorld<RegionSeedWorld>| {
+                voxel_world.get_voxel(IVec3::new(65, 2, 1))
+            })
+            .unwrap()
+    };
+    for _ in 0..100 {
+        if raised_voxel(&mut app) == WorldVoxel::Solid(0) {
+            break;
+        }
+        app.update();
+        std::thread::yield_now();
+    }
+    assert_eq!(raised_voxel(&mut app), WorldVoxel::Solid(0));
+}
+
+#[test]
+fn smooth_mesh_encloses_single_voxel() {
+    use crate::custom_meshing::generate_chunk_mesh_smooth;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos == IVec3::new(4, 4, 4) {
+            WorldVoxel::Solid(2)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+
+    let mesh = generate_chunk_mesh_smooth(
+        chunk_task.chunk_data.voxels.unwrap(),
+        IVec3::ZERO,
+        crate::chunk::padded_chunk_shape_uniform(32),
+        DefaultWorld.texture_index_mapper(),
+    );
+
+    // One vertex in each of the eight cells around the voxel, and a quad for each of its faces
+    assert_eq!(mesh.count_vertices(), 8);
+    assert_eq!(mesh.indices().unwrap().len(), 6 * 6);
+
+    let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("mesh has no positions");
+    };
+    let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    else {
+        panic!("mesh has no normals");
+    };
+
+    // The voxel is at index 5 in the padded array, so its center is at 5.5
+    let center = Vec3::splat(5.5);
+    for (position, normal) in positions.iter().zip(normals) {
+        let outward = Vec3::from(*position) - center;
+        assert!(outward.length() < 1.0);
+        assert!(Vec3::from(*normal).dot(outward) > 0.0);
+    }
+    assert!(mesh
+        .attribute(crate::rendering::ATTRIBUTE_TEX_INDEX)
+        .is_some());
+}
+
+#[test]
+fn sparse_chunk_data_reads_same_voxels() {
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos.y == 5 && pos.x < 4 && pos.z < 4 {
+            WorldVoxel::Solid(3)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+
+    let raw = chunk_task.chunk_data.clone();
+    let mut sparse = chunk_task.chunk_data.clone();
+    assert!(!sparse.make_sparse(8));
+    assert!(sparse.voxels.is_some());
+    assert!(sparse.make_sparse(256));
+
+    let sparse_voxels = sparse.sparse_voxels.clone().unwrap();
+    assert!(sparse.voxels.is_none());
+    assert_eq!(sparse_voxels.background, WorldVoxel::Air);
+    // Only the filled voxels are stored, out of 34^3 in the full array. The padding adds one
+    // more row of voxels at x = -1 and z = -1.
+    assert_eq!(sparse_voxels.voxels.len(), 5 * 5);
+    assert_eq!(sparse_voxels.len, 34 * 34 * 34);
+
+    assert_eq!(sparse.voxels_hash, raw.voxels_hash);
+    assert_eq!(
+        crate::chunk::voxels_hash(&sparse.decoded_voxels().unwrap()),
+        raw.voxels_hash
+    );
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                let position = UVec3::new(x, y, z);
+                assert_eq!(sparse.get_voxel(position), raw.get_voxel(position));
+            }
+        }
+    }
+}
+
+#[test]
+fn sparse_chunk_data_indexes_large_chunks() {
+    // The padded array of a 64 voxel chunk has far more than u16::MAX voxels
+    let filled = [
+        IVec3::new(1, 2, 3),
+        IVec3::new(60, 61, 62),
+        IVec3::new(63, 63, 63),
+    ];
+    let chunk_task = generated_task::<LargeChunkWorld>(IVec3::ZERO, move |pos| {
+        if filled.contains(&pos) {
+            WorldVoxel::Solid(1)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+
+    let raw = chunk_task.chunk_data.clone();
+    let mut sparse = chunk_task.chunk_data.clone();
+    assert!(!sparse.make_sparse(0));
+    assert!(sparse.make_sparse(16));
+    assert_eq!(
+        sparse.sparse_voxels.as_ref().unwrap().voxels.len(),
+        filled.len()
+    );
+
+    // Voxels are read in padded coordinates
+    for position in filled {
+        let padded = position.as_uvec3() + UVec3::ONE;
+        assert_eq!(sparse.get_voxel(padded), WorldVoxel::Solid(1));
+    }
+    assert_eq!(
+        crate::chunk::voxels_hash(&sparse.decoded_voxels().unwrap()),
+        raw.voxels_hash
+    );
+}
+
+#[derive(Resource, Clone, Default)]
+struct MeshBudgetWorld;
+
+impl VoxelWorldConfig for MeshBudgetWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn max_meshes_per_frame(&self) -> usize {
+        2
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn spawn_meshes_respects_mesh_budget() {
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = mesh_spawning_app::<MeshBudgetWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<MeshBudgetWorld>| {
+            for x in 0..5 {
+                voxel_world.load_chunk(IVec3::new(x, -1, 0));
+            }
+        })
+        .unwrap();
+
+    let mesh_count = |app: &mut App| {
+        app.world_mut()
+            .query::<&MeshRef>()
+            .iter(app.world())
+            .count()
+    };
+
+    let mut previous_count = 0;
+    for _ in 0..1000 {
+        app.update();
+        let count = mesh_count(&mut app);
+        assert!(count - previous_count <= 2);
+        previous_count = count;
+        if count == 5 {
+            break;
+        }
+        std::thread::yield_now();
+    }
+    assert_eq!(previous_count, 5);
+}
+
+#[derive(Resource, Clone, Default)]
+struct AgeTintWorld;
+
+impl VoxelWorldConfig for AgeTintWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn debug_chunk_age_tint(&self) -> bool {
+        true
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn spawn_meshes_records_mesh_time_for_age_tint() {
+    use crate::debug::ChunkMeshedAt;
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = mesh_spawning_app::<AgeTintWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<AgeTintWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+
+    assert!(run_until(&mut app, 1000, |app| first_meshed_chunk(app).is_some()));
+
+    let elapsed = app.world().resource::<Time>().elapsed();
+    let meshed_at = app
+        .world_mut()
+        .query_filtered::<&ChunkMeshedAt, With<MeshRef>>()
+        .iter(app.world())
+        .map(|meshed_at| meshed_at.0)
+        .collect::<Vec<_>>();
+    assert_eq!(meshed_at.len(), 1);
+    assert!(meshed_at[0] <= elapsed);
+}
+
+#[test]
+fn chunk_age_tint_outline_follows_the_voxel_size() {
+    use crate::debug_draw::chunk_outline_transform;
+
+    // A chunk at chunk position (1, 0, 0) in a world with a voxel size of 0.5
+    let transform = GlobalTransform::from(
+        Transform::from_translation(Vec3::new(31.0, -1.0, -1.0) * 0.5)
+            .with_scale(Vec3::splat(0.5)),
+    );
+    let outline = chunk_outline_transform(&transform, 32.0);
+    assert_eq!(outline.scale, Vec3::splat(16.0));
+    assert_eq!(outline.translation, Vec3::new(24.0, 8.0, 8.0));
+}
+
+#[derive(Resource, Clone, Default)]
+struct LitWorld;
+
+impl VoxelWorldConfig for LitWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn bake_voxel_light(&self) -> bool {
+        true
+    }
+
+    fn voxel_light(&self, pos: IVec3, _voxel: &WorldVoxel<Self::MaterialIndex>) -> u8 {
+        // Lights the whole chunk at the origin, including its padding
+        if pos.x < 48 {
+            u8::MAX
+        } else {
+            0
+        }
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < -16 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn chunks_with_baked_light_get_their_own_meshes() {
+    use crate::mesh_cache::MeshRef;
+    use crate::voxel_material::ATTRIBUTE_VOXEL_LIGHT;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let mut app = mesh_spawning_app::<LitWorld>();
+
+    // Same voxels, but only the first chunk is lit
+    let chunk_positions = [IVec3::new(0, -1, 0), IVec3::new(2, -1, 0)];
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<LitWorld>| {
+            for chunk_pos in chunk_positions {
+                voxel_world.load_chunk(chunk_pos);
+            }
+        })
+        .unwrap();
+    assert!(run_until(&mut app, 1000, |app| {
+        app.world_mut()
+            .query::<&MeshRef>()
+            .iter(app.world())
+            .count()
+            == 2
+    }));
+
+    let entities = app
+        .world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<LitWorld>| {
+            chunk_positions.map(|chunk_pos| {
+                voxel_world.get_chunk_data(chunk_pos).unwrap().get_entity()
+            })
+        })
+        .unwrap();
+    let lights = entities.map(|entity| {
+        let mesh_ref = app.world().get::<MeshRef>(entity).unwrap();
+        let meshes = app.world().resource::<Assets<Mesh>>();
+        let mesh = meshes.get(mesh_ref.0.as_ref()).unwrap();
+        let Some(VertexAttributeValues::Float32(lights)) =
+            mesh.attribute(ATTRIBUTE_VOXEL_LIGHT)
+        else {
+            panic!("mesh has no voxel light");
+        };
+        lights.clone()
+    });
+    assert!(lights[0].iter().all(|light| *light == 1.0));
+    assert!(lights[1].iter().all(|light| *light == 0.0));
+}
+
+#[derive(Resource, Clone, Default)]
+struct SolidBlockWorld;
+
+impl VoxelWorldConfig for SolidBlockWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Solid(0)))
+    }
+}
+
+#[test]
+fn clearing_a_chunk_remeshes_its_neighbours() {
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = mesh_spawning_app::<SolidBlockWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<SolidBlockWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+            voxel_world.load_chunk(IVec3::X);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<SolidBlockWorld>(
+        &mut app, 2, 1000
+    ));
+
+    // Both chunks are full, so neither has a mesh
+    let neighbour = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<SolidBlockWorld>| {
+            voxel_world.get_chunk_data(IVec3::X).unwrap().get_entity()
+        })
+        .unwrap();
+    assert!(app.world().get::<MeshRef>(neighbour).is_none());
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<SolidBlockWorld>| {
+            voxel_world.fill_box(IVec3::ZERO, IVec3::splat(31), WorldVoxel::Air);
+        })
+        .unwrap();
+
+    // The faces of the neighbour that face the cleared chunk are now exposed
+    assert!(run_until(&mut app, 1000, |app| {
+        app.world().get::<MeshRef>(neighbour).is_some()
+    }));
+}
+
+#[test]
+fn border_edit_remeshes_neighbour_chunk() {
+    use crate::chunk::NeedsRemesh;
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+            voxel_world.load_chunk(IVec3::NEG_X);
+            voxel_world.load_chunk(IVec3::X);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 3, 100));
+
+    let [chunk, neg_x_neighbour, pos_x_neighbour] = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            [IVec3::ZERO, IVec3::NEG_X, IVec3::X].map(|chunk_pos| {
+                voxel_world.get_chunk_data(chunk_pos).unwrap().get_entity()
+            })
+        })
+        .unwrap();
+
+    // Local x = 0 is on the border with the -X neighbour
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(0, 5, 5), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.world_mut()
+        .run_system_once(Internals::<DefaultWorld>::flush_voxel_write_buffer)
+        .unwrap();
+
+    assert!(app.world().get::<NeedsRemesh>(chunk).is_some());
+    assert!(app.world().get::<NeedsRemesh>(neg_x_neighbour).is_some());
+    assert!(app.world().get::<NeedsRemesh>(pos_x_neighbour).is_none());
+}
+
+#[derive(Resource, Clone, Default)]
+struct CustomAabbWorld;
+
+impl VoxelWorldConfig for CustomAabbWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+
+    fn chunk_meshing_delegate(
+        &self,
+    ) -> ChunkMeshingDelegate<Self::MaterialIndex, Self::ChunkUserBundle> {
+        Some(Box::new(|pos: IVec3| {
+            Box::new(
+                move |voxels: crate::custom_meshing::VoxelArray<u8>,
+                      shape: ndshape::RuntimeShape<u32, 3>,
+                      texture_index_mapper: TextureIndexMapperFn<u8>,
+                      neighbours: Option<crate::custom_meshing::ChunkNeighbours<u8>>| {
+                    let (mesh, user_bundle, _) = default_chunk_meshing_delegate::<u8, ()>(
+                        pos,
+                    )(
+                        voxels, shape, texture_index_mapper, neighbours,
+                    );
+                    let aabb = bevy::render::primitives::Aabb::from_min_max(
+                        Vec3::splat(-100.0),
+                        Vec3::splat(100.0),
+                    );
+                    (mesh, user_bundle, Some(aabb))
+                },
+            )
+        }))
+    }
+}
+
+#[test]
+fn meshing_delegate_aabb_is_applied() {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::primitives::Aabb;
+
+    let mut app = mesh_spawning_app::<CustomAabbWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<CustomAabbWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+
+    assert!(run_until(&mut app, 1000, |app| first_meshed_chunk(app).is_some()));
+
+    let meshed_chunk = first_meshed_chunk(&mut app).unwrap();
+    let aabb = app.world().get::<Aabb>(meshed_chunk).unwrap();
+    assert_eq!(
+        *aabb,
+        Aabb::from_min_max(Vec3::splat(-100.0), Vec3::splat(100.0))
+    );
+}
+
+#[derive(Resource, Clone, Default)]
+struct DetailUvWorld;
+
+impl VoxelWorldConfig for DetailUvWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn detail_uv_tiling(&self) -> Option<f32> {
+        Some(0.25)
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < 0 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn detail_uvs_are_added_when_enabled() {
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let mut app = mesh_spawning_app::<DetailUvWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DetailUvWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+
+    assert!(run_until(&mut app, 1000, |app| first_meshed_chunk(app).is_some()));
+
+    let meshed_chunk = first_meshed_chunk(&mut app).unwrap();
+    let mesh_handle = app.world().get::<MeshRef>(meshed_chunk).unwrap().0.clone();
+    let meshes = app.world().resource::<Assets<Mesh>>();
+    let mesh = meshes.get(mesh_handle.as_ref()).unwrap();
+    let Some(VertexAttributeValues::Float32x2(uvs)) =
+        mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+    else {
+        panic!("mesh has no UV_0");
+    };
+    let Some(VertexAttributeValues::Float32x2(detail_uvs)) =
+        mesh.attribute(Mesh::ATTRIBUTE_UV_1)
+    else {
+        panic!("mesh has no UV_1");
+    };
+    assert_eq!(uvs.len(), detail_uvs.len());
+    for (uv, detail_uv) in uvs.iter().zip(detail_uvs) {
+        assert_eq!([uv[0] * 0.25, uv[1] * 0.25], *detail_uv);
+    }
+}
+
+#[test]
+fn chunk_mesh_ready_event_carries_mesh_and_bounds() {
+    use crate::chunk::ChunkThread;
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = mesh_spawning_app::<MeshBudgetWorld>();
+
+    // The first chunk has a surface at its top, the second one is completely solid
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<MeshBudgetWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+            voxel_world.load_chunk(IVec3::new(0, -3, 0));
+        })
+        .unwrap();
+
+    let mut ready = Vec::new();
+    for _ in 0..1000 {
+        app.update();
+        let events = app
+            .world()
+            .resource::<Events<ChunkMeshReady<MeshBudgetWorld>>>();
+        ready.extend(events.iter_current_update_events().map(|event| {
+            (
+                event.chunk_key,
+                event.entity,
+                event.mesh.clone(),
+                event.aabb,
+            )
+        }));
+        let chunk_tasks = app
+            .world_mut()
+            .query::<&ChunkThread<MeshBudgetWorld, u8>>()
+            .iter(app.world())
+            .count();
+        if !ready.is_empty() && chunk_tasks == 0 {
+            break;
+        }
+        std::thread::yield_now();
+    }
+
+    assert_eq!(ready.len(), 1);
+    let (chunk_key, entity, mesh, aabb) = ready.pop().unwrap();
+    assert_eq!(chunk_key, IVec3::new(0, -1, 0));
+    assert_eq!(
+        app.world().get::<MeshRef>(entity).unwrap().0.as_ref(),
+        &mesh
+    );
+    assert!(app.world().resource::<Assets<Mesh>>().contains(&mesh));
+    assert!(aabb.half_extents.x > 0.0);
+    assert!(aabb.half_extents.z > 0.0);
+}
+
+#[derive(Resource, Clone, Default)]
+struct SolidWorld;
+
+impl VoxelWorldConfig for SolidWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Solid(0)))
+    }
+}
+
+#[test]
+fn chunks_spawn_around_camera_inside_solid_terrain() {
+    let mut app = spawn_test_app::<SolidWorld>();
+
+    // All chunks within the spawning distance of the camera chunk, including the ones behind
+    // the camera, which the viewport rays never reach
+    assert!(run_until_chunks_loaded::<SolidWorld>(&mut app, 33, 1000));
+    assert_eq!(loaded_chunk_count::<SolidWorld>(&app), 33);
+
+    let chunk_map = app
+        .world()
+        .resource::<crate::chunk_map::ChunkMap<SolidWorld, u8>>();
+    let read_lock = chunk_map.get_read_lock();
+    for behind_camera in [
+        IVec3::new(2, 0, 0),
+        IVec3::new(0, 2, 0),
+        IVec3::new(0, 0, 2),
+    ] {
+        assert!(
+            crate::chunk_map::ChunkMap::<SolidWorld, u8>::contains_chunk(
+                &behind_camera,
+                &read_lock
+            )
+        );
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct SeededWorld;
+
+impl VoxelWorldConfig for SeededWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        3
+    }
+
+    fn spawn_seed(&self) -> Option<u64> {
+        Some(42)
+    }
+}
+
+#[test]
+fn seeded_chunk_spawning_is_deterministic() {
+    use crate::chunk::Chunk;
+    use crate::voxel_world_internal::ChunkSpawnRng;
+    use rand::Rng;
+
+    let run = || {
+        let mut app = spawn_test_app::<SeededWorld>();
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let mut chunks = app
+            .world_mut()
+            .query::<(Entity, &Chunk<SeededWorld>)>()
+            .iter(app.world())
+            .map(|(entity, chunk)| (entity.index(), chunk.position))
+            .collect::<Vec<_>>();
+        chunks.sort_by_key(|(index, _)| *index);
+
+        let next_random = app
+            .world_mut()
+            .resource_mut::<ChunkSpawnRng<SeededWorld>>()
+            .gen::<u64>();
+
+        (chunks, next_random)
+    };
+
+    let (first_chunks, first_random) = run();
+    let (second_chunks, second_random) = run();
+
+    assert!(!first_chunks.is_empty());
+    assert_eq!(first_chunks, second_chunks);
+    assert_eq!(first_random, second_random);
+}
+
+#[test]
+fn voxels_slice_matches_get_voxel() {
+    use crate::chunk::padded_chunk_shape_uniform;
+    use ndshape::Shape;
+
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos.y < pos.x / 4 {
+            WorldVoxel::Solid(pos.z.rem_euclid(4) as u8)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+    let chunk_data = chunk_task.chunk_data;
+
+    let shape = padded_chunk_shape_uniform(chunk_data.chunk_size());
+    let voxels = chunk_data.voxels_slice().unwrap();
+    assert_eq!(voxels.len(), shape.size() as usize);
+    for (index, voxel) in voxels.iter().enumerate() {
+        let position = UVec3::from(shape.delinearize(index as u32));
+        assert_eq!(*voxel, chunk_data.get_voxel(position));
+    }
+
+    // Compressed chunks have no raw voxel array, and are decoded into a new one
+    let mut compressed = chunk_data.clone();
+    compressed.compress();
+    assert!(compressed.voxels_slice().is_none());
+    assert_eq!(*compressed.decoded_voxels().unwrap(), *voxels);
+}
+
+#[test]
+fn raycast_sees_latest_pending_writes() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            // 20 * 10 * 25 = 5000 pending writes
+            for x in 0..20 {
+                for y in 0..10 {
+                    for z in 0..25 {
+                        voxel_world.set_voxel(IVec3::new(x, y, z), WorldVoxel::Solid(1));
+                    }
+                }
+            }
+            voxel_world.set_voxel(IVec3::new(5, 5, 24), WorldVoxel::Solid(2));
+            voxel_world.set_voxel(IVec3::new(6, 5, 24), WorldVoxel::Air);
+
+            let ray = |x: f32| Ray3d {
+                origin: Vec3::new(x, 5.5, 31.0),
+                direction: -Dir3::Z,
+            };
+
+            let result = voxel_world.raycast(ray(5.5), &|_| true).unwrap();
+            assert_eq!(result.voxel_pos(), IVec3::new(5, 5, 24));
+            assert_eq!(result.voxel, WorldVoxel::Solid(2));
+
+            let result = voxel_world.raycast(ray(6.5), &|_| true).unwrap();
+            assert_eq!(result.voxel_pos(), IVec3::new(6, 5, 23));
+            assert_eq!(result.voxel, WorldVoxel::Solid(1));
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct FallbackWorld;
+
+impl VoxelWorldConfig for FallbackWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn modification_precedence(&self) -> ModificationPrecedence {
+        ModificationPrecedence::Fallback
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(move |_| {
+            Box::new(move |pos| {
+                if pos.y < 4 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Unset
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn fallback_precedence_lets_lookup_override_modifications() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<FallbackWorld>();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<FallbackWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Air);
+            voxel_world.set_voxel(IVec3::new(1, 6, 1), WorldVoxel::Solid(3));
+        })
+        .unwrap();
+    app.update();
+
+    force_chunk_into_map::<FallbackWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<FallbackWorld>| {
+            let chunk_data = voxel_world.get_chunk_data(IVec3::ZERO).unwrap();
+            // Chunk data is indexed with the one voxel padding
+            // The lookup returns a voxel here, so the modification is ignored
+            assert_eq!(
+                chunk_data.get_voxel(UVec3::new(2, 2, 2)),
+                WorldVoxel::Solid(0)
+            );
+            assert_eq!(
+                chunk_data.get_voxel(UVec3::new(2, 7, 2)),
+                WorldVoxel::Solid(3)
+            );
+            assert_eq!(chunk_data.get_voxel(UVec3::new(3, 7, 2)), WorldVoxel::Unset);
+
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(1, 1, 1)),
+                WorldVoxel::Solid(0)
+            );
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(1, 6, 1)),
+                WorldVoxel::Solid(3)
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct FallbackFillWorld;
+
+impl VoxelWorldConfig for FallbackFillWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn modification_precedence(&self) -> ModificationPrecedence {
+        ModificationPrecedence::Fallback
+    }
+
+    fn default_solid_below(&self) -> Option<(i32, Self::MaterialIndex)> {
+        Some((4, 3))
+    }
+}
+
+#[test]
+fn fallback_precedence_applies_modifications_before_the_default_fill() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<FallbackFillWorld>();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<FallbackFillWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Air);
+            voxel_world.set_voxel(IVec3::new(1, 6, 1), WorldVoxel::Solid(2));
+        })
+        .unwrap();
+    app.update();
+
+    force_chunk_into_map::<FallbackFillWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<FallbackFillWorld>| {
+            let chunk_data = voxel_world.get_chunk_data(IVec3::ZERO).unwrap();
+            // Chunk data is indexed with the one voxel padding
+            assert_eq!(chunk_data.get_voxel(UVec3::new(2, 2, 2)), WorldVoxel::Air);
+            assert_eq!(
+                chunk_data.get_voxel(UVec3::new(2, 7, 2)),
+                WorldVoxel::Solid(2)
+            );
+            assert_eq!(
+                chunk_data.get_voxel(UVec3::new(3, 2, 2)),
+                WorldVoxel::Solid(3)
+            );
+            assert_eq!(chunk_data.get_voxel(UVec3::new(3, 7, 2)), WorldVoxel::Air);
+
+            assert_eq!(voxel_world.get_voxel(IVec3::new(1, 1, 1)), WorldVoxel::Air);
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(1, 6, 1)),
+                WorldVoxel::Solid(2)
+            );
+        })
+        .unwrap();
+}
+
+#[test]
+fn voxel_world_reader_sees_pending_writes() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 5, 5), WorldVoxel::Solid(2));
+        })
+        .unwrap();
+
+    app.world_mut()
+        .run_system_once(|reader: VoxelWorldReader<DefaultWorld>| {
+            assert_eq!(reader.get_voxel(IVec3::new(5, 5, 5)), WorldVoxel::Solid(2));
+            assert_eq!(
+                reader.get_voxel_fn()(IVec3::new(5, 5, 5)),
+                WorldVoxel::Solid(2)
+            );
+            assert!(reader.get_chunk_data(IVec3::ZERO).is_some());
+
+            let ray = Ray3d {
+                origin: Vec3::new(5.5, 5.5, 31.0),
+                direction: -Dir3::Z,
+            };
+            let result = reader.raycast(ray, &|_| true).unwrap();
+            assert_eq!(result.voxel_pos(), IVec3::new(5, 5, 5));
+            assert_eq!(result.voxel, WorldVoxel::Solid(2));
+        })
+        .unwrap();
+}
+
+#[test]
+fn restore_chunks_returns_to_captured_state() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    let edited = IVec3::new(1, 1, 1);
+    let added = IVec3::new(2, 2, 2);
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(edited, WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+
+    let (snapshot, added_before) = app
+        .world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            (
+                voxel_world.capture_chunks(&[IVec3::ZERO]),
+                voxel_world.get_voxel(added),
+            )
+        })
+        .unwrap();
+    assert_eq!(
+        snapshot.modified_voxels().get(&edited),
+        Some(&WorldVoxel::Solid(1))
+    );
+
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(edited, WorldVoxel::Solid(2));
+            voxel_world.set_voxel(added, WorldVoxel::Solid(3));
+        })
+        .unwrap();
+    for _ in 0..5 {
+        app.update();
+    }
+
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.restore_chunks(&snapshot);
+        })
+        .unwrap();
+
+    for _ in 0..5 {
+        app.update();
+        app.world_mut()
+            .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+                assert_eq!(voxel_world.get_voxel(edited), WorldVoxel::Solid(1));
+                assert_eq!(voxel_world.get_voxel(added), added_before);
+                assert_eq!(
+                    voxel_world.export_modified(),
+                    vec![(edited, WorldVoxel::Solid(1))]
+                );
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn jittered_grid_points_cover_every_cell() {
+    use crate::voxel_world_internal::jittered_grid_points;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let min = Vec2::new(-25.0, -25.0);
+    let size = Vec2::new(1650.0, 950.0);
+    let points = jittered_grid_points(min, size, 100, &mut rng);
+
+    // 13 columns and 7 rows for a 100 ray budget at this aspect ratio
+    assert_eq!(points.len(), 13 * 7);
+    let cell_size = size / Vec2::new(13.0, 7.0);
+    let mut cells = std::collections::HashSet::new();
+    for point in points {
+        assert!(point.cmpge(min).all() && point.cmplt(min + size).all());
+        let cell = ((point - min) / cell_size).floor().as_ivec2();
+        assert!(cells.insert(cell));
+    }
+    assert_eq!(cells.len(), 13 * 7);
+
+    assert!(jittered_grid_points(min, Vec2::ZERO, 100, &mut rng).is_empty());
+}
+
+#[derive(Resource, Clone, Default)]
+struct NeighbourMeshingWorld<const NEEDS_NEIGHBOURS: bool = true> {
+    /// The neighbours the chunk below the origin was last meshed with, if it was meshed
+    origin_neighbours: std::sync::Arc<std::sync::Mutex<Option<Option<Vec<IVec3>>>>>,
+}
+
+impl<const NEEDS_NEIGHBOURS: bool> VoxelWorldConfig
+    for NeighbourMeshingWorld<NEEDS_NEIGHBOURS>
+{
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < -16 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+
+    fn chunk_meshing_delegate(
+        &self,
+    ) -> ChunkMeshingDelegate<Self::MaterialIndex, Self::ChunkUserBundle> {
+        let origin_neighbours = self.origin_neighbours.clone();
+        Some(Box::new(move |pos: IVec3| {
+            let origin_neighbours = origin_neighbours.clone();
+            Box::new(
+                move |voxels: crate::custom_meshing::VoxelArray<u8>,
+                      shape: ndshape::RuntimeShape<u32, 3>,
+                      texture_index_mapper: TextureIndexMapperFn<u8>,
+                      neighbours: Option<crate::custom_meshing::ChunkNeighbours<u8>>| {
+                    if pos == IVec3::new(0, -1, 0) {
+                        *origin_neighbours.lock().unwrap() = Some(neighbours.as_ref().map(|n| {
+                            n.iter().map(|(direction, _)| direction).collect()
+                        }));
+                    }
+                    default_chunk_meshing_delegate::<u8, ()>(pos)(
+                        voxels,
+                        shape,
+                        texture_index_mapper,
+                        neighbours,
+                    )
+                },
+            )
+        }))
+    }
+
+    fn chunk_meshing_needs_neighbours(&self) -> bool {
+        NEEDS_NEIGHBOURS
+    }
+}
+
+#[test]
+fn meshing_delegate_only_gets_neighbours_when_it_needs_them() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<NeighbourMeshingWorld<false>>::minimal(),
+    ));
+    app.add_systems(
+        Update,
+        Internals::<NeighbourMeshingWorld<false>>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(
+            |mut voxel_world: VoxelWorld<NeighbourMeshingWorld<false>>| {
+                voxel_world.load_chunk(IVec3::new(1, -1, 0));
+                voxel_world.load_chunk(IVec3::new(0, -1, 0));
+            },
+        )
+        .unwrap();
+    assert!(run_until_chunks_loaded::<NeighbourMeshingWorld<false>>(
+        &mut app, 2, 1000
+    ));
+
+    let origin_neighbours = app
+        .world()
+        .resource::<NeighbourMeshingWorld<false>>()
+        .origin_neighbours
+        .clone();
+    assert_eq!(*origin_neighbours.lock().unwrap(), Some(None));
+}
+
+#[test]
+fn meshing_delegate_gets_loaded_neighbours() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<NeighbourMeshingWorld>::minimal(),
+    ));
+    app.add_systems(
+        Update,
+        Internals::<NeighbourMeshingWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<NeighbourMeshingWorld>| {
+            voxel_world.load_chunk(IVec3::new(1, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<NeighbourMeshingWorld>(
+        &mut app, 1, 1000
+    ));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<NeighbourMeshingWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<NeighbourMeshingWorld>(
+        &mut app, 2, 1000
+    ));
+
+    let origin_neighbours = app
+        .world()
+        .resource::<NeighbourMeshingWorld>()
+        .origin_neighbours
+        .clone();
+    assert_eq!(
+        *origin_neighbours.lock().unwrap(),
+        Some(Some(vec![IVec3::X]))
+    );
+}
+
+#[test]
+fn meshing_delegate_remeshes_when_a_neighbour_generates() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<NeighbourMeshingWorld>::minimal(),
+    ));
+    app.add_systems(
+        Update,
+        Internals::<NeighbourMeshingWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    let origin_neighbours = app
+        .world()
+        .resource::<NeighbourMeshingWorld>()
+        .origin_neighbours
+        .clone();
+
+    // The chunk is meshed without neighbours first
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<NeighbourMeshingWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<NeighbourMeshingWorld>(
+        &mut app, 1, 1000
+    ));
+    assert_eq!(*origin_neighbours.lock().unwrap(), Some(Some(vec![])));
+
+    // Then again once the neighbour has generated
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<NeighbourMeshingWorld>| {
+            voxel_world.load_chunk(IVec3::new(1, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until(&mut app, 1000, |_| {
+        *origin_neighbours.lock().unwrap() == Some(Some(vec![IVec3::X]))
+    }));
+}
+
+#[test]
+fn diagnostics_count_chunk_work() {
+    use bevy::diagnostic::DiagnosticsStore;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 10, 1000));
+    app.update();
+
+    let diagnostics = app
+        .world()
+        .resource::<VoxelWorldDiagnostics<DefaultWorld>>();
+    assert!(diagnostics.chunks_spawned() >= 10);
+    assert!(diagnostics.chunks_remeshed() >= 10);
+    assert!(diagnostics.average_task_time() > std::time::Duration::ZERO);
+
+    let store = app.world().resource::<DiagnosticsStore>();
+    for measurement in [
+        VoxelWorldDiagnostics::<DefaultWorld>::CHUNKS_SPAWNED,
+        VoxelWorldDiagnostics::<DefaultWorld>::CHUNK_TASK_TIME,
+    ] {
+        let diagnostic = store
+            .get(&VoxelWorldDiagnostics::<DefaultWorld>::diagnostic_path(
+                measurement,
+            ))
+            .unwrap();
+        assert!(diagnostic.measurement().is_some());
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct FlatTerrainWorld;
+
+impl VoxelWorldConfig for FlatTerrainWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            Box::new(|pos| {
+                if pos.y < -16 {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn diagnostics_count_meshes_taken_from_the_mesh_cache() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = mesh_spawning_app::<FlatTerrainWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<FlatTerrainWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until(&mut app, 1000, |app| first_meshed_chunk(app).is_some()));
+    app.update();
+
+    // A chunk with the same voxels gets the cached mesh
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<FlatTerrainWorld>| {
+            voxel_world.load_chunk(IVec3::new(1, -1, 0));
+        })
+        .unwrap();
+    assert!(run_until(&mut app, 1000, |app| {
+        let diagnostics = app
+            .world()
+            .resource::<VoxelWorldDiagnostics<FlatTerrainWorld>>();
+        diagnostics.mesh_cache_hits() + diagnostics.mesh_cache_misses() == 2
+    }));
+
+    let diagnostics = app
+        .world()
+        .resource::<VoxelWorldDiagnostics<FlatTerrainWorld>>();
+    assert_eq!(diagnostics.mesh_cache_hits(), 1);
+    assert_eq!(diagnostics.mesh_cache_misses(), 1);
+    assert_eq!(diagnostics.mesh_cache_hit_rate(), Some(0.5));
+}
+
+#[derive(Resource, Clone, Default)]
+struct GenericWorld<T: Send + Sync + 'static>(std::marker::PhantomData<T>);
+
+impl<T: Send + Sync + Default + Clone + 'static> VoxelWorldConfig for GenericWorld<T> {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+}
+
+#[test]
+fn diagnostic_paths_tell_generic_worlds_apart() {
+    let path = |path: bevy::diagnostic::DiagnosticPath| path.as_str().to_string();
+    let u8_world = path(VoxelWorldDiagnostics::<GenericWorld<u8>>::diagnostic_path(
+        VoxelWorldDiagnostics::<GenericWorld<u8>>::CHUNKS_SPAWNED,
+    ));
+    let u16_world = path(VoxelWorldDiagnostics::<GenericWorld<u16>>::diagnostic_path(
+        VoxelWorldDiagnostics::<GenericWorld<u16>>::CHUNKS_SPAWNED,
+    ));
+
+    assert_ne!(u8_world, u16_world);
+    assert!(u8_world.starts_with("voxel_world/"));
+    assert!(u8_world.ends_with("/chunks_spawned"));
+    assert!(u8_world.contains("GenericWorld<u8>"));
+    assert!(!u8_world.contains("::"));
+}
+
+#[test]
+fn set_voxel_immediate_skips_write_buffer() {
+    use crate::chunk::NeedsRemesh;
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 1, 100));
+
+    let chunk = app
+        .world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 5, 5), WorldVoxel::Solid(1));
+            voxel_world.set_voxel_immediate(IVec3::new(5, 5, 5), WorldVoxel::Solid(2));
+            voxel_world
+                .get_chunk_data(IVec3::ZERO)
+                .unwrap()
+                .get_entity()
+        })
+        .unwrap();
+
+    // Without an update in between, the voxel is already in the modified voxels
+    let modified_voxels = app
+        .world()
+        .resource::<crate::voxel_world_internal::ModifiedVoxels<DefaultWorld, u8>>();
+    assert_eq!(
+        modified_voxels.get_voxel(&IVec3::new(5, 5, 5)),
+        Some(WorldVoxel::Solid(2))
+    );
+    assert!(app.world().get::<NeedsRemesh>(chunk).is_some());
+
+    // The earlier buffered write doesn't replace it when the buffer is flushed
+    app.update();
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(5, 5, 5)),
+                WorldVoxel::Solid(2)
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct ExtraAttributesWorld;
+
+const ATTRIBUTE_TEST_EXTRA: bevy::render::mesh::MeshVertexAttribute =
+    bevy::render::mesh::MeshVertexAttribute::new(
+        "TestExtra",
+        3412870935,
+        bevy::render::render_resource::VertexFormat::Float32,
+    );
+
+impl VoxelWorldConfig for ExtraAttributesWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn extra_vertex_attributes(&self) -> Vec<bevy::render::mesh::MeshVertexAttribute> {
+        vec![ATTRIBUTE_TEST_EXTRA]
+    }
+}
+
+#[test]
+fn extra_vertex_attributes_follow_the_default_layout() {
+    use crate::custom_meshing::generate_chunk_mesh;
+    use crate::rendering::{
+        vertex_layout, world_vertex_layout, FIRST_EXTRA_SHADER_LOCATION,
+    };
+    use bevy::render::mesh::MeshVertexBufferLayouts;
+
+    let layout = world_vertex_layout(&ExtraAttributesWorld);
+    assert_eq!(layout.len(), vertex_layout().len() + 1);
+    let extra = layout.last().unwrap();
+    assert_eq!(extra.id, ATTRIBUTE_TEST_EXTRA.id);
+    assert_eq!(extra.shader_location, FIRST_EXTRA_SHADER_LOCATION);
+    assert!(vertex_layout()
+        .iter()
+        .all(|attribute| attribute.shader_location < FIRST_EXTRA_SHADER_LOCATION));
+
+    let chunk_task = generated_task::<ExtraAttributesWorld>(IVec3::ZERO, |pos| {
+        if pos.y < 10 {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+
+    let mut mesh = generate_chunk_mesh(
+        chunk_task.chunk_data.voxels.clone().unwrap(),
+        IVec3::ZERO,
+        std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+    );
+    let vertex_count = mesh.count_vertices();
+    mesh.insert_attribute(ATTRIBUTE_TEST_EXTRA, vec![0.0; vertex_count]);
+    let mesh_layout =
+        mesh.get_mesh_vertex_buffer_layout(&mut MeshVertexBufferLayouts::default());
+
+    // The default material only asks for the attributes it knows about
+    let default_attributes = vertex_layout()
+        .into_iter()
+        .filter(|attribute| mesh_layout.0.contains(attribute.id))
+        .collect::<Vec<_>>();
+    assert!(mesh_layout.0.get_layout(&default_attributes).is_ok());
+
+    // A custom material gets the extra attribute as well
+    let custom_attributes = layout
+        .into_iter()
+        .filter(|attribute| mesh_layout.0.contains(attribute.id))
+        .collect::<Vec<_>>();
+    let buffer_layout = mesh_layout.0.get_layout(&custom_attributes).unwrap();
+    assert!(buffer_layout
+        .attributes
+        .iter()
+        .any(|attribute| attribute.shader_location == FIRST_EXTRA_SHADER_LOCATION));
+}
+
+#[derive(Resource, Clone, Default)]
+struct RemeshBudgetWorld;
+
+impl VoxelWorldConfig for RemeshBudgetWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn max_remesh_tasks_per_frame(&self) -> usize {
+        2
+    }
+}
+
+#[test]
+fn remesh_dirty_chunks_respects_task_budget() {
+    use crate::chunk::{ChunkThread, NeedsRemesh};
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<RemeshBudgetWorld>::minimal(),
+    ));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<RemeshBudgetWorld>| {
+            for x in 0..5 {
+                voxel_world.load_chunk(IVec3::new(x, 0, 0));
+            }
+        })
+        .unwrap();
+
+    // Finished tasks are never picked up without `spawn_meshes`, so the tasks add up
+    let task_count = |app: &mut App| {
+        app.world_mut()
+            .query::<&ChunkThread<RemeshBudgetWorld, u8>>()
+            .iter(app.world())
+            .count()
+    };
+    let waiting_count = |app: &mut App| {
+        app.world_mut()
+            .query_filtered::<(), (With<Chunk<RemeshBudgetWorld>>, With<NeedsRemesh>)>()
+            .iter(app.world())
+            .count()
+    };
+
+    app.update();
+    assert_eq!(task_count(&mut app), 2);
+    assert_eq!(waiting_count(&mut app), 3);
+
+    app.update();
+    assert_eq!(task_count(&mut app), 4);
+    assert_eq!(waiting_count(&mut app), 1);
+
+    app.update();
+    assert_eq!(task_count(&mut app), 5);
+    assert_eq!(waiting_count(&mut app), 0);
+}
+
+#[test]
+fn apply_heightmap_builds_columns() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = _test_setup_app();
+    app.update();
+
+    // A ramp rising along x, with a flat row of empty columns at z = 3
+    let heights = [
+        1, 2, 3, 4, //
+        1, 2, 3, 4, //
+        1, 2, 3, 4, //
+        0, 0, 0, 0, //
+    ];
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.apply_heightmap(
+                IVec3::new(10, 5, 20),
+                4,
+                4,
+                &heights,
+                WorldVoxel::Solid(1),
+                WorldVoxel::Solid(2),
+            );
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            // Height 1 is only the surface voxel
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(10, 5, 20)),
+                WorldVoxel::Solid(1)
+            );
+            assert!(voxel_world.get_voxel(IVec3::new(10, 6, 20)).is_unset());
+
+            // Height 4 is three fill voxels below the surface
+            for y in 5..8 {
+                assert_eq!(
+                    voxel_world.get_voxel(IVec3::new(13, y, 22)),
+                    WorldVoxel::Solid(2)
+                );
+            }
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(13, 8, 22)),
+                WorldVoxel::Solid(1)
+            );
+            assert!(voxel_world.get_voxel(IVec3::new(13, 9, 22)).is_unset());
+
+            // Columns with height 0 are skipped
+            assert!(voxel_world.get_voxel(IVec3::new(12, 5, 23)).is_unset());
+
+            assert_eq!(voxel_world.export_modified().len(), 3 * (1 + 2 + 3 + 4));
+        })
+        .unwrap();
+}
+
+#[test]
+fn apply_heightmap_ignores_a_heightmap_of_the_wrong_size() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = _test_setup_app();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            // One row short of a 4x4 heightmap
+            voxel_world.apply_heightmap(
+                IVec3::ZERO,
+                4,
+                4,
+                &[3; 12],
+                WorldVoxel::Solid(1),
+                WorldVoxel::Solid(2),
+            );
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert!(voxel_world.export_modified().is_empty());
+        })
+        .unwrap();
+}
+
+#[test]
+fn surface_voxels_in_finds_exposed_tops() {
+    let mut app = spawn_test_app::<DefaultWorld>();
+
+    // Runs before any chunk has been spawned
+    app.add_systems(Startup, |mut voxel_world: VoxelWorld<DefaultWorld>| {
+        // A stack of two, a single voxel under water, and a voxel just above the box
+        voxel_world.set_voxel(IVec3::new(1, 0, 1), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(2, 0, 2), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(2, 1, 2), WorldVoxel::Translucent(3));
+        voxel_world.set_voxel(IVec3::new(0, 3, 0), WorldVoxel::Solid(1));
+        voxel_world.set_voxel(IVec3::new(0, 2, 0), WorldVoxel::Solid(1));
+
+        assert_eq!(
+            voxel_world.surface_voxels_in(IVec3::new(3, 2, 3), IVec3::ZERO),
+            vec![IVec3::new(1, 1, 1)]
+        );
+
+        assert_eq!(
+            voxel_world.surface_voxels_in(IVec3::ZERO, IVec3::new(3, 3, 3)),
+            vec![IVec3::new(0, 3, 0), IVec3::new(1, 1, 1)]
+        );
+    });
+
+    app.update();
+}
+
+#[test]
+fn clear_despawns_all_chunks() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    // No camera, so nothing spawns chunks again after the clear
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            for x in 0..4 {
+                voxel_world.load_chunk(IVec3::new(x, 0, 0));
+            }
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 4, 100));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(2, 2, 2), WorldVoxel::Solid(1));
+            voxel_world.clear();
+        })
+        .unwrap();
+    app.update();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(voxel_world.loaded_chunk_count(), 0);
+            assert!(voxel_world.export_modified().is_empty());
+            assert!(voxel_world.get_voxel(IVec3::new(1, 1, 1)).is_unset());
+        })
+        .unwrap();
+    let chunk_entities = app
+        .world_mut()
+        .query::<&Chunk<DefaultWorld>>()
+        .iter(app.world())
+        .count();
+    assert_eq!(chunk_entities, 0);
+}
+
+#[test]
+fn ao_curve_sets_vertex_color_brightness() {
+    use crate::chunk::padded_chunk_shape_uniform;
+    use crate::custom_meshing::{
+        generate_chunk_mesh_with_options, ChunkMeshOptions, DEFAULT_AO_CURVE,
+    };
+    use bevy::render::mesh::VertexAttributeValues;
+
+    // A floor with a single voxel on it, so the floor around it is occluded
+    let chunk_task = generated_task::<DefaultWorld>(IVec3::ZERO, |pos| {
+        if pos.y < 10 || pos == IVec3::new(5, 10, 5) {
+            WorldVoxel::Solid(0)
+        } else {
+            WorldVoxel::Air
+        }
+    });
+    let voxels = chunk_task.chunk_data.voxels.clone().unwrap();
+
+    let colors = |ao_curve: [f32; 4]| {
+        let mesh = generate_chunk_mesh_with_options(
+            voxels.clone(),
+            padded_chunk_shape_uniform(32),
+            std::sync::Arc::new(|mat: u8| [mat as u32; 3]),
+            &ChunkMeshOptions {
+                ao_curve,
+                ..default()
+            },
+        );
+        let Some(VertexAttributeValues::Float32x4(colors)) =
+            mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            panic!("mesh has no vertex colors");
+        };
+        colors.clone()
+    };
+
+    let occluded = colors(DEFAULT_AO_CURVE);
+    assert!(occluded.iter().any(|color| color[0] < 1.0));
+    assert!(occluded
+        .iter()
+        .all(|color| DEFAULT_AO_CURVE.contains(&color[0])));
+
+    // With AO turned off, all vertices are fully lit
+    let unoccluded = colors([1.0; 4]);
+    assert_eq!(unoccluded.len(), occluded.len());
+    assert!(unoccluded.iter().flatten().all(|value| *value == 1.0));
+}
+
+#[derive(Resource, Clone, Default)]
+struct NoAoWorld;
+
+impl VoxelWorldConfig for NoAoWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn ao_enabled(&self) -> bool {
+        false
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| {
+            // A floor with a single voxel on it, which would occlude the floor around it
+            Box::new(|pos| {
+                if pos.y < 10 || pos == IVec3::new(5, 10, 5) {
+                    WorldVoxel::Solid(0)
+                } else {
+                    WorldVoxel::Air
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn ao_enabled_false_leaves_chunk_meshes_unoccluded() {
+    use crate::mesh_cache::MeshRef;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    let mut app = mesh_spawning_app::<NoAoWorld>();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<NoAoWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+
+    assert!(run_until(&mut app, 1000, |app| first_meshed_chunk(app).is_some()));
+
+    let meshed_chunk = first_meshed_chunk(&mut app).unwrap();
+    let mesh_handle = app.world().get::<MeshRef>(meshed_chunk).unwrap().0.clone();
+    let meshes = app.world().resource::<Assets<Mesh>>();
+    let mesh = meshes.get(mesh_handle.as_ref()).unwrap();
+    let Some(VertexAttributeValues::Float32x4(colors)) =
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+    else {
+        panic!("mesh has no vertex colors");
+    };
+    assert!(!colors.is_empty());
+    assert!(colors.iter().flatten().all(|value| *value == 1.0));
+}
+
+#[test]
+fn raycast_from_surface_skips_origin_voxel() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 5, 20), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(5, 5, 17), WorldVoxel::Solid(2));
+
+            // Starting inside the first voxel
+            let ray = Ray3d {
+                origin: Vec3::new(5.5, 5.5, 20.5),
+                direction: -Dir3::Z,
+            };
+
+            let result = voxel_world.raycast(ray, &|_| true).unwrap();
+            assert_eq!(result.voxel_pos(), IVec3::new(5, 5, 20));
+
+            let result = voxel_world.raycast_from_surface(ray, &|_| true).unwrap();
+            assert_eq!(result.voxel_pos(), IVec3::new(5, 5, 17));
+            assert_eq!(result.voxel, WorldVoxel::Solid(2));
+            assert_eq!(result.voxel_normal(), Some(IVec3::Z));
+        })
+        .unwrap();
+}
+
+#[test]
+fn replace_all_modified_swaps_modifications() {
+    use crate::chunk::NeedsRemesh;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::utils::HashMap;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(1, 1, 1), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(2, 2, 2), WorldVoxel::Solid(1));
+            // Pending writes are not applied yet
+            assert!(voxel_world.iter_modified().is_empty());
+        })
+        .unwrap();
+    app.update();
+
+    let chunk = app
+        .world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            let mut modified = voxel_world.iter_modified();
+            modified.sort_by_key(|(position, _)| position.to_array());
+            assert_eq!(
+                modified,
+                vec![
+                    (IVec3::new(1, 1, 1), WorldVoxel::Solid(1)),
+                    (IVec3::new(2, 2, 2), WorldVoxel::Solid(1)),
+                ]
+            );
+
+            voxel_world.replace_all_modified(HashMap::from_iter([
+                (IVec3::new(2, 2, 2), WorldVoxel::Solid(1)),
+                (IVec3::new(3, 3, 3), WorldVoxel::Solid(2)),
+            ]));
+            voxel_world
+                .get_chunk_data(IVec3::ZERO)
+                .unwrap()
+                .get_entity()
+        })
+        .unwrap();
+
+    // The unchanged voxel is not written again
+    let write_buffer = app
+        .world()
+        .resource::<crate::voxel_world_internal::VoxelWriteBuffer<DefaultWorld, u8>>();
+    assert_eq!(write_buffer.len(), 1);
+    assert!(write_buffer.contains_key(&IVec3::new(3, 3, 3)));
+
+    app.update();
+    assert!(app.world().get::<NeedsRemesh>(chunk).is_some());
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let mut modified = voxel_world.iter_modified();
+            modified.sort_by_key(|(position, _)| position.to_array());
+            assert_eq!(
+                modified,
+                vec![
+                    (IVec3::new(2, 2, 2), WorldVoxel::Solid(1)),
+                    (IVec3::new(3, 3, 3), WorldVoxel::Solid(2)),
+                ]
+            );
+        })
+        .unwrap();
+}
+
+#[test]
+fn majority_downsample_keeps_the_most_common_material() {
+    use crate::custom_meshing::{resample_voxels_majority, resample_voxels_nearest};
+    use ndshape::{RuntimeShape, Shape};
+
+    // A 4x4x4 grid downsampled 2x gives 8 blocks of 2x2x2 voxels
+    let shape = RuntimeShape::<u32, 3>::new([4, 4, 4]);
+    let mut voxels = vec![WorldVoxel::<u8>::Air; shape.size() as usize];
+    let mut set = |x: u32, y: u32, z: u32, voxel| {
+        voxels[shape.linearize([x, y, z]) as usize] = voxel;
+    };
+
+    // Block (0, 0, 0): mostly stone, with dirt at the min corner
+    for (x, y, z) in [(1, 0, 0), (0, 1, 0), (1, 1, 0), (0, 0, 1), (1, 0, 1)] {
+        set(x, y, z, WorldVoxel::Solid(1));
+    }
+    set(0, 0, 0, WorldVoxel::Solid(2));
+
+    // Block (1, 0, 0): a single solid voxel at the min corner, the rest air
+    set(2, 0, 0, WorldVoxel::Solid(3));
+
+    // Block (0, 1, 0): half air, half solid, which is not an air majority
+    for (x, y, z) in [(0, 2, 0), (1, 2, 0), (0, 3, 0), (1, 3, 0)] {
+        set(x, y, z, WorldVoxel::Solid(4));
+    }
+
+    let (majority, resampled_shape) = resample_voxels_majority(&voxels, &shape, 2);
+    assert_eq!(resampled_shape.as_array(), [2, 2, 2]);
+    let at = |resampled: &[WorldVoxel<u8>], x: u32, y: u32, z: u32| {
+        resampled[resampled_shape.linearize([x, y, z]) as usize]
+    };
+    assert_eq!(at(&majority, 0, 0, 0), WorldVoxel::Solid(1));
+    assert_eq!(at(&majority, 1, 0, 0), WorldVoxel::Air);
+    assert_eq!(at(&majority, 0, 1, 0), WorldVoxel::Solid(4));
+    assert_eq!(at(&majority, 1, 1, 1), WorldVoxel::Air);
+
+    let (nearest, _) = resample_voxels_nearest(&voxels, &shape, 2);
+    assert_eq!(at(&nearest, 0, 0, 0), WorldVoxel::Solid(2));
+    assert_eq!(at(&nearest, 1, 0, 0), WorldVoxel::Solid(3));
+}
+
+#[test]
+fn chunk_entity_returns_spawned_chunks_only() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<DefaultWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.load_chunk(IVec3::new(1, 0, 0));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<DefaultWorld>(&mut app, 1, 100));
+
+    let (chunk_pos, entity) = app
+        .world_mut()
+        .query::<&Chunk<DefaultWorld>>()
+        .iter(app.world())
+        .map(|chunk| (chunk.position, chunk.entity))
+        .next()
+        .unwrap();
+    assert_eq!(chunk_pos, IVec3::new(1, 0, 0));
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            assert_eq!(voxel_world.chunk_entity(chunk_pos), Some(entity));
+            assert_eq!(voxel_world.chunk_entity(IVec3::new(5, 5, 5)), None);
+        })
+        .unwrap();
+
+    // Chunks that are only in the map, without an entity, are not returned
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::new(-3, 0, 0));
+    app.update();
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            assert!(voxel_world.get_chunk_data(IVec3::new(-3, 0, 0)).is_some());
+            assert_eq!(voxel_world.chunk_entity(IVec3::new(-3, 0, 0)), None);
+        })
+        .unwrap();
+}
+
+#[test]
+fn fill_cylinder_digs_round_tunnels() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.fill_box(IVec3::ZERO, IVec3::splat(10), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.fill_cylinder(
+                IVec3::new(2, 5, 5),
+                IVec3::new(8, 5, 5),
+                2.0,
+                WorldVoxel::Air,
+            );
+            voxel_world.fill_cone(
+                IVec3::new(20, 5, 5),
+                IVec3::new(30, 5, 5),
+                3.0,
+                0.0,
+                WorldVoxel::Solid(2),
+            );
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            // Within the radius of the tunnel
+            for position in [
+                IVec3::new(5, 5, 5),
+                IVec3::new(5, 5, 7),
+                IVec3::new(5, 7, 5),
+                IVec3::new(5, 6, 6),
+                IVec3::new(0, 5, 5),
+                IVec3::new(10, 5, 5),
+            ] {
+                assert_eq!(voxel_world.get_voxel(position), WorldVoxel::Air);
+            }
+            // Just outside of it
+            for position in [
+                IVec3::new(5, 5, 8),
+                IVec3::new(5, 3, 8),
+                IVec3::new(5, 7, 7),
+                IVec3::new(0, 5, 6),
+            ] {
+                assert_eq!(voxel_world.get_voxel(position), WorldVoxel::Solid(1));
+            }
+
+            // The cone narrows towards its tip
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(20, 5, 8)),
+                WorldVoxel::Solid(2)
+            );
+            assert!(voxel_world.get_voxel(IVec3::new(29, 5, 7)).is_unset());
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(30, 5, 5)),
+                WorldVoxel::Solid(2)
+            );
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct JitterFarAwayWorld;
+
+impl VoxelWorldConfig for JitterFarAwayWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Frustum
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Air))
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct JitterLruWorld;
+
+impl VoxelWorldConfig for JitterLruWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Frustum
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::LeastRecentlySeen { keep: 1000 }
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Air))
+    }
+}
+
+/// Move the camera back and forth across a chunk boundary, and return the number of chunks
+/// spawned and despawned while doing so, after the chunks on both sides have loaded once
+fn spawns_and_despawns_with_jittering_camera<C: VoxelWorldConfig>() -> (usize, usize) {
+    use crate::voxel_world_internal::Internals;
+
+    #[derive(Resource, Default)]
+    struct EventCounts(usize, usize);
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<C>::minimal()));
+    app.init_resource::<EventCounts>();
+    app.add_systems(
+        Update,
+        (
+            Internals::<C>::apply_chunk_tasks_headless,
+            |mut counts: ResMut<EventCounts>,
+             mut spawned: EventReader<ChunkWillSpawn<C>>,
+             mut despawned: EventReader<ChunkWillDespawn<C>>| {
+                counts.0 += spawned.read().count();
+                counts.1 += despawned.read().count();
+            },
+        ),
+    );
+    let camera = app
+        .world_mut()
+        .spawn((
+            Camera3d::default(),
+            GlobalTransform::default(),
+            VoxelWorldCamera::<C>::default(),
+        ))
+        .id();
+
+    let move_camera = |app: &mut App, x: f32, updates: usize| {
+        *app.world_mut().get_mut::<GlobalTransform>(camera).unwrap() =
+            GlobalTransform::from(
+                Transform::from_xyz(x, 16.0, 16.0).looking_to(Vec3::X, Vec3::Y),
+            );
+        for _ in 0..updates {
+            app.update();
+            std::thread::yield_now();
+        }
+    };
+
+    // Let the chunks on both sides of the boundary load
+    move_camera(&mut app, 30.0, 50);
+    move_camera(&mut app, 34.0, 50);
+    move_camera(&mut app, 30.0, 50);
+    *app.world_mut().resource_mut::<EventCounts>() = EventCounts::default();
+
+    for _ in 0..20 {
+        move_camera(&mut app, 34.0, 3);
+        move_camera(&mut app, 30.0, 3);
+    }
+
+    let counts = app.world().resource::<EventCounts>();
+    (counts.0, counts.1)
+}
+
+#[test]
+fn least_recently_seen_despawn_keeps_chunks_at_the_boundary() {
+    // Chunks at the edge of the spawning distance despawn every time the camera moves away
+    let (_, despawned) =
+        spawns_and_despawns_with_jittering_camera::<JitterFarAwayWorld>();
+    assert!(despawned > 0);
+
+    // While there is room for them, they are kept, so nothing spawns or despawns
+    let (spawned, despawned) =
+        spawns_and_despawns_with_jittering_camera::<JitterLruWorld>();
+    assert_eq!(spawned, 0);
+    assert_eq!(despawned, 0);
+}
+
+#[test]
+fn coords_handle_negative_positions() {
+    use crate::coords::*;
+    use ndshape::Shape;
+
+    assert_eq!(chunk_pos_of(IVec3::new(0, 31, 32)), IVec3::new(0, 0, 1));
+    assert_eq!(
+        chunk_pos_of(IVec3::new(-1, -32, -33)),
+        IVec3::new(-1, -1, -2)
+    );
+    assert_eq!(
+        local_in_chunk(IVec3::new(-1, -32, -33)),
+        UVec3::new(31, 0, 31)
+    );
+    assert_eq!(local_in_chunk(IVec3::new(5, 32, 70)), UVec3::new(5, 0, 6));
+    assert_eq!(world_of_chunk(IVec3::new(-1, 0, 2)), IVec3::new(-32, 0, 64));
+    assert_eq!(
+        chunk_pos_of_for_size(IVec3::new(-17, 16, -16), 16),
+        IVec3::new(-2, 1, -1)
+    );
+
+    // Going to a chunk and a position in it and back gives the same world position
+    for world in [
+        IVec3::new(-1, -1, -1),
+        IVec3::new(-64, 33, -95),
+        IVec3::new(7, -8, 9),
+    ] {
+        assert_eq!(
+            world_of_chunk(chunk_pos_of(world)) + local_in_chunk(world).as_ivec3(),
+            world
+        );
+        let (chunk_pos, voxel_pos) = get_chunk_voxel_position(world);
+        assert_eq!(chunk_pos, chunk_pos_of(world));
+        assert_eq!(voxel_pos, local_in_chunk(world) + 1);
+    }
+
+    assert_eq!(
+        padded_index(UVec3::new(31, 0, 2)),
+        crate::chunk::PaddedChunkShape::linearize([32, 1, 3]) as usize
+    );
+}
+
+#[test]
+fn has_voxel_works_for_negative_positions() {
+    let position = IVec3::new(-3, -30, -40);
+    let chunk_task = generated_task::<DefaultWorld>(
+        crate::coords::chunk_pos_of(position),
+        move |world| {
+            if world == position {
+                WorldVoxel::Solid(9)
+            } else {
+                WorldVoxel::Air
+            }
+        },
+    );
+
+    // The chunk map sets the position when the chunk is inserted
+    let chunk_data = ChunkData {
+        position: crate::coords::chunk_pos_of(position),
+        ..chunk_task.chunk_data
+    };
+    assert!(chunk_data.has_voxel(position, WorldVoxel::Solid(9)));
+    assert!(chunk_data.has_voxel(position + IVec3::X, WorldVoxel::Air));
+    // In the next chunk along z
+    assert!(!chunk_data.has_voxel(position + IVec3::new(0, 0, 32), WorldVoxel::Air));
+}
+
+#[test]
+fn has_voxel_finds_voxels_set_at_negative_positions() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    // No camera, so only the forced chunk gets loaded
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    let positions = [IVec3::new(-1, 0, -1), IVec3::new(-32, -1, -5)];
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            for position in positions {
+                voxel_world.set_voxel(position, WorldVoxel::Solid(3));
+            }
+        })
+        .unwrap();
+    app.update();
+
+    for chunk_pos in [IVec3::new(-1, 0, -1), IVec3::new(-1, -1, -1)] {
+        force_chunk_into_map::<DefaultWorld>(&mut app, chunk_pos);
+    }
+    app.update();
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            for position in positions {
+                let (chunk_pos, _) = get_chunk_voxel_position(position);
+                let chunk_data = voxel_world.get_chunk_data(chunk_pos).unwrap();
+                assert!(chunk_data.has_voxel(position, WorldVoxel::Solid(3)));
+                assert_eq!(voxel_world.get_voxel(position), WorldVoxel::Solid(3));
+                assert!(!chunk_data
+                    .has_voxel(position + IVec3::new(0, 0, 1), WorldVoxel::Solid(3)));
+            }
+        })
+        .unwrap();
+}
+
+#[test]
+fn voxels_hash_differs_for_single_voxel_changes() {
+    use crate::chunk::voxels_hash;
+
+    let voxels = vec![WorldVoxel::<u8>::Air; 34 * 34 * 34];
+    let base = voxels_hash(&voxels);
+    assert_eq!(base, voxels_hash(&voxels.clone()));
+
+    let mut hashes = bevy::utils::HashSet::new();
+    hashes.insert(base);
+    for index in [0, 1, 17, 1000, voxels.len() - 1] {
+        for voxel in [
+            WorldVoxel::Solid(1),
+            WorldVoxel::Solid(2),
+            WorldVoxel::Unset,
+        ] {
+            let mut changed = voxels.clone();
+            changed[index] = voxel;
+            // Both halves of the hash change, since they come from independent hashers
+            let hash = voxels_hash(&changed);
+            assert_ne!(hash as u64, base as u64);
+            assert_ne!((hash >> 64) as u64, (base >> 64) as u64);
+            assert!(hashes.insert(hash));
+        }
+    }
+}
+
+#[test]
+fn shared_lookup_generates_the_same_voxels() {
+    use crate::chunk::ChunkTask;
+    use crate::voxel_world_internal::ModifiedVoxels;
+
+    let lookup = |pos: IVec3| match pos.y {
+        ..=2 => WorldVoxel::Solid((pos.x + pos.z).rem_euclid(4) as u8),
+        3 if pos.x % 3 == 0 => WorldVoxel::Translucent(5),
+        _ => WorldVoxel::Air,
+    };
+    let modified_voxels = ModifiedVoxels::<DefaultWorld, u8>::default();
+    modified_voxels.write().unwrap().extend([
+        (IVec3::new(-5, 1, -7), WorldVoxel::Air),
+        (IVec3::new(-2, 20, -3), WorldVoxel::Solid(9)),
+    ]);
+
+    let chunk_task = |precedence| {
+        ChunkTask::<DefaultWorld, u8>::new(
+            Entity::PLACEHOLDER,
+            IVec3::NEG_ONE,
+            32,
+            modified_voxels.clone(),
+        )
+        .with_bedrock(Some((-30, 7)))
+        .with_modification_precedence(precedence)
+    };
+
+    for precedence in [
+        ModificationPrecedence::Override,
+        ModificationPrecedence::Fallback,
+    ] {
+        let mut sequential = chunk_task(precedence);
+        sequential.generate(lookup);
+        let mut shared = chunk_task(precedence);
+        shared.generate_shared(std::sync::Arc::new(lookup));
+
+        assert_eq!(
+            shared.chunk_data.decoded_voxels().unwrap(),
+            sequential.chunk_data.decoded_voxels().unwrap()
+        );
+        assert_eq!(
+            shared.chunk_data.voxels_hash,
+            sequential.chunk_data.voxels_hash
+        );
+        assert_eq!(shared.is_empty(), sequential.is_empty());
+        assert_eq!(shared.is_full(), sequential.is_full());
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct RegenerateWorld {
+    alternate_terrain: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl VoxelWorldConfig for RegenerateWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        let alternate_terrain = self
+            .alternate_terrain
+            .load(std::sync::atomic::Ordering::Relaxed);
+        Box::new(move |_| {
+            Box::new(move |pos| match (pos.y, alternate_terrain) {
+                (..=0, false) => WorldVoxel::Solid(1),
+                (..=0, true) => WorldVoxel::Solid(2),
+                _ => WorldVoxel::Air,
+            })
+        })
+    }
+}
+
+#[test]
+fn regenerate_all_uses_the_current_voxel_lookup() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<RegenerateWorld>::minimal(),
+    ));
+    app.add_systems(
+        Update,
+        Internals::<RegenerateWorld>::apply_chunk_tasks_headless,
+    );
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<RegenerateWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+            voxel_world.set_voxel(IVec3::new(5, 0, 5), WorldVoxel::Solid(9));
+        })
+        .unwrap();
+    assert!(run_until_chunks_loaded::<RegenerateWorld>(&mut app, 1, 100));
+
+    let voxel_at = |app: &mut App, position: IVec3| {
+        app.world_mut()
+            .run_system_once(move |voxel_world: VoxelWorld<RegenerateWorld>| {
+                voxel_world
+                    .get_chunk_data(IVec3::ZERO)
+                    .unwrap()
+                    .get_voxel(get_chunk_voxel_position(position).1)
+            })
+            .unwrap()
+    };
+    assert_eq!(voxel_at(&mut app, IVec3::ZERO), WorldVoxel::Solid(1));
+
+    // The flag is not part of what the config resource change detection sees
+    app.world()
+        .resource::<RegenerateWorld>()
+        .alternate_terrain
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    app.update();
+    assert_eq!(voxel_at(&mut app, IVec3::ZERO), WorldVoxel::Solid(1));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<RegenerateWorld>| {
+            voxel_world.regenerate_all();
+        })
+        .unwrap();
+    let mut regenerated = false;
+    for _ in 0..100 {
+        app.update();
+        if voxel_at(&mut app, IVec3::ZERO) == WorldVoxel::Solid(2) {
+            regenerated = true;
+            break;
+        }
+        std::thread::yield_now();
+    }
+    assert!(regenerated);
+    // Modifications are kept
+    assert_eq!(
+        voxel_at(&mut app, IVec3::new(5, 0, 5)),
+        WorldVoxel::Solid(9)
+    );
+}
+
+#[test]
+fn ground_below_finds_the_floor() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(2, 3, 2), WorldVoxel::Solid(1));
+            voxel_world.set_voxel(IVec3::new(6, 3, 2), WorldVoxel::Solid(2));
+            voxel_world.set_voxel(IVec3::new(6, 4, 2), WorldVoxel::Translucent(3));
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let ground = voxel_world
+                .ground_below(Vec3::new(2.5, 10.0, 2.5), 8.0)
+                .unwrap();
+            assert_eq!(ground.voxel_pos(), IVec3::new(2, 3, 2));
+            assert_eq!(ground.voxel, WorldVoxel::Solid(1));
+            assert_eq!(ground.normal, Some(Vec3::Y));
+            // The top face, where feet would be snapped to
+            assert_eq!(ground.position, Vec3::new(2.0, 4.0, 2.0));
+
+            // Translucent voxels are passed through
+            let ground = voxel_world
+                .ground_below(Vec3::new(6.5, 10.0, 2.5), 8.0)
+                .unwrap();
+            assert_eq!(ground.voxel, WorldVoxel::Solid(2));
+            assert_eq!(ground.position.y, 4.0);
+
+            // Further down than `max_drop`
+            assert!(voxel_world
+                .ground_below(Vec3::new(2.5, 10.0, 2.5), 5.0)
+                .is_none());
+            // Nothing below
+            assert!(voxel_world
+                .ground_below(Vec3::new(4.5, 10.0, 2.5), 8.0)
+                .is_none());
+        })
+        .unwrap();
+}
+
+#[test]
+fn sphere_cast_touches_wall_one_radius_early() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    // No camera, the voxels are found through the modified voxels
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    // A wall one voxel thick, with its near face at x = 10
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.fill_box(
+                IVec3::new(10, -3, -3),
+                IVec3::new(10, 3, 3),
+                WorldVoxel::Solid(1),
+            );
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<DefaultWorld>| {
+            let origin = Vec3::new(2.5, 0.5, 0.5);
+            let hit = voxel_world.sphere_cast(origin, 1.0, Dir3::X, 20.0).unwrap();
+            // The sphere center stops one radius before the wall
+            assert!((hit.position.x - 9.0).abs() < 0.01);
+            assert!((origin.distance(hit.position) - 6.5).abs() < 0.01);
+            assert_eq!(hit.voxel_coords, IVec3::new(10, 0, 0));
+            assert_eq!(hit.normal, Some(Vec3::NEG_X));
+            assert_eq!(hit.voxel, WorldVoxel::Solid(1));
+
+            // Passing just above the top edge of the wall, where a ray from the center would
+            // miss it
+            let above = Vec3::new(2.5, 4.3, 0.5);
+            let hit = voxel_world.sphere_cast(above, 1.0, Dir3::X, 20.0).unwrap();
+            assert_eq!(hit.voxel_coords.x, 10);
+            assert_eq!(hit.voxel_coords.y, 3);
+
+            // Out of reach
+            assert!(voxel_world.sphere_cast(origin, 1.0, Dir3::X, 5.0).is_none());
+            assert!(voxel_world
+                .sphere_cast(origin, 1.0, Dir3::NEG_X, 20.0)
+                .is_none());
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct SilentWorld;
+
+impl VoxelWorldConfig for SilentWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        2
+    }
+
+    fn emit_chunk_events(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn chunk_events_can_be_turned_off() {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[derive(Resource, Default)]
+    struct EventCount(usize);
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<SilentWorld>::minimal()));
+    app.init_resource::<EventCount>();
+    app.add_systems(
+        Update,
+        (
+            Internals::<SilentWorld>::apply_chunk_tasks_headless,
+            |mut count: ResMut<EventCount>,
+             mut spawned: EventReader<ChunkWillSpawn<SilentWorld>>,
+             mut remeshed: EventReader<ChunkWillRemesh<SilentWorld>>,
+             mut updated: EventReader<ChunkWillUpdate<SilentWorld>>,
+             mut invalidated: EventReader<ChunkInvalidated<SilentWorld>>| {
+                count.0 += spawned.read().count()
+                    + remeshed.read().count()
+                    + updated.read().count()
+                    + invalidated.read().count();
+            },
+        ),
+    );
+    app.world_mut().spawn((
+        Camera3d::default(),
+        GlobalTransform::default(),
+        VoxelWorldCamera::<SilentWorld>::default(),
+    ));
+
+    assert!(run_until_chunks_loaded::<SilentWorld>(&mut app, 1, 1000));
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<SilentWorld>| {
+            voxel_world.set_voxel(IVec3::ZERO, WorldVoxel::Solid(1));
+        })
+        .unwrap();
+    for _ in 0..10 {
+        app.update();
+    }
+
+    // The chunks still load and the edit is applied, without any events
+    assert_eq!(app.world().resource::<EventCount>().0, 0);
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<SilentWorld>| {
+            assert_eq!(voxel_world.get_voxel(IVec3::ZERO), WorldVoxel::Solid(1));
+        })
+        .unwrap();
+}
+
+#[test]
+fn raycast_happens_in_the_local_space_of_the_world_root() {
+    use crate::voxel_world_internal::WorldRoot;
+    use bevy::ecs::system::RunSystemOnce;
+    use std::f32::consts::FRAC_PI_2;
+
+    let mut app = spawn_test_app::<DefaultWorld>();
+    force_chunk_into_map::<DefaultWorld>(&mut app, IVec3::ZERO);
+    app.update();
+
+    let root_transform = Transform::from_rotation(Quat::from_rotation_y(FRAC_PI_2));
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            voxel_world.set_voxel(IVec3::new(5, 5, 5), WorldVoxel::Solid(1));
+            voxel_world.set_root_transform(root_transform);
+        })
+        .unwrap();
+    app.update();
+
+    // Transforms are not propagated with `MinimalPlugins`, so the `GlobalTransform` of the root
+    // is set directly
+    let root = app
+        .world_mut()
+        .query_filtered::<Entity, With<WorldRoot<DefaultWorld>>>()
+        .single(app.world());
+    assert_eq!(*app.world().get::<Transform>(root).unwrap(), root_transform);
+    *app.world_mut().get_mut::<GlobalTransform>(root).unwrap() =
+        GlobalTransform::from(root_transform);
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            // The center of the voxel, which the root has rotated to the other side of the
+            // world z axis
+            let center = root_transform.transform_point(Vec3::splat(5.5));
+            assert!((center - Vec3::new(5.5, 5.5, -5.5)).length() < 0.001);
+
+            // A ray along the world -x axis enters the voxel through its local +z face
+            let ray = Ray3d::new(center + Vec3::X * 20.0, Dir3::NEG_X);
+            let result = voxel_world.raycast(ray, &|_| true).unwrap();
+            assert_eq!(result.voxel_coords, IVec3::new(5, 5, 5));
+            assert_eq!(result.normal, Some(Vec3::Z));
+
+            // Where the voxel would be without the rotation, there is nothing
+            let ray = Ray3d::new(Vec3::new(25.5, 5.5, 5.5), Dir3::NEG_X);
+            assert!(voxel_world.raycast(ray, &|_| true).is_none());
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct SeaLevelWorld;
+
+impl VoxelWorldConfig for SeaLevelWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn default_solid_below(&self) -> Option<(i32, Self::MaterialIndex)> {
+        Some((0, 3))
+    }
+}
+
+#[test]
+fn default_solid_below_fills_unset_voxels() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<SeaLevelWorld>::minimal()));
+    // The padding of a chunk reaches one voxel into its neighbours, so the chunks right at the
+    // surface are neither full nor empty
+    for chunk_pos in [
+        IVec3::new(0, -2, 0),
+        IVec3::new(0, -1, 0),
+        IVec3::new(0, 1, 0),
+    ] {
+        force_chunk_into_map::<SeaLevelWorld>(&mut app, chunk_pos);
+    }
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<SeaLevelWorld>| {
+            let below = voxel_world.get_chunk_data(IVec3::new(0, -2, 0)).unwrap();
+            assert!(below.is_full());
+            let above = voxel_world.get_chunk_data(IVec3::new(0, 1, 0)).unwrap();
+            assert!(above.is_empty());
+            let surface = voxel_world.get_chunk_data(IVec3::new(0, -1, 0)).unwrap();
+            assert!(!surface.is_full() && !surface.is_empty());
+
+            assert_eq!(
+                voxel_world.get_voxel(IVec3::new(4, -40, 4)),
+                WorldVoxel::Solid(3)
+            );
+            assert_eq!(voxel_world.get_voxel(IVec3::new(4, 40, 4)), WorldVoxel::Air);
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct NoLookaheadWorld;
+
+impl VoxelWorldConfig for NoLookaheadWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        6
+    }
+
+    // Chunks are never in view without rendering
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct LookaheadWorld;
+
+impl VoxelWorldConfig for LookaheadWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        6
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn spawn_lookahead_seconds(&self) -> f32 {
+        1.0
+    }
+}
+
+/// Move a camera along the x axis at 64 units per second, and return whether the chunk two
+/// chunks ahead of it has been spawned
+fn chunk_ahead_of_moving_camera_is_spawned<C: VoxelWorldConfig>() -> bool {
+    use crate::voxel_world_internal::Internals;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<C>::minimal()));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        0.1,
+    )));
+    app.add_systems(Update, Internals::<C>::apply_chunk_tasks_headless);
+    let camera = app
+        .world_mut()
+        .spawn((
+            Camera3d::default(),
+            GlobalTransform::default(),
+            VoxelWorldCamera::<C>::default(),
+        ))
+        .id();
+
+    // From x = 16 to x = 80, which is in chunk 2
+    for step in 0..=10 {
+        *app.world_mut().get_mut::<GlobalTransform>(camera).unwrap() =
+            GlobalTransform::from(
+                Transform::from_xyz(16.0 + step as f32 * 6.4, 16.0, 16.0)
+                    .looking_to(Vec3::X, Vec3::Y),
+            );
+        app.update();
+    }
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<C>| {
+            voxel_world.chunk_entity(IVec3::new(4, 0, 0)).is_some()
+        })
+        .unwrap()
+}
+
+#[test]
+fn spawn_lookahead_spawns_chunks_ahead_of_the_camera() {
+    assert!(!chunk_ahead_of_moving_camera_is_spawned::<NoLookaheadWorld>());
+    assert!(chunk_ahead_of_moving_camera_is_spawned::<LookaheadWorld>());
+}
+
+#[derive(Resource, Clone, Default)]
+struct PooledWorld;
+
+impl VoxelWorldConfig for PooledWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn pool_chunk_entities(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn pooled_chunk_entities_are_reused() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<PooledWorld>::minimal()));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.load_chunk(IVec3::ZERO);
+        })
+        .unwrap();
+    app.update();
+    let entity = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.chunk_entity(IVec3::ZERO)
+        })
+        .unwrap()
+        .unwrap();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<PooledWorld>| voxel_world.clear())
+        .unwrap();
+    app.update();
+
+    // The entity is kept alive without any of the chunk components
+    assert!(app.world().get_entity(entity).is_ok());
+    assert!(app.world().get::<Chunk<PooledWorld>>(entity).is_none());
+
+    // It only becomes available for reuse in the next update
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.load_chunk(IVec3::new(3, 0, 0));
+        })
+        .unwrap();
+    app.update();
+
+    let respawned = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.chunk_entity(IVec3::new(3, 0, 0))
+        })
+        .unwrap();
+    assert_eq!(respawned, Some(entity));
+    assert_eq!(
+        app.world()
+            .get::<Chunk<PooledWorld>>(entity)
+            .unwrap()
+            .position,
+        IVec3::new(3, 0, 0)
+    );
+
+    // Pooled entities that get despawned from outside are skipped
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<PooledWorld>| voxel_world.clear())
+        .unwrap();
+    app.update();
+    app.update();
+    app.world_mut().despawn(entity);
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.load_chunk(IVec3::new(6, 0, 0));
+        })
+        .unwrap();
+    app.update();
+
+    let spawned = app
+        .world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<PooledWorld>| {
+            voxel_world.chunk_entity(IVec3::new(6, 0, 0))
+        })
+        .unwrap()
+        .unwrap();
+    assert_ne!(spawned, entity);
+    assert!(app.world().get::<Chunk<PooledWorld>>(spawned).is_some());
+}
+
+#[derive(Resource, Clone, Default)]
+struct TopDownWorld;
+
+impl VoxelWorldConfig for TopDownWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        4
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn voxel_lookup_delegate(&self) -> VoxelLookupDelegate<Self::MaterialIndex> {
+        Box::new(|_| Box::new(|_| WorldVoxel::Air))
+    }
+}
+
+#[test]
+fn orthographic_camera_spawns_a_rectangular_footprint() {
+    use crate::voxel_world_internal::Internals;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<TopDownWorld>::minimal()));
+    app.add_systems(
+        Update,
+        Internals::<TopDownWorld>::apply_chunk_tasks_headless,
+    );
+
+    // Looking straight down, with the camera x axis along the world -x axis and the camera y
+    // axis along the world z axis. The visible area spans x = -48..80 and z = -16..48, which
+    // is chunks -2..=2 along x and -1..=1 along z.
+    app.world_mut().spawn((
+        Camera3d::default(),
+        Projection::Orthographic(OrthographicProjection {
+            area: Rect::new(-64.0, -32.0, 64.0, 32.0),
+            ..OrthographicProjection::default_3d()
+        }),
+        GlobalTransform::from(
+            Transform::from_xyz(16.0, 40.0, 16.0).looking_to(Vec3::NEG_Y, Vec3::Z),
+        ),
+        VoxelWorldCamera::<TopDownWorld>::default(),
+    ));
+
+    for _ in 0..50 {
+        app.update();
+    }
+
+    let positions = app
+        .world_mut()
+        .query::<&Chunk<TopDownWorld>>()
+        .iter(app.world())
+        .map(|chunk| chunk.position)
+        .collect::<Vec<_>>();
+    let min = positions.iter().copied().reduce(IVec3::min).unwrap();
+    let max = positions.iter().copied().reduce(IVec3::max).unwrap();
+
+    assert_eq!((min.x, max.x), (-2, 2));
+    assert_eq!((min.z, max.z), (-1, 1));
+    // The rays go straight down, so every column of the footprint is loaded below the camera
+    for x in -2..=2 {
+        for z in -1..=1 {
+            assert!(positions.contains(&IVec3::new(x, -1, z)));
+        }
+    }
+}
+
+#[test]
+fn is_enclosed_tells_sealed_pockets_from_open_ones() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<DefaultWorld>::minimal()));
+    app.update();
+
+    // Two 3x3x3 air pockets in solid cubes, and the second one has a hole in its roof
+    let sealed = IVec3::ZERO;
+    let open = IVec3::new(10, 0, 0);
+    app.world_mut()
+        .run_system_once(move |mut voxel_world: VoxelWorld<DefaultWorld>| {
+            for center in [sealed, open] {
+                voxel_world.fill_box(center - 2, center + 2, WorldVoxel::Solid(1));
+                voxel_world.fill_box(center - 1, center + 1, WorldVoxel::Air);
+            }
+            voxel_world.set_voxel(open + IVec3::new(0, 2, 0), WorldVoxel::Air);
+        })
+        .unwrap();
+    app.update();
+
+    app.world_mut()
+        .run_system_once(move |voxel_world: VoxelWorld<DefaultWorld>| {
+            assert!(voxel_world.is_enclosed(sealed, 1000));
+            assert!(!voxel_world.is_enclosed(open, 1000));
+            // The sealed pocket has 27 voxels, which doesn't fit in a smaller budget
+            assert!(!voxel_world.is_enclosed(sealed, 20));
+            assert!(voxel_world.is_enclosed(sealed, 27));
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct BandedWorld;
+
+impl VoxelWorldConfig for BandedWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        3
+    }
+
+    fn spawn_y_range(&self) -> std::ops::RangeInclusive<i32> {
+        0..=4
+    }
+}
+
+#[test]
+fn chunks_outside_the_spawn_y_range_are_not_spawned() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<BandedWorld>::minimal()));
+    app.world_mut().spawn((
+        GlobalTransform::from_xyz(16.0, 16.0, 16.0),
+        VoxelWorldAnchor::<BandedWorld>::default(),
+    ));
+    app.update();
+
+    app.world_mut()
+        .run_system_once(|mut voxel_world: VoxelWorld<BandedWorld>| {
+            voxel_world.load_chunk(IVec3::new(0, 10, 0));
+        })
+        .unwrap();
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let positions = app
+        .world_mut()
+        .query::<&Chunk<BandedWorld>>()
+        .iter(app.world())
+        .map(|chunk| chunk.position)
+        .collect::<Vec<_>>();
+    // The anchor is in chunk y = 0, so the chunks below it are left out as well
+    assert!(positions.contains(&IVec3::new(0, 3, 0)));
+    assert!(positions
+        .iter()
+        .all(|position| (0..=4).contains(&position.y)));
+
+    app.world_mut()
+        .run_system_once(|voxel_world: VoxelWorld<BandedWorld>| {
+            assert!(voxel_world.chunk_entity(IVec3::new(0, 10, 0)).is_none());
+        })
+        .unwrap();
+}
+
+#[derive(Resource, Clone, Default)]
+struct CloseBandedWorld;
+
+impl VoxelWorldConfig for CloseBandedWorld {
+    type MaterialIndex = u8;
+    type ChunkUserBundle = ();
+
+    fn spawning_distance(&self) -> u32 {
+        4
+    }
+
+    fn chunk_spawn_strategy(&self) -> ChunkSpawnStrategy {
+        ChunkSpawnStrategy::Close
+    }
+
+    fn chunk_despawn_strategy(&self) -> ChunkDespawnStrategy {
+        ChunkDespawnStrategy::FarAway
+    }
+
+    fn spawn_y_range(&self) -> std::ops::RangeInclusive<i32> {
+        0..=1
+    }
+}
+
+#[test]
+fn close_spawning_reaches_the_spawn_y_range_from_above() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        VoxelWorldPlugin::<CloseBandedWorld>::minimal(),
+    ));
+
+    // The camera is in chunk y = 4, so none of the chunks right around it are in the range
+    app.world_mut().spawn((
+        Camera3d::default(),
+        GlobalTransform::from_xyz(16.0, 144.0, 16.0),
+        VoxelWorldCamera::<CloseBandedWorld>::default(),
+    ));
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let positions = app
+        .world_mut()
+        .query::<&Chunk<CloseBandedWorld>>()
+        .iter(app.world())
+        .map(|chunk| chunk.position)
+        .collect::<Vec<_>>();
+    assert!(positions.contains(&IVec3::new(0, 1, 0)));
+    assert!(positions.contains(&IVec3::new(0, 0, 0)));
+    assert!(positions
+        .iter()
+        .all(|position| (0..=1).contains(&position.y)));
+}
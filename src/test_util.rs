@@ -0,0 +1,189 @@
+//! Test utilities
+//!
+//! Helpers for testing voxel world integrations without a renderer. Enable the `test-util`
+//! feature to use these from downstream crates.
+
+use bevy::prelude::*;
+
+use crate::{
+    chunk::ChunkTask,
+    chunk_map::{ChunkMap, ChunkMapUpdateBuffer},
+    configuration::VoxelWorldConfig,
+    mesh_cache::MeshRef,
+    plugin::VoxelWorldPlugin,
+    voxel::WorldVoxel,
+    voxel_material::LoadingTexture,
+    voxel_world::{ChunkWillSpawn, VoxelWorldCamera},
+    voxel_world_internal::{Internals, ModifiedVoxels},
+};
+
+/// Build an `App` with `MinimalPlugins`, a minimal `VoxelWorldPlugin<C>` and a camera tagged with
+/// `VoxelWorldCamera<C>`.
+///
+/// Meshes are never spawned in this app, but the voxel data of spawned chunks will still get
+/// generated and inserted into the chunk map, so `ChunkWillSpawn` events fire as usual.
+pub fn spawn_test_app<C: VoxelWorldConfig>() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<C>::minimal()));
+    app.add_systems(Update, Internals::<C>::apply_chunk_tasks_headless);
+    app.add_systems(Startup, |mut commands: Commands| {
+        commands.spawn((
+            Camera3d::default(),
+            Transform::from_xyz(10.0, 10.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            VoxelWorldCamera::<C>::default(),
+        ));
+    });
+
+    app
+}
+
+/// Build an `App` like `spawn_test_app`, but without a camera and with `spawn_meshes` running
+/// against a headless `Assets<Mesh>`, so that meshes of loaded chunks get spawned.
+///
+/// The voxel texture is marked as loaded, since there is no renderer to load it.
+pub fn mesh_spawning_app<C: VoxelWorldConfig>() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, VoxelWorldPlugin::<C>::minimal()));
+    app.add_plugins(AssetPlugin::default()).init_asset::<Mesh>();
+    app.insert_resource(LoadingTexture {
+        is_loaded: true,
+        handle: Handle::default(),
+    });
+    app.add_systems(Update, Internals::<C>::spawn_meshes);
+    app.update();
+
+    app
+}
+
+/// Run `app.update()` until `done` returns `true`, or until `max_updates` updates have run.
+///
+/// Returns `true` if `done` returned `true` within the given number of updates.
+pub fn run_until(
+    app: &mut App,
+    max_updates: usize,
+    mut done: impl FnMut(&mut App) -> bool,
+) -> bool {
+    for _ in 0..max_updates {
+        app.update();
+        if done(app) {
+            return true;
+        }
+        // Give the task pool a chance to make progress on the chunk tasks
+        std::thread::yield_now();
+    }
+    false
+}
+
+/// Returns the first chunk entity that has a mesh, if any.
+pub fn first_meshed_chunk(app: &mut App) -> Option<Entity> {
+    app.world_mut()
+        .query_filtered::<Entity, With<MeshRef>>()
+        .iter(app.world())
+        .next()
+}
+
+/// Generate the voxels of the chunk at `chunk_pos` on the calling thread, using `lookup`
+/// and no modified voxels.
+#[cfg(test)]
+pub(crate) fn generated_task<C: VoxelWorldConfig>(
+    chunk_pos: IVec3,
+    lookup: impl FnMut(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + 'static,
+) -> ChunkTask<C, C::MaterialIndex> {
+    let mut chunk_task = ChunkTask::<C, C::MaterialIndex>::new(
+        Entity::PLACEHOLDER,
+        chunk_pos,
+        C::default().chunk_size(),
+        ModifiedVoxels::default(),
+    );
+    chunk_task.generate(lookup);
+    chunk_task
+}
+
+/// Generate the chunk at `chunk_pos` on the calling thread, using the voxel lookup delegate
+/// from the config, and queue it for insertion into the chunk map.
+///
+/// The chunk will be available through `VoxelWorld::get_chunk_data` after the next
+/// `app.update()`. If the voxel world has not been set up yet, an update is run first.
+pub fn force_chunk_into_map<C: VoxelWorldConfig>(app: &mut App, chunk_pos: IVec3) {
+    if !app
+        .world()
+        .contains_resource::<ModifiedVoxels<C, C::MaterialIndex>>()
+    {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let configuration = world.resource::<C>().clone();
+    let modified_voxels = world
+        .resource::<ModifiedVoxels<C, C::MaterialIndex>>()
+        .clone();
+
+    // Keep the entity of an already spawned chunk, so that it stays in sync with the map
+    let entity = {
+        let chunk_map = world.resource::<ChunkMap<C, C::MaterialIndex>>();
+        let read_lock = chunk_map.get_read_lock();
+        ChunkMap::<C, C::MaterialIndex>::get(&chunk_pos, &read_lock)
+            .map(|chunk_data| chunk_data.get_entity())
+            .unwrap_or(Entity::PLACEHOLDER)
+    };
+
+    let mut chunk_task = ChunkTask::<C, C::MaterialIndex>::new(
+        entity,
+        chunk_pos,
+        configuration.chunk_size(),
+        modified_voxels,
+    )
+    .with_bedrock(
+        configuration
+            .bedrock_y()
+            .map(|bedrock_y| (bedrock_y, configuration.bedrock_material())),
+    )
+    .with_default_fill(configuration.default_solid_below())
+    .with_modification_precedence(configuration.modification_precedence());
+    match configuration.shared_voxel_lookup_delegate() {
+        Some(delegate) => chunk_task.generate_shared(delegate(chunk_pos)),
+        None => chunk_task.generate((configuration.voxel_lookup_delegate())(chunk_pos)),
+    }
+    if !chunk_task
+        .chunk_data
+        .make_sparse(configuration.sparse_chunk_threshold())
+        && configuration.compress_chunk_storage()
+    {
+        chunk_task.chunk_data.compress();
+    }
+
+    world
+        .resource_mut::<ChunkMapUpdateBuffer<C, C::MaterialIndex>>()
+        .push((
+            chunk_pos,
+            chunk_task.chunk_data,
+            ChunkWillSpawn::<C>::new(chunk_pos, entity),
+        ));
+}
+
+/// Returns the number of chunks in the chunk map that have finished generating.
+pub fn loaded_chunk_count<C: VoxelWorldConfig>(app: &App) -> usize {
+    let Some(chunk_map) = app.world().get_resource::<ChunkMap<C, C::MaterialIndex>>()
+    else {
+        return 0;
+    };
+    let read_lock = chunk_map.get_read_lock();
+    read_lock
+        .values()
+        .filter(|chunk_data| chunk_data.has_generated())
+        .count()
+}
+
+/// Run `app.update()` until at least `min_chunks` chunks have finished generating, or until
+/// `max_updates` updates have run.
+///
+/// Returns `true` if the chunks were loaded within the given number of updates.
+pub fn run_until_chunks_loaded<C: VoxelWorldConfig>(
+    app: &mut App,
+    min_chunks: usize,
+    max_updates: usize,
+) -> bool {
+    run_until(app, max_updates, |app| {
+        loaded_chunk_count::<C>(app) >= min_chunks
+    })
+}
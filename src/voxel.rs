@@ -3,12 +3,18 @@ use block_mesh::{MergeVoxel, Voxel, VoxelVisibility};
 
 pub const VOXEL_SIZE: f32 = 1.;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+/// Voxels are ordered `Unset < Air < Solid < Translucent`, and by material within `Solid` and
+/// `Translucent`, when the material index type is ordered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WorldVoxel<I = u8> {
     #[default]
     Unset,
     Air,
     Solid(I),
+    /// A voxel that neighbouring faces can be seen through, like glass or water. Faces between
+    /// translucent voxels of the same material are not meshed.
+    Translucent(I),
 }
 
 impl<I: PartialEq> WorldVoxel<I> {
@@ -23,14 +29,18 @@ impl<I: PartialEq> WorldVoxel<I> {
     pub fn is_solid(&self) -> bool {
         matches!(self, WorldVoxel::Solid(_))
     }
+
+    pub fn is_translucent(&self) -> bool {
+        matches!(self, WorldVoxel::Translucent(_))
+    }
 }
 
 impl<I: PartialEq> Voxel for WorldVoxel<I> {
     fn get_visibility(&self) -> VoxelVisibility {
-        if *self == WorldVoxel::Air || *self == WorldVoxel::Unset {
-            VoxelVisibility::Empty
-        } else {
-            VoxelVisibility::Opaque
+        match self {
+            WorldVoxel::Unset | WorldVoxel::Air => VoxelVisibility::Empty,
+            WorldVoxel::Solid(_) => VoxelVisibility::Opaque,
+            WorldVoxel::Translucent(_) => VoxelVisibility::Translucent,
         }
     }
 }
@@ -40,7 +50,7 @@ impl<I: PartialEq + Eq + Default + Copy> MergeVoxel for WorldVoxel<I> {
 
     fn merge_value(&self) -> Self::MergeValue {
         match self {
-            WorldVoxel::Solid(v) => *v,
+            WorldVoxel::Solid(v) | WorldVoxel::Translucent(v) => *v,
             _ => I::default(),
         }
     }
@@ -1,10 +1,14 @@
 use bevy::{
-    pbr::{MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    pbr::{
+        ExtendedMaterial, MaterialExtension, MaterialExtensionKey,
+        MaterialExtensionPipeline,
+    },
     prelude::*,
     reflect::TypePath,
     render::{
         mesh::{
             MeshVertexAttribute, MeshVertexBufferLayoutRef, VertexAttributeDescriptor,
+            VertexAttributeValues,
         },
         render_resource::{
             AsBindGroup, RenderPipelineDescriptor, ShaderDefVal, ShaderRef,
@@ -13,40 +17,114 @@ use bevy::{
     },
 };
 
+use crate::{
+    chunk::Chunk, configuration::VoxelWorldConfig, mesh_cache::MeshRef,
+    plugin::VoxelWorldMaterialHandle,
+};
+
 /// Keeps track of the loading status of the image used for the voxel texture
 #[derive(Resource)]
-pub(crate) struct LoadingTexture {
+pub struct LoadingTexture {
     pub is_loaded: bool,
     pub handle: Handle<Image>,
 }
 
-#[derive(Resource)]
-pub(crate) struct TextureLayers(pub u32);
+/// Textures that are still loading, with the number of layers to split each of them into once
+/// loaded. `LoadingTexture::is_loaded` is set when all of them are done.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub(crate) struct PendingTextures(Vec<(Handle<Image>, u32)>);
 
 pub const VOXEL_TEXTURE_SHADER_HANDLE: Handle<Shader> =
     Handle::weak_from_u128(6998301138411443008);
 
+/// Shader module with the triplanar texture sampling used by the default material, importable
+/// as `bevy_voxel_world::triplanar`
+pub const VOXEL_TRIPLANAR_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(2717965041386221571);
+
 pub const ATTRIBUTE_TEX_INDEX: MeshVertexAttribute =
     MeshVertexAttribute::new("TextureIndex", 989640910, VertexFormat::Uint32x3);
 
+/// Signed distance to the nearest surface, added to chunk meshes when
+/// `VoxelWorldConfig::surface_distance_range` is set. Not used by the default material.
+pub const ATTRIBUTE_SURFACE_DISTANCE: MeshVertexAttribute =
+    MeshVertexAttribute::new("SurfaceDistance", 3810429766, VertexFormat::Float32);
+
+/// Baked light level between 0 and 1, added to chunk meshes when
+/// `VoxelWorldConfig::bake_voxel_light` is true.
+pub const ATTRIBUTE_VOXEL_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("VoxelLight", 1577206831, VertexFormat::Float32);
+
 pub fn vertex_layout() -> Vec<VertexAttributeDescriptor> {
     vec![
         Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
         Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
         Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+        Mesh::ATTRIBUTE_UV_1.at_shader_location(3),
         //Mesh::ATTRIBUTE_TANGENT.at_shader_location(4),
         Mesh::ATTRIBUTE_COLOR.at_shader_location(5),
         Mesh::ATTRIBUTE_COLOR.at_shader_location(7),
         //Mesh::ATTRIBUTE_JOINT_INDEX.at_shader_location(6),
         //Mesh::ATTRIBUTE_JOINT_WEIGHT.at_shader_location(7),
         ATTRIBUTE_TEX_INDEX.at_shader_location(8),
+        ATTRIBUTE_VOXEL_LIGHT.at_shader_location(9),
     ]
 }
+
+/// The shader location of the first of `VoxelWorldConfig::extra_vertex_attributes`
+pub const FIRST_EXTRA_SHADER_LOCATION: u32 = 10;
+
+/// `vertex_layout()` followed by the `extra_vertex_attributes` of the config, for custom
+/// materials that read attributes written by a meshing delegate
+pub fn world_vertex_layout<C: VoxelWorldConfig>(
+    configuration: &C,
+) -> Vec<VertexAttributeDescriptor> {
+    let mut layout = vertex_layout();
+    layout.extend(
+        configuration
+            .extra_vertex_attributes()
+            .into_iter()
+            .zip(FIRST_EXTRA_SHADER_LOCATION..)
+            .map(|(attribute, location)| attribute.at_shader_location(location)),
+    );
+    layout
+}
+
 #[derive(Asset, AsBindGroup, Debug, Clone, TypePath)]
+#[bind_group_data(StandardVoxelMaterialKey)]
 pub(crate) struct StandardVoxelMaterial {
     #[texture(100, dimension = "2d_array")]
     #[sampler(101)]
     pub voxels_texture: Handle<Image>,
+    /// See `VoxelWorldConfig::voxel_normal_texture`
+    #[texture(102, dimension = "2d_array")]
+    #[sampler(103)]
+    pub normal_texture: Option<Handle<Image>>,
+    /// Occlusion, roughness and metallic in the red, green and blue channels. See
+    /// `VoxelWorldConfig::voxel_orm_texture`.
+    #[texture(104, dimension = "2d_array")]
+    #[sampler(105)]
+    pub metallic_roughness_texture: Option<Handle<Image>>,
+    /// Sample the voxel textures with triplanar projection instead of the mesh UVs. See
+    /// `VoxelWorldConfig::triplanar_texturing`.
+    pub triplanar: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StandardVoxelMaterialKey {
+    normal_texture: bool,
+    metallic_roughness_texture: bool,
+    triplanar: bool,
+}
+
+impl From<&StandardVoxelMaterial> for StandardVoxelMaterialKey {
+    fn from(material: &StandardVoxelMaterial) -> Self {
+        Self {
+            normal_texture: material.normal_texture.is_some(),
+            metallic_roughness_texture: material.metallic_roughness_texture.is_some(),
+            triplanar: material.triplanar,
+        }
+    }
 }
 
 impl MaterialExtension for StandardVoxelMaterial {
@@ -62,7 +140,7 @@ impl MaterialExtension for StandardVoxelMaterial {
         _pipeline: &MaterialExtensionPipeline,
         descriptor: &mut RenderPipelineDescriptor,
         layout: &MeshVertexBufferLayoutRef,
-        _key: MaterialExtensionKey<Self>,
+        key: MaterialExtensionKey<Self>,
     ) -> Result<(), SpecializedMeshPipelineError> {
         if descriptor
             .vertex
@@ -72,28 +150,104 @@ impl MaterialExtension for StandardVoxelMaterial {
             return Ok(());
         }
 
-        let vertex_layout = layout.0.get_layout(&vertex_layout())?;
+        // Meshes made with `ChunkMeshAttributes` other than `Full` lack some of the attributes,
+        // so only the ones present in the mesh are required
+        let attributes = vertex_layout()
+            .into_iter()
+            .filter(|attribute| layout.0.contains(attribute.id))
+            .collect::<Vec<_>>();
+        let vertex_layout = layout.0.get_layout(&attributes)?;
         descriptor.vertex.buffers = vec![vertex_layout];
+
+        let mut shader_defs = Vec::new();
+        if layout.0.contains(ATTRIBUTE_VOXEL_LIGHT) {
+            shader_defs.push(ShaderDefVal::Bool("VOXEL_LIGHT".into(), true));
+        }
+        if key.bind_group_data.triplanar {
+            shader_defs.push(ShaderDefVal::Bool("VOXEL_TRIPLANAR".into(), true));
+        }
+        if key.bind_group_data.normal_texture {
+            shader_defs.push(ShaderDefVal::Bool("VOXEL_NORMAL_MAP".into(), true));
+        }
+        if key.bind_group_data.metallic_roughness_texture {
+            shader_defs.push(ShaderDefVal::Bool("VOXEL_ORM_MAP".into(), true));
+        }
+        for shader_def in shader_defs {
+            descriptor.vertex.shader_defs.push(shader_def.clone());
+            if let Some(fragment) = descriptor.fragment.as_mut() {
+                fragment.shader_defs.push(shader_def);
+            }
+        }
         Ok(())
     }
 }
 
 pub(crate) fn prepare_texture(
     asset_server: Res<AssetServer>,
-    texture_layers: Res<TextureLayers>,
+    mut pending_textures: ResMut<PendingTextures>,
     mut loading_texture: ResMut<LoadingTexture>,
     mut images: ResMut<Assets<Image>>,
 ) {
-    if loading_texture.is_loaded
-        || !matches!(
-            asset_server.get_load_state(loading_texture.handle.clone().id()),
+    if loading_texture.is_loaded {
+        return;
+    }
+
+    // Each texture is split into layers as soon as it has loaded
+    pending_textures.retain(|(handle, layers)| {
+        if !matches!(
+            asset_server.get_load_state(handle.id()),
             Some(bevy::asset::LoadState::Loaded)
-        )
-    {
+        ) {
+            return true;
+        }
+        let image = images.get_mut(handle).unwrap();
+        image.reinterpret_stacked_2d_as_array(*layers);
+        false
+    });
+    loading_texture.is_loaded = pending_textures.is_empty();
+}
+
+/// Switches the default material to `AlphaMode::Blend` once a chunk of the world is meshed with
+/// vertex colors that are not fully opaque. Only used when
+/// `VoxelWorldConfig::material_alpha_mode` is not set.
+pub(crate) fn blend_translucent_chunk_meshes<C: VoxelWorldConfig>(
+    material_handle: Res<
+        VoxelWorldMaterialHandle<
+            ExtendedMaterial<StandardMaterial, StandardVoxelMaterial>,
+        >,
+    >,
+    mut materials: ResMut<
+        Assets<ExtendedMaterial<StandardMaterial, StandardVoxelMaterial>>,
+    >,
+    meshed_chunks: Query<&MeshRef, (With<Chunk<C>>, Changed<MeshRef>)>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    // Looked up with `get`, so that the material isn't marked as changed every frame
+    let is_opaque = materials
+        .get(&material_handle.handle)
+        .is_some_and(|material| material.base.alpha_mode == AlphaMode::Opaque);
+    if !is_opaque {
         return;
     }
-    loading_texture.is_loaded = true;
 
-    let image = images.get_mut(&loading_texture.handle).unwrap();
-    image.reinterpret_stacked_2d_as_array(texture_layers.0);
+    let is_translucent = meshed_chunks
+        .iter()
+        .filter_map(|mesh_ref| meshes.get(mesh_ref.0.as_ref()))
+        .any(has_translucent_vertices);
+    if let Some(material) = is_translucent
+        .then(|| materials.get_mut(&material_handle.handle))
+        .flatten()
+    {
+        material.base.alpha_mode = AlphaMode::Blend;
+    }
+}
+
+/// Returns true if any vertex color of the mesh has an alpha below 1.0
+pub(crate) fn has_translucent_vertices(mesh: &Mesh) -> bool {
+    match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => {
+            colors.iter().any(|color| color[3] < 1.0)
+        }
+        _ => false,
+    }
 }
@@ -93,18 +93,30 @@ pub fn voxel_cartesian_traversal<F: FnMut(IVec3) -> bool + Sized>(
 pub fn voxel_line_traversal<F: FnMut(IVec3, f32, VoxelFace) -> bool + Sized>(
     start: Vec3,
     end: Vec3,
+    visit_voxel: F,
+) {
+    voxel_line_traversal_with_size(start, end, VOXEL_SIZE, visit_voxel)
+}
+
+/// Same as `voxel_line_traversal`, but for a grid of voxels of the given size. `start` and `end`
+/// are given in world units, while the voxel coordinates passed to `visit_voxel` are on the
+/// voxel grid.
+pub fn voxel_line_traversal_with_size<F: FnMut(IVec3, f32, VoxelFace) -> bool + Sized>(
+    start: Vec3,
+    end: Vec3,
+    voxel_size: f32,
     mut visit_voxel: F,
 ) {
     let ray = end - start;
     let end_t = ray.length();
     let ray_dir = ray / end_t;
     let r_ray_dir = ray_dir.recip();
-    let delta_t = (VOXEL_SIZE * r_ray_dir).abs();
+    let delta_t = (voxel_size * r_ray_dir).abs();
 
     let step = ray_dir.signum().as_ivec3();
 
-    let start_voxel = start.floor().as_ivec3();
-    let end_voxel = end.floor().as_ivec3();
+    let start_voxel = (start / voxel_size).floor().as_ivec3();
+    let end_voxel = (end / voxel_size).floor().as_ivec3();
 
     let mut voxel = start_voxel;
     let mut max_t = Vec3::ZERO;
@@ -113,7 +125,7 @@ pub fn voxel_line_traversal<F: FnMut(IVec3, f32, VoxelFace) -> bool + Sized>(
         end_t
     } else {
         let o = if step.x > 0 { 1 } else { 0 };
-        let plane = (start_voxel.x + o) as f32 * VOXEL_SIZE;
+        let plane = (start_voxel.x + o) as f32 * voxel_size;
         (plane - start.x) * r_ray_dir.x
     };
 
@@ -121,7 +133,7 @@ pub fn voxel_line_traversal<F: FnMut(IVec3, f32, VoxelFace) -> bool + Sized>(
         end_t
     } else {
         let o = if step.y > 0 { 1 } else { 0 };
-        let plane = (start_voxel.y + o) as f32 * VOXEL_SIZE;
+        let plane = (start_voxel.y + o) as f32 * voxel_size;
         (plane - start.y) * r_ray_dir.y
     };
 
@@ -129,7 +141,7 @@ pub fn voxel_line_traversal<F: FnMut(IVec3, f32, VoxelFace) -> bool + Sized>(
         end_t
     } else {
         let o = if step.z > 0 { 1 } else { 0 };
-        let plane = (start_voxel.z + o) as f32 * VOXEL_SIZE;
+        let plane = (start_voxel.z + o) as f32 * voxel_size;
         (plane - start.z) * r_ray_dir.z
     };
 
@@ -2,18 +2,39 @@
 /// VoxelWorld
 /// This module implements most of the public API for bevy_voxel_world.
 ///
+use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
-use bevy::{ecs::system::SystemParam, math::bounding::RayCast3d, prelude::*};
+use bevy::{
+    ecs::system::SystemParam,
+    math::{
+        bounding::{Aabb3d, RayCast3d},
+        Affine3A,
+    },
+    prelude::*,
+    render::primitives::Aabb,
+    utils::{HashMap, HashSet},
+};
 
 use crate::{
-    chunk::{ChunkData, CHUNK_SIZE_F, CHUNK_SIZE_I},
+    chunk::{
+        voxels_hash, ChunkData, ChunkVoxelEdits, NeedsDespawn, NeedsRemesh, VoxelArray,
+        CHUNK_SIZE_U,
+    },
     chunk_map::ChunkMap,
-    configuration::VoxelWorldConfig,
-    traversal_alg::voxel_line_traversal,
-    voxel::WorldVoxel,
-    voxel_world_internal::{ModifiedVoxels, VoxelWriteBuffer},
+    configuration::{FloodFillConnectivity, ModificationPrecedence, VoxelWorldConfig},
+    coords::{chunk_pos_of_for_size, local_in_chunk_for_size},
+    mesh_cache::{MeshCache, MeshPreloadBuffer},
+    meshing::merge_meshes,
+    traversal_alg::voxel_line_traversal_with_size,
+    voxel::{VoxelFace, WorldVoxel},
+    voxel_material::LoadingTexture,
+    voxel_world_internal::{
+        chunk_transform, chunks_sharing_voxel, is_within_world_bounds, root_from_world,
+        ChunkLoadBuffer, ModifiedVoxels, VoxelClearBuffer, VoxelWriteBuffer, WorldRoot,
+    },
 };
 
 /// This component is used to mark the Camera that bevy_voxel_world should use to determine
@@ -31,6 +52,25 @@ impl<C> Default for VoxelWorldCamera<C> {
     }
 }
 
+/// Marks an entity that chunks should be spawned around, in addition to the
+/// `VoxelWorldCamera`. Useful for example for other players in a multiplayer game.
+///
+/// All chunks within `spawning_distance` of an anchor are spawned, regardless of whether they
+/// are in view, and chunks are only despawned once they are too far away from the camera and
+/// all anchors.
+#[derive(Component)]
+pub struct VoxelWorldAnchor<C> {
+    _marker: PhantomData<C>,
+}
+
+impl<C> Default for VoxelWorldAnchor<C> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
 pub trait ChunkEventType {}
 
 #[derive(Event)]
@@ -68,16 +108,107 @@ pub type ChunkWillSpawn<C> = ChunkEvent<C, WillSpawn>;
 pub struct WillSpawn;
 impl ChunkEventType for WillSpawn {}
 
-/// Fired when a chunk is about to be remeshed.
+/// Fired when a chunk is about to be remeshed, which is when the task that generates and meshes
+/// the chunk is started. This happens for every newly spawned chunk, as well as after edits.
+///
+/// See `ChunkInvalidated` for an event that also tells why the chunk is remeshed.
 pub type ChunkWillRemesh<C> = ChunkEvent<C, WillRemesh>;
 pub struct WillRemesh;
 impl ChunkEventType for WillRemesh {}
 
 /// Fired when a chunk is about to be updated, typically when `set_voxel` was called on a voxel
 /// within the chunk.
-pub type ChunkWillUpdate<C> = ChunkEvent<C, WillUpdate>;
-pub struct WillUpdate;
-impl ChunkEventType for WillUpdate {}
+///
+/// This fires once per edited chunk in the frame the voxel writes are applied, before the chunk
+/// is remeshed. It doesn't fire for chunks that are spawned without being edited.
+#[derive(Event)]
+pub struct ChunkWillUpdate<C> {
+    pub chunk_key: IVec3,
+    pub entity: Entity,
+    /// World positions of the voxels in the chunk that were changed
+    pub changed: Vec<IVec3>,
+    _marker: PhantomData<C>,
+}
+
+impl<C> ChunkWillUpdate<C> {
+    pub fn new(chunk_key: IVec3, entity: Entity, changed: Vec<IVec3>) -> Self {
+        Self {
+            chunk_key,
+            entity,
+            changed,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The reason a chunk was invalidated. See `ChunkInvalidated`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidationReason {
+    /// Voxels in the chunk were changed, for example with `set_voxel`
+    Edited,
+    /// The chunk is generated for the first time since it was spawned
+    Streamed,
+    /// The chunk is generated again from scratch, after `VoxelWorld::regenerate_all`, a
+    /// configuration change or a failed generation attempt
+    Regenerated,
+    /// A neighbouring chunk was spawned, despawned or changed. Only happens when
+    /// `VoxelWorldConfig::chunk_meshing_needs_neighbours` is true.
+    NeighbourChanged,
+}
+
+/// Fired when a chunk is about to be regenerated and remeshed, along with the reason why.
+///
+/// This fires at the same time as `ChunkWillRemesh`, so exactly once per remesh of a chunk, and
+/// can be used instead of listening to both `ChunkWillRemesh` and `ChunkWillUpdate`.
+#[derive(Event)]
+pub struct ChunkInvalidated<C> {
+    pub chunk_key: IVec3,
+    pub entity: Entity,
+    pub reason: InvalidationReason,
+    _marker: PhantomData<C>,
+}
+
+impl<C> ChunkInvalidated<C> {
+    pub fn new(chunk_key: IVec3, entity: Entity, reason: InvalidationReason) -> Self {
+        Self {
+            chunk_key,
+            entity,
+            reason,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Fired when a chunk has been meshed, in the same frame as the mesh gets inserted on the chunk
+/// entity. Empty and full chunks don't get a mesh, so this doesn't fire for them.
+#[derive(Event)]
+pub struct ChunkMeshReady<C> {
+    pub chunk_key: IVec3,
+    pub entity: Entity,
+    pub mesh: Handle<Mesh>,
+    /// Bounds of the mesh, in the local space of the chunk
+    pub aabb: Aabb,
+    _marker: PhantomData<C>,
+}
+
+impl<C> ChunkMeshReady<C> {
+    pub fn new(chunk_key: IVec3, entity: Entity, mesh: Handle<Mesh>, aabb: Aabb) -> Self {
+        Self {
+            chunk_key,
+            entity,
+            mesh,
+            aabb,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Fired when generation of a chunk has failed, and all retries have been used up.
+/// See `VoxelWorldConfig::max_generation_retries`.
+pub type ChunkGenerationFailed<C> = ChunkEvent<C, GenerationFailed>;
+pub struct GenerationFailed;
+impl ChunkEventType for GenerationFailed {}
 
 pub trait FilterFn<I> {
     fn call(&self, input: (Vec3, WorldVoxel<I>)) -> bool;
@@ -92,17 +223,23 @@ impl<F: Fn((Vec3, WorldVoxel<I>)) -> bool, I> FilterFn<I> for F {
 pub type RaycastFn<I> =
     dyn Fn(Ray3d, &dyn FilterFn<I>) -> Option<VoxelRaycastResult<I>> + Send + Sync;
 
+pub type RaycastAllFn<I> =
+    dyn Fn(Ray3d, f32, &dyn FilterFn<I>) -> Vec<VoxelRaycastResult<I>> + Send + Sync;
+
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct VoxelRaycastResult<I = u8> {
+    /// Position of the min corner of the voxel, in world units
     pub position: Vec3,
     pub normal: Option<Vec3>,
     pub voxel: WorldVoxel<I>,
+    /// Position of the voxel on the voxel grid
+    pub voxel_coords: IVec3,
 }
 
 impl<I> VoxelRaycastResult<I> {
     /// Get the voxel position of the raycast result
     pub fn voxel_pos(&self) -> IVec3 {
-        self.position.floor().as_ivec3()
+        self.voxel_coords
     }
 
     /// Get the face normal of the ray hit
@@ -111,18 +248,77 @@ impl<I> VoxelRaycastResult<I> {
     }
 }
 
+/// A copy of the voxels in a box shaped region of the world, made with `VoxelWorld::copy_region`
+/// and written back with `VoxelWorld::paste_region`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoxelClipboard<I> {
+    size: UVec3,
+    voxels: Vec<WorldVoxel<I>>,
+}
+
+impl<I: Copy> VoxelClipboard<I> {
+    /// Number of voxels along each axis
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    /// Get the voxel at the given position, relative to the min corner of the copied region.
+    /// Returns `WorldVoxel::Unset` outside of the clipboard.
+    pub fn get(&self, position: UVec3) -> WorldVoxel<I> {
+        if position.cmpge(self.size).any() {
+            return WorldVoxel::Unset;
+        }
+        self.voxels[self.index(position)]
+    }
+
+    fn index(&self, position: UVec3) -> usize {
+        (position.x + position.y * self.size.x + position.z * self.size.x * self.size.y)
+            as usize
+    }
+}
+
+/// The voxel state of a set of chunks, captured with `VoxelWorld::capture_chunks` and put back
+/// with `VoxelWorld::restore_chunks`.
+///
+/// The voxel data of the chunks is shared with the chunk map rather than copied, so capturing
+/// is cheap. Chunks replace their voxel data when they are edited, so the snapshot is not
+/// affected by later edits.
+#[derive(Clone)]
+pub struct ChunkStateSnapshot<C: VoxelWorldConfig> {
+    chunks: Vec<(IVec3, Option<Arc<ChunkData<C::MaterialIndex>>>)>,
+    modified_voxels: HashMap<IVec3, WorldVoxel<C::MaterialIndex>>,
+}
+
+impl<C: VoxelWorldConfig> ChunkStateSnapshot<C> {
+    /// Positions of the captured chunks
+    pub fn chunk_positions(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.chunks.iter().map(|(position, _)| *position)
+    }
+
+    /// The voxels in the captured chunks that had been modified with `set_voxel`
+    pub fn modified_voxels(&self) -> &HashMap<IVec3, WorldVoxel<C::MaterialIndex>> {
+        &self.modified_voxels
+    }
+}
+
 /// Grants access to the VoxelWorld in systems
 #[derive(SystemParam)]
-pub struct VoxelWorld<'w, C: VoxelWorldConfig> {
+pub struct VoxelWorld<'w, 's, C: VoxelWorldConfig> {
+    commands: Commands<'w, 's>,
     chunk_map: Res<'w, ChunkMap<C, <C as VoxelWorldConfig>::MaterialIndex>>,
     modified_voxels: Res<'w, ModifiedVoxels<C, <C as VoxelWorldConfig>::MaterialIndex>>,
     voxel_write_buffer:
         ResMut<'w, VoxelWriteBuffer<C, <C as VoxelWorldConfig>::MaterialIndex>>,
-    #[allow(unused)]
+    voxel_clear_buffer: ResMut<'w, VoxelClearBuffer<C>>,
+    mesh_preload_buffer: ResMut<'w, MeshPreloadBuffer<C>>,
+    mesh_cache: Res<'w, MeshCache<C>>,
+    chunk_load_buffer: ResMut<'w, ChunkLoadBuffer<C>>,
     configuration: Res<'w, C>,
+    loading_texture: Option<Res<'w, LoadingTexture>>,
+    world_root: Query<'w, 's, (Entity, &'static GlobalTransform), With<WorldRoot<C>>>,
 }
 
-impl<C: VoxelWorldConfig> VoxelWorld<'_, C> {
+impl<C: VoxelWorldConfig> VoxelWorld<'_, '_, C> {
     /// Get the voxel at the given position. The voxel will be WorldVoxel::Unset if there is no voxel at that position
     pub fn get_voxel(&self, position: IVec3) -> WorldVoxel<C::MaterialIndex> {
         self.get_voxel_fn()(position)
@@ -130,71 +326,713 @@ impl<C: VoxelWorldConfig> VoxelWorld<'_, C> {
 
     /// Set the voxel at the given position. This will create a new chunk if one does not exist at
     /// the given position.
+    ///
+    /// Writes at or below `VoxelWorldConfig::bedrock_y` are ignored.
     pub fn set_voxel(&mut self, position: IVec3, voxel: WorldVoxel<C::MaterialIndex>) {
-        self.voxel_write_buffer.push((position, voxel));
+        if self.is_bedrock(position) {
+            return;
+        }
+        self.voxel_clear_buffer.remove(&position);
+        self.voxel_write_buffer.insert(position, voxel);
     }
 
-    /// Get a sendable closure that can be used to get the voxel at the given position
-    /// This is useful for spawning tasks that need to access the voxel world
-    pub fn get_voxel_fn(
+    /// Set the voxel at the given position right away, instead of at the end of the frame like
+    /// `set_voxel`. The voxel is written straight to the modified voxels, so `get_voxel` returns
+    /// it right after this, and the chunks holding the voxel are marked for remeshing with
+    /// `Commands`, without waiting for the write buffer to be flushed. No `ChunkWillUpdate`
+    /// event is sent for the edit. This is handy in tests and tools that need edits to be in
+    /// place before the next update.
+    ///
+    /// Every call takes the write lock on the modified voxels, so use `set_voxel` for many
+    /// edits, and don't call this in hot loops.
+    ///
+    /// Writes at or below `VoxelWorldConfig::bedrock_y` are ignored.
+    pub fn set_voxel_immediate(
+        &mut self,
+        position: IVec3,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        if self.is_bedrock(position) {
+            return;
+        }
+        // A buffered write or clear would otherwise undo this edit when it gets flushed
+        self.voxel_write_buffer.remove(&position);
+        self.voxel_clear_buffer.remove(&position);
+        self.modified_voxels
+            .write()
+            .unwrap()
+            .insert(position, voxel);
+
+        let chunk_size = self.configuration.chunk_size();
+        let (chunk_pos, vox_pos) =
+            get_chunk_voxel_position_for_size(position, chunk_size);
+        let read_lock = self.chunk_map.get_read_lock();
+        for sharing_chunk_pos in chunks_sharing_voxel(chunk_pos, vox_pos, chunk_size) {
+            let Some(chunk_data) =
+                ChunkMap::<C, C::MaterialIndex>::get_arc(&sharing_chunk_pos, &read_lock)
+            else {
+                continue;
+            };
+            // The edit is not in `ChunkVoxelEdits`, so the chunk is fully regenerated
+            if let Some(mut entity) = self.commands.get_entity(chunk_data.entity) {
+                entity
+                    .try_insert(NeedsRemesh(InvalidationReason::Edited))
+                    .remove::<ChunkVoxelEdits>();
+            }
+        }
+    }
+
+    /// Set the voxel at the given position, and return the voxel that was there before. Writes
+    /// made earlier in the same frame are taken into account, so swapping the same position
+    /// repeatedly returns each previously written voxel in turn.
+    ///
+    /// Like with `set_voxel`, writes at or below `VoxelWorldConfig::bedrock_y` are ignored, in
+    /// which case the returned voxel is still in place.
+    pub fn swap_voxel(
+        &mut self,
+        position: IVec3,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) -> WorldVoxel<C::MaterialIndex> {
+        let previous = self.get_voxel(position);
+        self.set_voxel(position, voxel);
+        previous
+    }
+
+    /// Undo any modification made to the voxel at the given position with `set_voxel`, so that it
+    /// goes back to what the voxel lookup delegate returns. The chunk holding the voxel is
+    /// regenerated, and `get_voxel` returns the generated voxel once that is done.
+    pub fn clear_voxel(&mut self, position: IVec3) {
+        self.voxel_write_buffer.remove(&position);
+        self.voxel_clear_buffer.insert(position);
+    }
+
+    fn is_bedrock(&self, position: IVec3) -> bool {
+        self.configuration
+            .bedrock_y()
+            .is_some_and(|bedrock_y| position.y <= bedrock_y)
+    }
+
+    /// Copy the voxels in the box between `min` and `max`, both inclusive. Voxels in chunks that
+    /// are not loaded are copied as `WorldVoxel::Unset`.
+    pub fn copy_region(
         &self,
-    ) -> Arc<dyn Fn(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + Sync> {
-        let chunk_map = self.chunk_map.get_map();
-        let write_buffer = self.voxel_write_buffer.clone();
-        let modified_voxels = self.modified_voxels.clone();
+        min: IVec3,
+        max: IVec3,
+    ) -> VoxelClipboard<C::MaterialIndex> {
+        let (min, max) = (min.min(max), min.max(max));
+        let size = (max - min + 1).as_uvec3();
+        let get_voxel = self.get_voxel_fn();
+
+        let mut voxels = Vec::with_capacity((size.x * size.y * size.z) as usize);
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    voxels.push(get_voxel(IVec3::new(x, y, z)));
+                }
+            }
+        }
 
-        Arc::new(move |position| {
-            let (chunk_pos, vox_pos) = get_chunk_voxel_position(position);
+        VoxelClipboard { size, voxels }
+    }
 
-            if let Some(voxel) = write_buffer
-                .iter()
-                .find(|(pos, _)| *pos == position)
-                .map(|(_, voxel)| *voxel)
-            {
-                return voxel;
+    /// Write the voxels in the clipboard back into the world, with the min corner of the copied
+    /// region at `origin`. The voxels are written the same way as with `set_voxel`.
+    ///
+    /// Voxels that are `WorldVoxel::Unset` in the clipboard, such as voxels copied from chunks
+    /// that weren't loaded, are never written. If `skip_air` is true, the voxels in the world are
+    /// also left as they are where the clipboard holds `WorldVoxel::Air`.
+    pub fn paste_region(
+        &mut self,
+        origin: IVec3,
+        clip: &VoxelClipboard<C::MaterialIndex>,
+        skip_air: bool,
+    ) {
+        for z in 0..clip.size.z {
+            for y in 0..clip.size.y {
+                for x in 0..clip.size.x {
+                    let offset = UVec3::new(x, y, z);
+                    let voxel = clip.get(offset);
+                    if voxel.is_unset() || (skip_air && voxel.is_air()) {
+                        continue;
+                    }
+                    self.set_voxel(origin + offset.as_ivec3(), voxel);
+                }
             }
+        }
+    }
 
+    /// Set all voxels within `radius` of `center` to `voxel`. The voxels are written the same way
+    /// as with `set_voxel`, so writes at or below `VoxelWorldConfig::bedrock_y` are ignored.
+    /// Voxels outside `VoxelWorldConfig::world_chunk_bounds` are skipped.
+    pub fn fill_sphere(
+        &mut self,
+        center: IVec3,
+        radius: u32,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        let radius = radius as i32;
+        let offsets = (-radius..=radius)
+            .flat_map(|z| (-radius..=radius).map(move |y| (y, z)))
+            .flat_map(|(y, z)| (-radius..=radius).map(move |x| IVec3::new(x, y, z)));
+        let chunk_size = self.configuration.chunk_size();
+        for offset in offsets {
+            let position = center + offset;
+            let (chunk_pos, _) = get_chunk_voxel_position_for_size(position, chunk_size);
+            if offset.length_squared() <= radius * radius
+                && is_within_world_bounds(chunk_pos, self.configuration.as_ref())
             {
-                if let Some(voxel) = modified_voxels.get_voxel(&position) {
-                    return voxel;
+                self.set_voxel(position, voxel);
+            }
+        }
+    }
+
+    /// Set all voxels within `radius` of the line segment from `a` to `b` to `voxel`, which gives
+    /// a cylinder with rounded ends. Use `WorldVoxel::Air` to dig tunnels. The voxels are written
+    /// the same way as with `fill_sphere`.
+    pub fn fill_cylinder(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        radius: f32,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        self.fill_along_segment(a, b, radius, radius, voxel);
+    }
+
+    /// Like `fill_cylinder`, but the radius goes from `radius_a` at `a` to `radius_b` at `b`,
+    /// for tapered shapes like tunnel entrances or spikes.
+    pub fn fill_cone(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        radius_a: f32,
+        radius_b: f32,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        self.fill_along_segment(a, b, radius_a, radius_b, voxel);
+    }
+
+    fn fill_along_segment(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        radius_a: f32,
+        radius_b: f32,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        let (radius_a, radius_b) = (radius_a.max(0.0), radius_b.max(0.0));
+        let reach = IVec3::splat(radius_a.max(radius_b).ceil() as i32);
+        let (min, max) = (a.min(b) - reach, a.max(b) + reach);
+        let (start, segment) = (a.as_vec3(), (b - a).as_vec3());
+        let chunk_size = self.configuration.chunk_size();
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let position = IVec3::new(x, y, z);
+                    let t = if segment == Vec3::ZERO {
+                        0.0
+                    } else {
+                        ((position.as_vec3() - start).dot(segment)
+                            / segment.length_squared())
+                        .clamp(0.0, 1.0)
+                    };
+                    let radius = radius_a + (radius_b - radius_a) * t;
+                    let distance = position.as_vec3().distance(start + segment * t);
+                    let (chunk_pos, _) =
+                        get_chunk_voxel_position_for_size(position, chunk_size);
+                    if distance <= radius
+                        && is_within_world_bounds(chunk_pos, self.configuration.as_ref())
+                    {
+                        self.set_voxel(position, voxel);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Set all voxels in the box between `min` and `max`, both inclusive, to `voxel`. The voxels
+    /// are written the same way as with `set_voxel`. The box is clamped to
+    /// `VoxelWorldConfig::world_chunk_bounds`.
+    pub fn fill_box(
+        &mut self,
+        min: IVec3,
+        max: IVec3,
+        voxel: WorldVoxel<C::MaterialIndex>,
+    ) {
+        let (mut min, mut max) = (min.min(max), min.max(max));
+        if let Some((min_chunk, max_chunk)) = self.configuration.world_chunk_bounds() {
+            let chunk_size = self.configuration.chunk_size() as i32;
+            min = min.max(min_chunk * chunk_size);
+            max = max.min((max_chunk + 1) * chunk_size - 1);
+        }
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.set_voxel(IVec3::new(x, y, z), voxel);
                 }
             }
+        }
+    }
+
+    /// Build terrain from a heightmap, such as one loaded from a grayscale image. `heights` holds
+    /// `width * depth` column heights, row by row along x, with the first one for the column at
+    /// `origin`.
+    ///
+    /// A column with height `h` gets `h` voxels, starting at `origin.y`. The top one is set to
+    /// `surface` and the ones below it to `fill`. Columns with height 0 are left as they are. The
+    /// voxels are written the same way as with `set_voxel`.
+    ///
+    /// Nothing is written, and a warning is logged, if `heights` doesn't hold exactly
+    /// `width * depth` heights.
+    pub fn apply_heightmap(
+        &mut self,
+        origin: IVec3,
+        width: u32,
+        depth: u32,
+        heights: &[u16],
+        surface: WorldVoxel<C::MaterialIndex>,
+        fill: WorldVoxel<C::MaterialIndex>,
+    ) {
+        let column_count = width as usize * depth as usize;
+        if heights.len() != column_count {
+            warn!(
+                "apply_heightmap got {} heights for a {}x{} heightmap, which needs {}",
+                heights.len(),
+                width,
+                depth,
+                column_count
+            );
+            return;
+        }
 
-            let chunk_opt = {
-                let chun_map_read = chunk_map.read().unwrap();
-                chun_map_read.get(&chunk_pos).cloned()
+        let columns = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| IVec3::new(x as i32, 0, z as i32)));
+        for (offset, &height) in columns.zip(heights) {
+            let column = origin + offset;
+            let top = column.y + height as i32 - 1;
+            for y in column.y..top {
+                self.set_voxel(column.with_y(y), fill);
+            }
+            if height > 0 {
+                self.set_voxel(column.with_y(top), surface);
+            }
+        }
+    }
+
+    /// Get a snapshot of all voxels that have been modified with `set_voxel`, including writes
+    /// that are still pending in this frame.
+    ///
+    /// Together with `import_modified`, this can be used to persist edits across sessions.
+    /// With the `serde` feature enabled, `WorldVoxel` can be serialized directly.
+    pub fn export_modified(&self) -> Vec<(IVec3, WorldVoxel<C::MaterialIndex>)> {
+        let mut modified = self.modified_voxels.read().unwrap().clone();
+        for position in self.voxel_clear_buffer.iter() {
+            modified.remove(position);
+        }
+        for (position, voxel) in self.voxel_write_buffer.iter() {
+            modified.insert(*position, *voxel);
+        }
+        modified.into_iter().collect()
+    }
+
+    /// Import previously exported voxel modifications.
+    ///
+    /// The voxels are written through the same buffer as `set_voxel`, so any affected chunk that
+    /// is already spawned will get remeshed. Chunks that are not spawned yet will pick up the
+    /// modifications when they are generated.
+    pub fn import_modified(
+        &mut self,
+        data: impl IntoIterator<Item = (IVec3, WorldVoxel<C::MaterialIndex>)>,
+    ) {
+        let bedrock_y = self.configuration.bedrock_y();
+        self.voxel_write_buffer
+            .extend(data.into_iter().filter(|(position, _)| {
+                bedrock_y.is_none_or(|bedrock_y| position.y > bedrock_y)
+            }));
+    }
+
+    /// Get a snapshot of the voxel modifications that have been applied to the world. Unlike
+    /// `export_modified`, writes that are still pending in this frame are not included.
+    pub fn iter_modified(&self) -> Vec<(IVec3, WorldVoxel<C::MaterialIndex>)> {
+        self.modified_voxels
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(position, voxel)| (*position, *voxel))
+            .collect()
+    }
+
+    /// Replace all voxel modifications with the ones in `modified`, for example when loading a
+    /// map in an editor. Modifications that are not in `modified` are cleared, as with
+    /// `clear_voxel`, and the rest are written as with `set_voxel`.
+    ///
+    /// Only the positions that change are queued, and the change is applied when the write
+    /// buffer is flushed at the end of the frame, which remeshes the affected chunks.
+    pub fn replace_all_modified(
+        &mut self,
+        mut modified: HashMap<IVec3, WorldVoxel<C::MaterialIndex>>,
+    ) {
+        for (position, voxel) in self.export_modified() {
+            match modified.remove(&position) {
+                Some(new_voxel) if new_voxel == voxel => {}
+                Some(new_voxel) => self.set_voxel(position, new_voxel),
+                None => self.clear_voxel(position),
+            }
+        }
+        for (position, voxel) in modified {
+            self.set_voxel(position, voxel);
+        }
+    }
+
+    /// Capture the voxel state of the chunks at the given chunk positions, for example to roll
+    /// back to it later with `restore_chunks`.
+    ///
+    /// The snapshot holds the voxel data of the chunks that are loaded, and the modified voxels
+    /// in all of the given chunks, including writes that are still pending in this frame.
+    pub fn capture_chunks(&self, positions: &[IVec3]) -> ChunkStateSnapshot<C> {
+        let chunk_positions: HashSet<IVec3> = positions.iter().copied().collect();
+        let chunks = {
+            let read_lock = self.chunk_map.get_read_lock();
+            chunk_positions
+                .iter()
+                .map(|position| {
+                    (
+                        *position,
+                        ChunkMap::<C, C::MaterialIndex>::get_arc(position, &read_lock),
+                    )
+                })
+                .collect()
+        };
+        let modified_voxels = self.modified_in_chunks(&chunk_positions).collect();
+
+        ChunkStateSnapshot {
+            chunks,
+            modified_voxels,
+        }
+    }
+
+    /// Put the chunks of a snapshot made with `capture_chunks` back in the captured state.
+    ///
+    /// The captured voxel data replaces that of chunks that are still loaded, so `get_voxel`
+    /// returns the captured voxels right away. Modified voxels that differ from the capture are
+    /// set or cleared through the same buffers as `set_voxel` and `clear_voxel`, which remeshes
+    /// the affected chunks.
+    pub fn restore_chunks(&mut self, snapshot: &ChunkStateSnapshot<C>) {
+        let chunk_positions: HashSet<IVec3> = snapshot.chunk_positions().collect();
+        let current: HashMap<_, _> = self.modified_in_chunks(&chunk_positions).collect();
+
+        for position in current.keys() {
+            if !snapshot.modified_voxels.contains_key(position) {
+                self.clear_voxel(*position);
+            }
+        }
+        for (position, voxel) in snapshot.modified_voxels.iter() {
+            if current.get(position) != Some(voxel) {
+                self.set_voxel(*position, *voxel);
+            }
+        }
+
+        let chunk_map = self.chunk_map.get_map();
+        let mut write_lock = chunk_map.write().unwrap();
+        for (position, chunk_data) in snapshot.chunks.iter() {
+            let Some(chunk_data) = chunk_data else {
+                continue;
             };
+            // Keep the entity of the chunk that is loaded now, it may have been respawned
+            if let Some(entity) = write_lock.get(position).map(|loaded| loaded.entity) {
+                write_lock.insert(
+                    *position,
+                    Arc::new(ChunkData {
+                        entity,
+                        ..ChunkData::clone(chunk_data)
+                    }),
+                );
+            }
+        }
+    }
 
-            if let Some(chunk_data) = chunk_opt {
-                chunk_data.get_voxel(vox_pos)
-            } else {
-                WorldVoxel::Unset
+    /// Modified voxels, including pending writes, that are in one of the given chunks
+    fn modified_in_chunks<'a>(
+        &self,
+        chunk_positions: &'a HashSet<IVec3>,
+    ) -> impl Iterator<Item = (IVec3, WorldVoxel<C::MaterialIndex>)> + 'a {
+        let chunk_size = self.configuration.chunk_size();
+        self.export_modified()
+            .into_iter()
+            .filter(move |(position, _)| {
+                let (chunk_pos, _) =
+                    get_chunk_voxel_position_for_size(*position, chunk_size);
+                chunk_positions.contains(&chunk_pos)
+            })
+    }
+
+    /// Add a precomputed mesh to the mesh cache. Chunks with voxel data matching `voxels` will
+    /// use this mesh directly, without running the meshing delegate.
+    ///
+    /// `voxels` is the padded voxel array of a chunk, the same as gets passed to the meshing
+    /// delegate. Preloaded meshes are kept alive for as long as the world exists.
+    pub fn preload_mesh(&mut self, voxels: &VoxelArray<C::MaterialIndex>, mesh: Mesh) {
+        self.mesh_preload_buffer.push((voxels_hash(voxels), mesh));
+    }
+
+    /// Merge the meshes of all loaded chunks into a single mesh, for example to export the world
+    /// or to render a static snapshot of it. The vertices of each chunk are offset by the world
+    /// position of the chunk, and scaled by the voxel size.
+    ///
+    /// `meshes` should be the `Assets<Mesh>` the chunk meshes were added to. Only chunks that
+    /// have been meshed are included, so this has no effect in a world that doesn't spawn meshes.
+    ///
+    /// Meshes shared between identical chunks are duplicated in the result, so for a large world
+    /// the merged mesh can use a lot more memory than the chunk meshes themselves.
+    pub fn bake_loaded_world(&self, meshes: &Assets<Mesh>) -> Mesh {
+        let read_lock = self.chunk_map.get_read_lock();
+        let chunk_meshes = read_lock
+            .values()
+            .filter(|chunk_data| !chunk_data.is_empty() && !chunk_data.is_full())
+            .filter_map(|chunk_data| {
+                let mesh_handle = self
+                    .mesh_cache
+                    .get_mesh_handle(&chunk_data.mesh_cache_key())?;
+                let mesh = meshes.get(mesh_handle.as_ref())?;
+                Some((
+                    mesh,
+                    chunk_transform(chunk_data.position, self.configuration.as_ref()),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        merge_meshes(chunk_meshes)
+    }
+
+    /// Spawn the chunk at the given chunk position, regardless of where the camera is. Does
+    /// nothing if the chunk has already been spawned, or if it is outside
+    /// `VoxelWorldConfig::world_chunk_bounds`.
+    ///
+    /// This can be used to generate chunks in a world without a camera, for example on a server.
+    /// Note that if there is a camera or an anchor, chunks loaded this way are still despawned
+    /// once they are outside the spawning distance.
+    pub fn load_chunk(&mut self, chunk_pos: IVec3) {
+        self.chunk_load_buffer.push(chunk_pos);
+    }
+
+    /// Tear down the world, for example when switching levels. All voxel modifications are
+    /// dropped, including writes that are still pending, and every chunk in the chunk map is
+    /// despawned in the next chunk update, with a `ChunkWillDespawn` event for each. Meshes that
+    /// no other chunk uses are dropped from the mesh cache along with the chunks.
+    ///
+    /// Like after `load_chunk`, chunks around a camera or anchor are spawned again afterwards,
+    /// so remove those first if the world should stay empty.
+    pub fn clear(&mut self) {
+        self.modified_voxels.write().unwrap().clear();
+        self.voxel_write_buffer.clear();
+        self.voxel_clear_buffer.clear();
+        self.chunk_load_buffer.clear();
+
+        for (chunk_pos, chunk_data) in self.chunk_map.get_read_lock().iter() {
+            let Some(mut entity) = self.commands.get_entity(chunk_data.entity) else {
+                continue;
+            };
+            entity.try_insert(NeedsDespawn);
+            if self.configuration.emit_chunk_events() {
+                self.commands.send_event(ChunkWillDespawn::<C>::new(
+                    *chunk_pos,
+                    chunk_data.entity,
+                ));
             }
-        })
+        }
+    }
+
+    /// Generate and mesh every spawned chunk again, calling the voxel lookup delegate for all
+    /// voxels that haven't been modified, regardless of `ChunkRegenerateStrategy`. Voxel
+    /// modifications are kept.
+    ///
+    /// The delegate is fetched from the configuration for every chunk that gets generated, so
+    /// this can be used to swap terrain live, for example for a new seed or biome. Changes to
+    /// the configuration resource already regenerate the chunks picked by
+    /// `VoxelWorldConfig::config_change_affects_chunk`, so this is mostly needed when the lookup
+    /// depends on state outside of the configuration, or when that method leaves chunks out.
+    pub fn regenerate_all(&mut self) {
+        for chunk_data in self.chunk_map.get_read_lock().values() {
+            let Some(mut entity) = self.commands.get_entity(chunk_data.entity) else {
+                continue;
+            };
+            // Without the edits, the chunk is generated from scratch instead of reusing its
+            // previous voxels
+            entity
+                .try_insert(NeedsRemesh(InvalidationReason::Regenerated))
+                .remove::<ChunkVoxelEdits>();
+        }
+    }
+
+    /// Rebuild the bounding box of loaded chunks, which limits how far raycasts are traced.
+    ///
+    /// The bounds are kept up to date as chunks spawn, but are only partially updated as chunks
+    /// despawn, so in long running worlds they can grow larger than the loaded area. Call this
+    /// now and then to tighten them again.
+    pub fn recompute_bounds(&mut self) {
+        self.chunk_map.recompute_bounds();
+    }
+
+    /// Get the bounding box of loaded chunks, in world units. Bounds are inclusive, so the box
+    /// covers all voxels of the outermost chunks.
+    pub fn world_bounds(&self) -> Aabb3d {
+        ChunkMap::<C, C::MaterialIndex>::get_world_bounds(
+            &self.chunk_map.get_read_lock(),
+            self.configuration.chunk_size(),
+            self.configuration.voxel_size(),
+        )
+    }
+
+    /// Get the number of chunks currently in the chunk map, including chunks that are still
+    /// being generated.
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.chunk_map.get_read_lock().len()
+    }
+
+    /// Get the distinct materials of the solid and translucent voxels in all loaded chunks, for
+    /// example to only load the textures of materials that are in use. Voxels in the one voxel
+    /// wide border around the loaded chunks are included as well.
+    pub fn loaded_materials(&self) -> HashSet<C::MaterialIndex> {
+        self.chunk_map
+            .get_read_lock()
+            .values()
+            .flat_map(|chunk_data| chunk_data.materials().iter().copied())
+            .collect()
+    }
+
+    /// Get the positions of all chunks in the chunk map, along with the distance from `point` to
+    /// the center of each chunk, sorted nearest first. `point` and the distances are in world
+    /// units.
+    pub fn chunks_by_distance(&self, point: Vec3) -> Vec<(IVec3, f32)> {
+        let chunk_extent =
+            self.configuration.chunk_size() as f32 * self.configuration.voxel_size();
+        let mut chunks = self
+            .chunk_map
+            .get_read_lock()
+            .keys()
+            .map(|chunk_pos| {
+                let center = (chunk_pos.as_vec3() + 0.5) * chunk_extent;
+                (*chunk_pos, center.distance(point))
+            })
+            .collect::<Vec<_>>();
+        chunks.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        chunks
+    }
+
+    /// Returns true once the voxel texture has finished loading. Chunk meshes are not spawned
+    /// until this is the case, so this can be used to keep a loading screen up.
+    ///
+    /// Always returns true when no texture needs to be loaded, for example when using the built-in
+    /// default texture or a custom material.
+    pub fn is_texture_loaded(&self) -> bool {
+        self.loading_texture
+            .as_ref()
+            .is_none_or(|loading_texture| loading_texture.is_loaded)
+    }
+
+    /// Get a sendable closure that can be used to get the voxel at the given position
+    /// This is useful for spawning tasks that need to access the voxel world
+    pub fn get_voxel_fn(
+        &self,
+    ) -> Arc<dyn Fn(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + Sync> {
+        voxel_lookup_fn(
+            &self.chunk_map,
+            &self.voxel_write_buffer,
+            &self.modified_voxels,
+            &*self.configuration,
+        )
     }
 
     /// Get the ChunkData for the given chunk position
     ///
-    /// The position should be the chunk position, measured in CHUNK_SIZE units (32 by default)
+    /// The position should be the chunk position, measured in `chunk_size` units (32 by default)
     ///
-    /// You can `floor(voxel_position / CHUNK_SIZE)` to get the chunk position from a voxel position
+    /// Use `coords::chunk_pos_of_for_size` to get the chunk position from a voxel position
     pub fn get_chunk_data(
         &self,
         chunk_pos: IVec3,
     ) -> Option<ChunkData<C::MaterialIndex>> {
+        ChunkMap::<C, C::MaterialIndex>::get(&chunk_pos, &self.chunk_map.get_read_lock())
+    }
+
+    /// Same as `get_chunk_data`, but returns the chunk data shared with the chunk map, instead
+    /// of a copy of it. This is cheaper for systems that look at many chunks every frame.
+    pub fn get_chunk_data_arc(
+        &self,
+        chunk_pos: IVec3,
+    ) -> Option<Arc<ChunkData<C::MaterialIndex>>> {
+        ChunkMap::<C, C::MaterialIndex>::get_arc(
+            &chunk_pos,
+            &self.chunk_map.get_read_lock(),
+        )
+    }
+
+    /// Get the entity of the chunk at the given chunk position, without copying its chunk data.
+    /// Returns `None` if the chunk has not been spawned.
+    pub fn chunk_entity(&self, chunk_pos: IVec3) -> Option<Entity> {
         self.chunk_map
-            .get_map()
-            .read()
-            .unwrap()
+            .get_read_lock()
             .get(&chunk_pos)
-            .cloned()
+            .map(|chunk_data| chunk_data.get_entity())
+            .filter(|entity| *entity != Entity::PLACEHOLDER)
     }
 
     pub fn get_chunk_data_fn(
         &self,
     ) -> Arc<dyn Fn(IVec3) -> Option<ChunkData<C::MaterialIndex>> + Send + Sync> {
         let chunk_map = self.chunk_map.get_map();
-        Arc::new(move |chunk_pos| chunk_map.read().unwrap().get(&chunk_pos).cloned())
+        Arc::new(move |chunk_pos| {
+            ChunkMap::<C, C::MaterialIndex>::get(&chunk_pos, &chunk_map.read().unwrap())
+        })
+    }
+
+    /// Get the topmost solid voxel in the column at `x`, `z`, within `y_range`.
+    ///
+    /// The column is scanned from the top down using `get_voxel_fn`, so this works even if the
+    /// chunks in the column have not been spawned, as long as the voxels have been set with
+    /// `set_voxel`. Returns `None` if there is no solid voxel in the range.
+    pub fn highest_solid_in_column(
+        &self,
+        x: i32,
+        z: i32,
+        y_range: RangeInclusive<i32>,
+    ) -> Option<(IVec3, WorldVoxel<C::MaterialIndex>)> {
+        let get_voxel = self.get_voxel_fn();
+
+        y_range.rev().find_map(|y| {
+            let position = IVec3::new(x, y, z);
+            let voxel = get_voxel(position);
+            voxel.is_solid().then_some((position, voxel))
+        })
+    }
+
+    /// Get the positions of all solid voxels in the box between `min` and `max`, both inclusive,
+    /// that have air or an unset voxel directly above them. Useful for scattering things like
+    /// grass or rocks over the terrain. The voxel above the top of the box is checked as well.
+    ///
+    /// Every voxel in the box is sampled with `get_voxel_fn`, so the cost grows with the volume
+    /// of the box. For large regions, query one chunk at a time, with boxes aligned to the chunk
+    /// grid.
+    pub fn surface_voxels_in(&self, min: IVec3, max: IVec3) -> Vec<IVec3> {
+        let (min, max) = (min.min(max), min.max(max));
+        let get_voxel = self.get_voxel_fn();
+
+        let mut surface = Vec::new();
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                // Walk down the column, so each voxel is looked up once
+                let mut above = get_voxel(IVec3::new(x, max.y + 1, z));
+                for y in (min.y..=max.y).rev() {
+                    let position = IVec3::new(x, y, z);
+                    let voxel = get_voxel(position);
+                    if voxel.is_solid() && (above.is_air() || above.is_unset()) {
+                        surface.push(position);
+                    }
+                    above = voxel;
+                }
+            }
+        }
+        surface
     }
 
     /// Get the closes surface voxel to the given position
@@ -270,11 +1108,78 @@ impl<C: VoxelWorldConfig> VoxelWorld<'_, C> {
         })
     }
 
-    /// Get the first solid voxel intersecting with the given ray.
-    /// The `filter` function can be used to filter out voxels that should not be considered for the raycast.
+    /// Returns true if `start` is inside an enclosed space, like a cave, for example to pick
+    /// ambient sounds or where mobs can spawn.
+    ///
+    /// The voxels that aren't solid are flood filled from `start`, through the faces of the
+    /// voxels, and the space is enclosed if the fill runs out before it has visited more than
+    /// `max_open` voxels. Unset voxels are filled like air, so a space that reaches the open sky
+    /// or the edge of the loaded world keeps filling until it runs over the budget. A larger
+    /// `max_open` lets bigger caverns count as enclosed, but every visited voxel is a lookup, so
+    /// the check gets slower for open spaces, which always use up the whole budget. A solid
+    /// voxel at `start` counts as enclosed.
+    pub fn is_enclosed(&self, start: IVec3, max_open: usize) -> bool {
+        let get_voxel = self.get_voxel_fn();
+        if get_voxel(start).is_solid() {
+            return true;
+        }
+
+        let neighbour_offsets = FloodFillConnectivity::Faces.neighbour_offsets();
+        let mut visited = HashSet::new();
+        visited.insert(start);
+        let mut queue = VecDeque::from([start]);
+        while let Some(position) = queue.pop_front() {
+            for offset in neighbour_offsets.iter() {
+                let neighbour = position + *offset;
+                if visited.contains(&neighbour) || get_voxel(neighbour).is_solid() {
+                    continue;
+                }
+                if visited.len() >= max_open {
+                    return false;
+                }
+                visited.insert(neighbour);
+                queue.push_back(neighbour);
+            }
+        }
+
+        true
+    }
+
+    /// The entity that all the chunks of this world are parented to. Moving or rotating it moves
+    /// the whole world, for example to mount it on a ship. See `set_root_transform`.
+    pub fn root_entity(&self) -> Option<Entity> {
+        self.world_root.get_single().ok().map(|(entity, _)| entity)
+    }
+
+    /// Set the transform of the world root, which all the chunks are parented to.
     ///
-    /// Returns a `VoxelRaycastResult` with position, normal and voxel info. The position is given in world space.
-    /// Returns `None` if no voxel was intersected
+    /// Chunk spawning and raycasts happen in the local space of the root, using its
+    /// `GlobalTransform`, so they catch up with the new transform once it has been propagated.
+    /// The root is expected to be moved and rotated, but not scaled.
+    pub fn set_root_transform(&mut self, transform: Transform) {
+        if let Some(root) = self.root_entity() {
+            self.commands.entity(root).try_insert(transform);
+        }
+    }
+
+    fn root_from_world(&self) -> Affine3A {
+        self.world_root
+            .get_single()
+            .map_or(Affine3A::IDENTITY, |(_, root_gtf)| {
+                root_gtf.affine().inverse()
+            })
+    }
+
+    /// Get the first solid or translucent voxel intersecting with the given ray.
+    /// The `filter` function can be used to filter out voxels that should not be considered for the raycast,
+    /// for example to let the ray pass through water.
+    ///
+    /// The ray is given in world space, and is moved into the local space of the world root
+    /// before it is traced, so the world can be mounted on a moving root.
+    ///
+    /// Returns a `VoxelRaycastResult` with position, normal and voxel info. The position and
+    /// normal are given in the local space of the world root, which is world space unless the
+    /// root has been moved. Returns `None` if no voxel was intersected
     ///
     /// # Example
     /// ```
@@ -308,52 +1213,565 @@ impl<C: VoxelWorldConfig> VoxelWorld<'_, C> {
         raycast_fn(ray, filter)
     }
 
+    /// Same as `raycast`, but the voxel that the ray starts inside is never hit. Useful for
+    /// interaction rays from a camera or player that can be embedded in a voxel, for example
+    /// right after placing a block, which would otherwise be reported straight away.
+    pub fn raycast_from_surface(
+        &self,
+        ray: Ray3d,
+        filter: &impl Fn((Vec3, WorldVoxel<C::MaterialIndex>)) -> bool,
+    ) -> Option<VoxelRaycastResult<C::MaterialIndex>> {
+        let raycast_fn = raycast_fn_for(
+            &self.chunk_map,
+            self.get_voxel_fn(),
+            &*self.configuration,
+            self.root_from_world(),
+            true,
+        );
+        raycast_fn(ray, filter)
+    }
+
     /// Get a sendable closure that can be used to raycast into the voxel world
     pub fn raycast_fn(&self) -> Arc<RaycastFn<C::MaterialIndex>> {
+        raycast_fn_for(
+            &self.chunk_map,
+            self.get_voxel_fn(),
+            &*self.configuration,
+            self.root_from_world(),
+            false,
+        )
+    }
+
+    /// Like `raycast`, but instead of stopping at the first solid voxel, this collects every
+    /// solid or translucent voxel that the ray passes through within `max_distance`.
+    ///
+    /// The results are ordered front-to-back along the ray. The `normal` of each result is the
+    /// normal of the face through which the ray entered that voxel.
+    pub fn raycast_all(
+        &self,
+        ray: Ray3d,
+        max_distance: f32,
+        filter: &impl Fn((Vec3, WorldVoxel<C::MaterialIndex>)) -> bool,
+    ) -> Vec<VoxelRaycastResult<C::MaterialIndex>> {
+        let raycast_all_fn = self.raycast_all_fn();
+        raycast_all_fn(ray, max_distance, filter)
+    }
+
+    /// Get a sendable closure that can be used to raycast into the voxel world, collecting all
+    /// solid voxels along the ray. See `raycast_all`.
+    pub fn raycast_all_fn(&self) -> Arc<RaycastAllFn<C::MaterialIndex>> {
         let chunk_map = self.chunk_map.get_map();
         let get_voxel = self.get_voxel_fn();
+        let chunk_size = self.configuration.chunk_size();
+        let voxel_size = self.configuration.voxel_size();
+        let root_from_world = self.root_from_world();
 
-        Arc::new(move |ray, filter| {
-            let p = ray.origin;
-            let d = ray.direction;
-
+        Arc::new(move |ray, max_distance, filter| {
+            let ray = ray_to_root_space(ray, root_from_world);
             let loaded_aabb = ChunkMap::<C, C::MaterialIndex>::get_world_bounds(
                 &chunk_map.read().unwrap(),
+                chunk_size,
+                voxel_size,
             );
-            let trace_start = if p.cmplt(loaded_aabb.min.into()).any()
-                || p.cmpgt(loaded_aabb.max.into()).any()
-            {
-                if let Some(trace_start_t) =
-                    RayCast3d::from_ray(ray, f32::MAX).aabb_intersection_at(&loaded_aabb)
-                {
-                    ray.get_point(trace_start_t)
+            let Some((trace_start, trace_end)) =
+                get_trace_bounds_within(ray, &loaded_aabb, max_distance)
+            else {
+                return Vec::new();
+            };
+
+            let mut raycast_results = Vec::new();
+            voxel_line_traversal_with_size(
+                trace_start,
+                trace_end,
+                voxel_size,
+                |voxel_coords, _time, face| {
+                    let voxel = get_voxel(voxel_coords);
+                    let position = voxel_coords.as_vec3() * voxel_size;
+
+                    if (voxel.is_solid() || voxel.is_translucent())
+                        && filter.call((position, voxel))
+                    {
+                        raycast_results.push(VoxelRaycastResult {
+                            position,
+                            normal: face.try_into().ok(),
+                            voxel,
+                            voxel_coords,
+                        });
+                    }
+
+                    // Always continue traversing
+                    true
+                },
+            );
+
+            raycast_results
+        })
+    }
+
+    /// Get the material of the surface below the given world position, for example to pick
+    /// footstep sounds or particle effects for a character standing at `world_pos`.
+    ///
+    /// A short ray is cast straight down, reaching one and a half voxels below `world_pos`, and
+    /// the material of the first solid or translucent voxel it hits is returned. Returns `None`
+    /// if there is no such voxel within reach, or if the chunk below is not loaded.
+    pub fn material_at_feet(&self, world_pos: Vec3) -> Option<C::MaterialIndex> {
+        let ray = Ray3d {
+            origin: world_pos,
+            direction: Dir3::NEG_Y,
+        };
+        let max_distance = self.configuration.voxel_size() * 1.5;
+
+        self.raycast_all(ray, max_distance, &|_| true)
+            .first()
+            .and_then(|result| match result.voxel {
+                WorldVoxel::Solid(material) | WorldVoxel::Translucent(material) => {
+                    Some(material)
+                }
+                _ => None,
+            })
+    }
+
+    /// Find the floor below `from`, for example to keep a character controller on the ground.
+    ///
+    /// A ray is cast straight down from `from`, and the first solid voxel within `max_drop` is
+    /// returned. Translucent voxels, like water, are passed through. Unlike `raycast`, the
+    /// `position` of the result is on the top face of the voxel, at the min corner of the voxel
+    /// above it, so the feet of a character at `from` would be snapped to `result.position.y`.
+    /// For a transformed world root, down is along the root's own `-Y` axis. Returns `None` if
+    /// there is no floor within `max_drop`, or if the chunks below are not loaded.
+    pub fn ground_below(
+        &self,
+        from: Vec3,
+        max_drop: f32,
+    ) -> Option<VoxelRaycastResult<C::MaterialIndex>> {
+        let get_voxel = self.get_voxel_fn();
+        let voxel_size = self.configuration.voxel_size();
+        let ray = Ray3d {
+            origin: self.root_from_world().transform_point3(from),
+            direction: Dir3::NEG_Y,
+        };
+
+        let loaded_aabb = ChunkMap::<C, C::MaterialIndex>::get_world_bounds(
+            &self.chunk_map.get_read_lock(),
+            self.configuration.chunk_size(),
+            voxel_size,
+        );
+        let (trace_start, trace_end) =
+            get_trace_bounds_within(ray, &loaded_aabb, max_drop)?;
+
+        let mut ground = None;
+        voxel_line_traversal_with_size(
+            trace_start,
+            trace_end,
+            voxel_size,
+            |voxel_coords, _time, face| {
+                let voxel = get_voxel(voxel_coords);
+                if !voxel.is_solid() {
+                    return true;
+                }
+                ground = Some(VoxelRaycastResult {
+                    position: (voxel_coords.as_vec3() + Vec3::Y) * voxel_size,
+                    normal: face.try_into().ok(),
+                    voxel,
+                    voxel_coords,
+                });
+                // Found the floor - stop traversing
+                false
+            },
+        );
+
+        ground
+    }
+
+    /// Sweep a sphere of `radius` from `origin` along `dir`, and find the first solid voxel it
+    /// touches within `max_distance`. The normal of the result is that of the face that was
+    /// touched, and the position is where the center of the sphere is when it touches the
+    /// voxel, so the distance the sphere moved is the distance from `origin` to the position.
+    /// Use `voxel_coords` for the voxel itself. A sphere that already overlaps a solid voxel at
+    /// `origin` touches it right away.
+    ///
+    /// Like `raycast`, the position and normal are in the local space of the world root.
+    ///
+    /// Unlike `raycast`, this doesn't miss thin walls and edges that pass between the center
+    /// of a moving character and its outline. The sweep is approximated by checking the sphere
+    /// against the voxels around it at steps of a quarter of the voxel size, which is too short
+    /// to step over a voxel, and then narrowing down the contact between the last two steps,
+    /// so the contact is exact to within a small fraction of a voxel. The cost grows with
+    /// `max_distance` and the size of the sphere in voxels.
+    pub fn sphere_cast(
+        &self,
+        origin: Vec3,
+        radius: f32,
+        dir: Dir3,
+        max_distance: f32,
+    ) -> Option<VoxelRaycastResult<C::MaterialIndex>> {
+        let get_voxel = self.get_voxel_fn();
+        let voxel_size = self.configuration.voxel_size();
+        let radius = radius.max(0.0);
+        let Ray3d {
+            origin,
+            direction: dir,
+        } = ray_to_root_space(Ray3d::new(origin, dir), self.root_from_world());
+
+        // The closest solid voxel overlapping the sphere at `center`
+        let contact = |center: Vec3| {
+            let min = ((center - radius) / voxel_size).floor().as_ivec3();
+            let max = ((center + radius) / voxel_size).floor().as_ivec3();
+            let mut closest: Option<(IVec3, WorldVoxel<C::MaterialIndex>, Vec3, f32)> =
+                None;
+            for z in min.z..=max.z {
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let voxel_coords = IVec3::new(x, y, z);
+                        let voxel_min = voxel_coords.as_vec3() * voxel_size;
+                        let closest_point =
+                            center.clamp(voxel_min, voxel_min + voxel_size);
+                        let distance_squared = center.distance_squared(closest_point);
+                        if distance_squared > radius * radius
+                            || closest
+                                .is_some_and(|(.., closest)| closest <= distance_squared)
+                        {
+                            continue;
+                        }
+                        let voxel = get_voxel(voxel_coords);
+                        if voxel.is_solid() {
+                            closest = Some((
+                                voxel_coords,
+                                voxel,
+                                closest_point,
+                                distance_squared,
+                            ));
+                        }
+                    }
+                }
+            }
+            closest
+        };
+
+        // Bisection narrows down the contact afterwards, so the step only needs to be short
+        // enough to never pass over a voxel
+        let step = voxel_size * 0.25;
+        let mut free = 0.0;
+        let mut touching = None;
+        let mut distance = 0.0_f32;
+        loop {
+            if contact(origin + dir * distance).is_some() {
+                touching = Some(distance);
+                break;
+            }
+            if distance >= max_distance {
+                break;
+            }
+            free = distance;
+            distance = (distance + step).min(max_distance);
+        }
+        let mut touching = touching?;
+
+        // Narrow down where the sphere first touches, between the last free and touching steps
+        if touching > 0.0 {
+            for _ in 0..16 {
+                let middle = (free + touching) * 0.5;
+                if contact(origin + dir * middle).is_some() {
+                    touching = middle;
                 } else {
-                    return None;
+                    free = middle;
                 }
+            }
+        }
+
+        let center = origin + dir * touching;
+        let (voxel_coords, voxel, closest_point, _) = contact(center)?;
+
+        // The face the sphere touched is the one facing the sphere center. When the center is
+        // inside the voxel, the face the sphere came in through is used instead.
+        let offset = center - closest_point;
+        let offset = if offset.length_squared() > 0.0 {
+            offset
+        } else {
+            -*dir
+        };
+        let abs = offset.abs();
+        let face = if abs.x >= abs.y && abs.x >= abs.z {
+            if offset.x > 0.0 {
+                VoxelFace::Right
             } else {
-                p
-            };
+                VoxelFace::Left
+            }
+        } else if abs.y >= abs.z {
+            if offset.y > 0.0 {
+                VoxelFace::Top
+            } else {
+                VoxelFace::Bottom
+            }
+        } else if offset.z > 0.0 {
+            VoxelFace::Forward
+        } else {
+            VoxelFace::Back
+        };
+
+        Some(VoxelRaycastResult {
+            position: center,
+            normal: Vec3::try_from(face).ok(),
+            voxel,
+            voxel_coords,
+        })
+    }
+
+    /// Returns true if there is a solid voxel on the line segment between `from` and `to`.
+    ///
+    /// This is meant for line of sight checks, so translucent voxels don't block the segment.
+    /// Like `raycast`, only the loaded part of the world is considered, and the traversal stops
+    /// at the first solid voxel.
+    pub fn segment_blocked(&self, from: Vec3, to: Vec3) -> bool {
+        let get_voxel = self.get_voxel_fn();
+        let voxel_size = self.configuration.voxel_size();
+        let root_from_world = self.root_from_world();
+        let (from, to) = (
+            root_from_world.transform_point3(from),
+            root_from_world.transform_point3(to),
+        );
+
+        let Ok(direction) = Dir3::new(to - from) else {
+            return get_voxel((from / voxel_size).floor().as_ivec3()).is_solid();
+        };
+
+        let loaded_aabb = ChunkMap::<C, C::MaterialIndex>::get_world_bounds(
+            &self.chunk_map.get_read_lock(),
+            self.configuration.chunk_size(),
+            voxel_size,
+        );
+        let Some((trace_start, trace_end)) = get_trace_bounds_within(
+            Ray3d::new(from, direction),
+            &loaded_aabb,
+            from.distance(to),
+        ) else {
+            return false;
+        };
+
+        let mut blocked = false;
+        voxel_line_traversal_with_size(
+            trace_start,
+            trace_end,
+            voxel_size,
+            |voxel_coords, _time, _face| {
+                blocked = get_voxel(voxel_coords).is_solid();
+
+                // Stop traversing at the first solid voxel
+                !blocked
+            },
+        );
+
+        blocked
+    }
+
+    /// Debugging aid that samples every voxel the ray passes through within `max_distance`,
+    /// regardless of whether it is solid, air or unset. This is useful for inspecting the layers
+    /// of generated terrain.
+    ///
+    /// Unlike `raycast` and `raycast_all`, the ray is not limited to the loaded part of the
+    /// world, so voxels in chunks that are not spawned are included as `WorldVoxel::Unset`.
+    /// The results are ordered front-to-back along the ray.
+    pub fn raycast_xray(
+        &self,
+        ray: Ray3d,
+        max_distance: f32,
+    ) -> Vec<VoxelRaycastResult<C::MaterialIndex>> {
+        let get_voxel = self.get_voxel_fn();
+        let voxel_size = self.configuration.voxel_size();
+        let ray = ray_to_root_space(ray, self.root_from_world());
+
+        let mut samples = Vec::new();
+        voxel_line_traversal_with_size(
+            ray.origin,
+            ray.get_point(max_distance),
+            voxel_size,
+            |voxel_coords, _time, face| {
+                samples.push(VoxelRaycastResult {
+                    position: voxel_coords.as_vec3() * voxel_size,
+                    normal: face.try_into().ok(),
+                    voxel: get_voxel(voxel_coords),
+                    voxel_coords,
+                });
+
+                true
+            },
+        );
+
+        samples
+    }
+}
+
+/// Read-only access to the VoxelWorld in systems.
+///
+/// Unlike `VoxelWorld`, this only takes `Res` access to the voxel world resources, so systems
+/// that only read voxels, like pathfinding or debug overlays, can run in parallel with each
+/// other. Writes still need `VoxelWorld`.
+///
+/// Like the reads of `VoxelWorld`, the reader sees writes that are still pending in the write
+/// buffer, so a voxel set earlier in the frame is returned before it has been flushed.
+#[derive(SystemParam)]
+pub struct VoxelWorldReader<'w, 's, C: VoxelWorldConfig> {
+    chunk_map: Res<'w, ChunkMap<C, <C as VoxelWorldConfig>::MaterialIndex>>,
+    modified_voxels: Res<'w, ModifiedVoxels<C, <C as VoxelWorldConfig>::MaterialIndex>>,
+    voxel_write_buffer:
+        Res<'w, VoxelWriteBuffer<C, <C as VoxelWorldConfig>::MaterialIndex>>,
+    configuration: Res<'w, C>,
+    world_root: Query<'w, 's, &'static GlobalTransform, With<WorldRoot<C>>>,
+}
+
+impl<C: VoxelWorldConfig> VoxelWorldReader<'_, '_, C> {
+    /// Get the voxel at the given position. The voxel will be WorldVoxel::Unset if there is no voxel at that position
+    pub fn get_voxel(&self, position: IVec3) -> WorldVoxel<C::MaterialIndex> {
+        self.get_voxel_fn()(position)
+    }
+
+    /// Get a sendable closure that can be used to get the voxel at the given position.
+    /// See `VoxelWorld::get_voxel_fn`.
+    pub fn get_voxel_fn(
+        &self,
+    ) -> Arc<dyn Fn(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + Sync> {
+        voxel_lookup_fn(
+            &self.chunk_map,
+            &self.voxel_write_buffer,
+            &self.modified_voxels,
+            &*self.configuration,
+        )
+    }
+
+    /// Get the ChunkData for the given chunk position. See `VoxelWorld::get_chunk_data`.
+    pub fn get_chunk_data(
+        &self,
+        chunk_pos: IVec3,
+    ) -> Option<ChunkData<C::MaterialIndex>> {
+        ChunkMap::<C, C::MaterialIndex>::get(&chunk_pos, &self.chunk_map.get_read_lock())
+    }
+
+    /// Get the closest solid voxel hit by the given ray. See `VoxelWorld::raycast`.
+    pub fn raycast(
+        &self,
+        ray: Ray3d,
+        filter: &impl Fn((Vec3, WorldVoxel<C::MaterialIndex>)) -> bool,
+    ) -> Option<VoxelRaycastResult<C::MaterialIndex>> {
+        let raycast_fn = raycast_fn_for(
+            &self.chunk_map,
+            self.get_voxel_fn(),
+            &*self.configuration,
+            root_from_world(&self.world_root),
+            false,
+        );
+        raycast_fn(ray, filter)
+    }
+}
+
+/// Builds the closure behind `get_voxel_fn`, shared by `VoxelWorld` and `VoxelWorldReader`.
+/// Pending writes are checked first, then modified voxels, then the chunk data. With
+/// `ModificationPrecedence::Fallback` the chunk data comes first instead, and the
+/// modifications are only used where it has nothing to say.
+fn voxel_lookup_fn<C: VoxelWorldConfig>(
+    chunk_map: &ChunkMap<C, C::MaterialIndex>,
+    write_buffer: &HashMap<IVec3, WorldVoxel<C::MaterialIndex>>,
+    modified_voxels: &ModifiedVoxels<C, C::MaterialIndex>,
+    configuration: &C,
+) -> Arc<dyn Fn(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + Sync> {
+    let chunk_map = chunk_map.get_map();
+    let write_buffer = write_buffer.clone();
+    let modified_voxels = modified_voxels.clone();
+    let chunk_size = configuration.chunk_size();
+    let world_chunk_bounds = configuration.world_chunk_bounds();
+    let modification_precedence = configuration.modification_precedence();
+
+    Arc::new(move |position| {
+        let (chunk_pos, vox_pos) =
+            get_chunk_voxel_position_for_size(position, chunk_size);
+
+        if let Some((min, max)) = world_chunk_bounds {
+            if chunk_pos.cmplt(min).any() || chunk_pos.cmpgt(max).any() {
+                return WorldVoxel::Unset;
+            }
+        }
+
+        let modified_voxel = || {
+            write_buffer
+                .get(&position)
+                .copied()
+                .or_else(|| modified_voxels.get_voxel(&position))
+        };
+
+        if modification_precedence == ModificationPrecedence::Override {
+            if let Some(voxel) = modified_voxel() {
+                return voxel;
+            }
+        }
+
+        let chunk_opt = {
+            let chun_map_read = chunk_map.read().unwrap();
+            chun_map_read.get(&chunk_pos).cloned()
+        };
+
+        let chunk_voxel = chunk_opt
+            .map(|chunk_data| chunk_data.get_voxel(vox_pos))
+            .unwrap_or(WorldVoxel::Unset);
+
+        match modification_precedence {
+            ModificationPrecedence::Override => chunk_voxel,
+            ModificationPrecedence::Fallback => {
+                // The chunk data already holds the modification wherever the lookup left
+                // the voxel unset, so a pending write may replace it there
+                let applied = modified_voxels.get_voxel(&position);
+                if chunk_voxel.is_unset() || applied == Some(chunk_voxel) {
+                    modified_voxel().unwrap_or(chunk_voxel)
+                } else {
+                    chunk_voxel
+                }
+            }
+        }
+    })
+}
+
+/// Builds the closure behind `raycast_fn`, shared by `VoxelWorld` and `VoxelWorldReader`.
+fn raycast_fn_for<C: VoxelWorldConfig>(
+    chunk_map: &ChunkMap<C, C::MaterialIndex>,
+    get_voxel: Arc<dyn Fn(IVec3) -> WorldVoxel<C::MaterialIndex> + Send + Sync>,
+    configuration: &C,
+    root_from_world: Affine3A,
+    ignore_origin_voxel: bool,
+) -> Arc<RaycastFn<C::MaterialIndex>> {
+    let chunk_map = chunk_map.get_map();
+    let chunk_size = configuration.chunk_size();
+    let voxel_size = configuration.voxel_size();
+
+    Arc::new(move |ray, filter| {
+        let ray = ray_to_root_space(ray, root_from_world);
+        let loaded_aabb = ChunkMap::<C, C::MaterialIndex>::get_world_bounds(
+            &chunk_map.read().unwrap(),
+            chunk_size,
+            voxel_size,
+        );
+        let (trace_start, trace_end) = get_trace_bounds(ray, &loaded_aabb)?;
+        let origin_voxel =
+            ignore_origin_voxel.then(|| (ray.origin / voxel_size).floor().as_ivec3());
+
+        let mut raycast_result = None;
+        voxel_line_traversal_with_size(
+            trace_start,
+            trace_end,
+            voxel_size,
+            |voxel_coords, _time, face| {
+                if origin_voxel == Some(voxel_coords) {
+                    // Starting inside this voxel - continue traversing
+                    return true;
+                }
 
-            // To find where we get out of the loaded cuboid, we can intersect from a point
-            // guaranteed to be on the other side of the cube and in the opposite direction
-            // of the ray.
-            let trace_end_orig =
-                trace_start + d * loaded_aabb.min.distance_squared(loaded_aabb.max);
-            let trace_end_t = RayCast3d::new(trace_end_orig, -ray.direction, f32::MAX)
-                .aabb_intersection_at(&loaded_aabb)
-                .unwrap();
-            let trace_end = Ray3d::new(trace_end_orig, -d).get_point(trace_end_t);
-
-            let mut raycast_result = None;
-            voxel_line_traversal(trace_start, trace_end, |voxel_coords, _time, face| {
                 let voxel = get_voxel(voxel_coords);
+                let position = voxel_coords.as_vec3() * voxel_size;
 
-                if !voxel.is_unset() && filter.call((voxel_coords.as_vec3(), voxel)) {
-                    if voxel.is_solid() {
+                if !voxel.is_unset() && filter.call((position, voxel)) {
+                    if voxel.is_solid() || voxel.is_translucent() {
                         raycast_result = Some(VoxelRaycastResult {
-                            position: voxel_coords.as_vec3(),
+                            position,
                             normal: face.try_into().ok(),
                             voxel,
+                            voxel_coords,
                         });
 
                         // Found solid voxel - stop traversing
@@ -366,23 +1784,88 @@ impl<C: VoxelWorldConfig> VoxelWorld<'_, C> {
                     // Ignoring this voxel bc of filter - continue traversing
                     true
                 }
-            });
+            },
+        );
 
-            raycast_result
-        })
+        raycast_result
+    })
+}
+
+/// Moves a ray from world space into the local space of the world root, where the voxels are
+fn ray_to_root_space(ray: Ray3d, root_from_world: Affine3A) -> Ray3d {
+    Ray3d {
+        origin: root_from_world.transform_point3(ray.origin),
+        direction: Dir3::new(root_from_world.transform_vector3(*ray.direction))
+            .unwrap_or(ray.direction),
+    }
+}
+
+/// Returns the start and end points for tracing the given ray through the loaded part of the
+/// world, or None if the ray does not intersect it.
+fn get_trace_bounds(ray: Ray3d, loaded_aabb: &Aabb3d) -> Option<(Vec3, Vec3)> {
+    let p = ray.origin;
+    let d = ray.direction;
+
+    let trace_start = if p.cmplt(loaded_aabb.min.into()).any()
+        || p.cmpgt(loaded_aabb.max.into()).any()
+    {
+        let trace_start_t =
+            RayCast3d::from_ray(ray, f32::MAX).aabb_intersection_at(loaded_aabb)?;
+        ray.get_point(trace_start_t)
+    } else {
+        p
+    };
+
+    // To find where we get out of the loaded cuboid, we can intersect from a point
+    // guaranteed to be on the other side of the cube and in the opposite direction
+    // of the ray.
+    let trace_end_orig =
+        trace_start + d * loaded_aabb.min.distance_squared(loaded_aabb.max);
+    let trace_end_t = RayCast3d::new(trace_end_orig, -ray.direction, f32::MAX)
+        .aabb_intersection_at(loaded_aabb)
+        .unwrap();
+    let trace_end = Ray3d::new(trace_end_orig, -d).get_point(trace_end_t);
+
+    Some((trace_start, trace_end))
+}
+
+/// Same as `get_trace_bounds`, but limits the trace to `max_distance` from the ray origin
+fn get_trace_bounds_within(
+    ray: Ray3d,
+    loaded_aabb: &Aabb3d,
+    max_distance: f32,
+) -> Option<(Vec3, Vec3)> {
+    let (trace_start, trace_end) = get_trace_bounds(ray, loaded_aabb)?;
+
+    if ray.origin.distance(trace_start) > max_distance {
+        return None;
+    }
+
+    if ray.origin.distance(trace_end) > max_distance {
+        Some((trace_start, ray.get_point(max_distance)))
+    } else {
+        Some((trace_start, trace_end))
     }
 }
 
 /// Returns a tuple of the chunk position and the voxel position within the chunk.
+///
+/// This assumes the default chunk size. For worlds with a custom `chunk_size`, use
+/// `get_chunk_voxel_position_for_size`.
 #[inline]
 pub fn get_chunk_voxel_position(position: IVec3) -> (IVec3, UVec3) {
-    let chunk_position = IVec3 {
-        x: (position.x as f32 / CHUNK_SIZE_F).floor() as i32,
-        y: (position.y as f32 / CHUNK_SIZE_F).floor() as i32,
-        z: (position.z as f32 / CHUNK_SIZE_F).floor() as i32,
-    };
-
-    let voxel_position = (position - chunk_position * CHUNK_SIZE_I).as_uvec3() + 1;
+    get_chunk_voxel_position_for_size(position, CHUNK_SIZE_U)
+}
 
-    (chunk_position, voxel_position)
+/// Returns a tuple of the chunk position and the voxel position within the chunk, for chunks of
+/// the given size.
+#[inline]
+pub fn get_chunk_voxel_position_for_size(
+    position: IVec3,
+    chunk_size: u32,
+) -> (IVec3, UVec3) {
+    (
+        chunk_pos_of_for_size(position, chunk_size),
+        local_in_chunk_for_size(position, chunk_size) + 1,
+    )
 }
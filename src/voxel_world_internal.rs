@@ -3,37 +3,101 @@
 /// This module contains the internal systems and resources used to implement bevy_voxel_world.
 ///
 use bevy::{
-    ecs::system::SystemParam,
+    ecs::{query::QuerySingleError, system::SystemParam},
+    math::Affine3A,
     prelude::*,
+    render::{
+        camera::CameraProjection,
+        primitives::{Aabb, Frustum},
+    },
     tasks::AsyncComputeTaskPool,
-    utils::{HashMap, HashSet},
+    utils::{HashMap, HashSet, Instant},
 };
 use futures_lite::future;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::{
     collections::VecDeque,
     marker::PhantomData,
-    sync::{Arc, RwLock},
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
 };
 
 use crate::{
     chunk::*,
     chunk_map::*,
-    configuration::{ChunkDespawnStrategy, ChunkSpawnStrategy, VoxelWorldConfig},
+    configuration::{
+        minimal_chunk_meshing_delegate, ChunkDespawnStrategy, ChunkMeshAttributes,
+        ChunkRegenerateStrategy, ChunkSpawnStrategy, MaterialAlphaFn,
+        ModificationPrecedence, SharedVoxelLookupFn, SpawningRayPattern, VoxelLightFn,
+        VoxelLookupFn, VoxelWorldConfig,
+    },
+    debug_draw::ChunkMeshedAt,
+    diagnostics::VoxelWorldDiagnostics,
     mesh_cache::*,
+    meshing::{add_detail_uvs, add_surface_distances, add_voxel_light, ChunkMeshOptions},
     plugin::VoxelWorldMaterialHandle,
-    prelude::default_chunk_meshing_delegate,
+    prelude::default_chunk_meshing_delegate_with_options,
     voxel::WorldVoxel,
     voxel_material::LoadingTexture,
     voxel_world::{
-        get_chunk_voxel_position, ChunkWillDespawn, ChunkWillRemesh, ChunkWillSpawn,
-        ChunkWillUpdate, VoxelWorldCamera,
+        get_chunk_voxel_position_for_size, ChunkGenerationFailed, ChunkInvalidated,
+        ChunkMeshReady, ChunkWillDespawn, ChunkWillRemesh, ChunkWillSpawn,
+        ChunkWillUpdate, InvalidationReason, VoxelWorldAnchor, VoxelWorldCamera,
     },
 };
 
-#[derive(SystemParam, Deref)]
-pub struct CameraInfo<'w, 's, C: VoxelWorldConfig>(
-    Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<VoxelWorldCamera<C>>>,
-);
+#[derive(SystemParam)]
+pub struct CameraInfo<'w, 's, C: VoxelWorldConfig> {
+    cameras: Query<
+        'w,
+        's,
+        (&'static Camera, &'static GlobalTransform),
+        With<VoxelWorldCamera<C>>,
+    >,
+    world_root: Query<'w, 's, &'static GlobalTransform, With<WorldRoot<C>>>,
+}
+
+impl<C: VoxelWorldConfig> CameraInfo<'_, '_, C> {
+    /// The camera of the world, with its transform in the local space of the world root
+    pub fn get_single(&self) -> Result<(&Camera, GlobalTransform), QuerySingleError> {
+        let root_from_world = root_from_world(&self.world_root);
+        self.cameras.get_single().map(|(camera, cam_gtf)| {
+            (
+                camera,
+                GlobalTransform::from(root_from_world * cam_gtf.affine()),
+            )
+        })
+    }
+}
+
+#[derive(SystemParam)]
+pub struct AnchorInfo<'w, 's, C: VoxelWorldConfig> {
+    anchors: Query<'w, 's, &'static GlobalTransform, With<VoxelWorldAnchor<C>>>,
+    world_root: Query<'w, 's, &'static GlobalTransform, With<WorldRoot<C>>>,
+}
+
+impl<C: VoxelWorldConfig> AnchorInfo<'_, '_, C> {
+    /// The transforms of the anchors of the world, in the local space of the world root
+    pub fn iter(&self) -> impl Iterator<Item = GlobalTransform> + '_ {
+        let root_from_world = root_from_world(&self.world_root);
+        self.anchors.iter().map(move |anchor_gtf| {
+            GlobalTransform::from(root_from_world * anchor_gtf.affine())
+        })
+    }
+}
+
+/// The transform from world space to the local space of the world root, which the chunks are
+/// laid out in. This is the identity until the root has been moved.
+pub(crate) fn root_from_world<C: VoxelWorldConfig>(
+    world_root: &Query<&GlobalTransform, With<WorldRoot<C>>>,
+) -> Affine3A {
+    world_root
+        .get_single()
+        .map_or(Affine3A::IDENTITY, |root_gtf| root_gtf.affine().inverse())
+}
 
 /// Holds a map of modified voxels that will persist between chunk spawn/despawn
 #[derive(Resource, Deref, DerefMut, Clone)]
@@ -56,15 +120,102 @@ impl<C: VoxelWorldConfig> ModifiedVoxels<C, C::MaterialIndex> {
 }
 
 /// A temporary buffer for voxel modifications that will get flushed to the `ModifiedVoxels` resource
-/// at the end of the frame.
+/// at the end of the frame. Keyed by position, so that the last write to a position wins.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct VoxelWriteBuffer<C, I>(#[deref] HashMap<IVec3, WorldVoxel<I>>, PhantomData<C>);
+
+/// A temporary buffer for positions cleared with `VoxelWorld::clear_voxel`, which will have their
+/// modifications removed at the end of the frame.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct VoxelClearBuffer<C>(#[deref] HashSet<IVec3>, PhantomData<C>);
+
+/// A temporary buffer for chunks queued with `VoxelWorld::load_chunk`, which will get spawned
+/// at the start of the next chunk update.
 #[derive(Resource, Deref, DerefMut, Default)]
-pub struct VoxelWriteBuffer<C, I>(#[deref] Vec<(IVec3, WorldVoxel<I>)>, PhantomData<C>);
+pub struct ChunkLoadBuffer<C>(#[deref] Vec<IVec3>, PhantomData<C>);
+
+/// Chunk entities that were despawned with `VoxelWorldConfig::pool_chunk_entities`. They are
+/// stripped of their components and kept alive, to be reused for the next spawned chunks.
+#[derive(Resource, Default)]
+pub struct ChunkEntityPool<C> {
+    /// Entities that have been stripped and can be reused
+    ready: Vec<Entity>,
+    /// Entities that were retired in the last chunk update. Their components are only removed
+    /// once the commands of that update have been applied, so they aren't reused until the next.
+    retired: Vec<Entity>,
+    _marker: PhantomData<C>,
+}
+
+impl<C> ChunkEntityPool<C> {
+    /// Takes an entity to reuse for a new chunk, skipping any that have been despawned since they
+    /// were pooled
+    fn take(&mut self, commands: &mut Commands) -> Option<Entity> {
+        std::iter::from_fn(|| self.ready.pop())
+            .find(|entity| commands.get_entity(*entity).is_some())
+    }
+}
+
+/// The velocity of the camera, from how far it moved since the last frame, in the local space of
+/// the world root. Used to spawn chunks ahead of the camera with
+/// `VoxelWorldConfig::spawn_lookahead_seconds`.
+#[derive(Resource)]
+pub struct CameraVelocity<C> {
+    last_position: Option<Vec3>,
+    velocity: Vec3,
+    _marker: PhantomData<C>,
+}
+
+impl<C> Default for CameraVelocity<C> {
+    fn default() -> Self {
+        Self {
+            last_position: None,
+            velocity: Vec3::ZERO,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A copy of the configuration from the last time it changed, which
+/// `VoxelWorldConfig::config_change_affects_chunk` compares against.
+#[derive(Resource, Deref)]
+pub struct PreviousConfig<C>(C);
+
+/// The random number generator used to pick the points in the viewport that chunk spawning
+/// rays are cast through. Seeded with `VoxelWorldConfig::spawn_seed` when it is set.
+#[derive(Resource, Deref, DerefMut)]
+pub struct ChunkSpawnRng<C>(#[deref] StdRng, PhantomData<C>);
+
+impl<C> ChunkSpawnRng<C> {
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self(rng, PhantomData)
+    }
+}
 
 #[derive(Component)]
 pub(crate) struct NeedsMaterial<C>(PhantomData<C>);
 
 pub(crate) struct Internals<C>(PhantomData<C>);
 
+/// The voxel lookup that a chunk task generates the voxels of its chunk with
+pub(crate) enum ChunkVoxelLookup<I> {
+    Sequential(VoxelLookupFn<I>),
+    Shared(SharedVoxelLookupFn<I>),
+}
+
+/// The frame each chunk was last seen by the camera or an anchor, for
+/// `ChunkDespawnStrategy::LeastRecentlySeen`
+#[derive(Default)]
+pub(crate) struct ChunkLastSeen {
+    frame: u64,
+    chunks: HashMap<IVec3, u64>,
+}
+
+/// The parent of all the chunks of a world. Moving or rotating it moves the whole world, and
+/// chunk spawning and raycasts happen in its local space.
 #[derive(Component)]
 pub struct WorldRoot<C>(PhantomData<C>);
 
@@ -74,14 +225,29 @@ where
 {
     /// Init the resources used internally by bevy_voxel_world
     pub fn setup(mut commands: Commands, configuration: Res<C>) {
+        if cfg!(debug_assertions) {
+            for problem in configuration_problems(configuration.as_ref()) {
+                warn!("{}", problem);
+            }
+        }
+
         commands.init_resource::<ChunkMap<C, C::MaterialIndex>>();
         commands.init_resource::<ChunkMapInsertBuffer<C, C::MaterialIndex>>();
         commands.init_resource::<ChunkMapUpdateBuffer<C, C::MaterialIndex>>();
         commands.init_resource::<ChunkMapRemoveBuffer<C>>();
         commands.init_resource::<MeshCache<C>>();
         commands.init_resource::<MeshCacheInsertBuffer<C>>();
+        commands.init_resource::<MeshPreloadBuffer<C>>();
+        commands.init_resource::<PreloadedMeshes<C>>();
         commands.init_resource::<ModifiedVoxels<C, C::MaterialIndex>>();
         commands.init_resource::<VoxelWriteBuffer<C, C::MaterialIndex>>();
+        commands.init_resource::<VoxelClearBuffer<C>>();
+        commands.init_resource::<ChunkLoadBuffer<C>>();
+        commands.init_resource::<CameraVelocity<C>>();
+        commands.init_resource::<ChunkEntityPool<C>>();
+        commands.init_resource::<VoxelWorldDiagnostics<C>>();
+        commands.insert_resource(ChunkSpawnRng::<C>::new(configuration.spawn_seed()));
+        commands.insert_resource(PreviousConfig(configuration.clone()));
 
         // Create the root node and allow to modify it by the configuration.
         let world_root = commands
@@ -94,7 +260,29 @@ where
         configuration.init_root(commands, world_root)
     }
 
+    /// Update the velocity of the camera from how far it moved since the last frame
+    pub fn track_camera_velocity(
+        mut camera_velocity: ResMut<CameraVelocity<C>>,
+        camera_info: CameraInfo<C>,
+        time: Res<Time>,
+    ) {
+        let position = camera_info
+            .get_single()
+            .ok()
+            .map(|(_, cam_gtf)| cam_gtf.translation());
+        let delta_secs = time.delta_secs();
+
+        camera_velocity.velocity = match (camera_velocity.last_position, position) {
+            (Some(last_position), Some(position)) if delta_secs > 0.0 => {
+                (position - last_position) / delta_secs
+            }
+            _ => Vec3::ZERO,
+        };
+        camera_velocity.last_position = position;
+    }
+
     /// Find and spawn chunks in need of spawning
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_chunks(
         mut commands: Commands,
         mut chunk_map_insert_buffer: ResMut<ChunkMapInsertBuffer<C, C::MaterialIndex>>,
@@ -102,17 +290,44 @@ where
         chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
         configuration: Res<C>,
         camera_info: CameraInfo<C>,
+        camera_projection: Query<&Projection, With<VoxelWorldCamera<C>>>,
+        anchors: AnchorInfo<C>,
+        mut spawn_rng: ResMut<ChunkSpawnRng<C>>,
+        diagnostics: Res<VoxelWorldDiagnostics<C>>,
+        camera_velocity: Res<CameraVelocity<C>>,
+        mut chunk_entity_pool: ResMut<ChunkEntityPool<C>>,
     ) {
         // Panic if no root exists as it is already inserted in the setup.
         let world_root = world_root.get_single().unwrap();
 
-        let (camera, cam_gtf) = camera_info.single();
-        let cam_pos = cam_gtf.translation().as_ivec3();
-
         let spawning_distance = configuration.spawning_distance() as i32;
         let spawning_distance_squared = spawning_distance.pow(2);
+        // Chunk size in world units
+        let chunk_extent = configuration.chunk_size() as f32 * configuration.voxel_size();
+
+        let camera = camera_info.get_single().ok();
+        let chunk_at_camera = camera.map(|(_, cam_gtf)| {
+            chunk_at_world_position(cam_gtf.translation(), configuration.as_ref())
+        });
+        let mut chunks_at_anchors = anchors
+            .iter()
+            .map(|anchor_gtf| {
+                chunk_at_world_position(anchor_gtf.translation(), configuration.as_ref())
+            })
+            .collect::<Vec<_>>();
+
+        // Viewport rays stop right away when the camera is inside solid terrain, so the chunks
+        // around the camera are handled the same way as the chunks around an anchor
+        if let Some(chunk_at_camera) = chunk_at_camera {
+            if is_full_chunk(chunk_at_camera, chunk_map.as_ref()) {
+                chunks_at_anchors.push(chunk_at_camera);
+            }
+        }
 
-        let viewport_size = camera.physical_viewport_size().unwrap_or_default();
+        // Without a camera or anchors, chunks only get spawned through `VoxelWorld::load_chunk`
+        if camera.is_none() && chunks_at_anchors.is_empty() {
+            return;
+        }
 
         let mut visited = HashSet::new();
         let mut chunks_deque = VecDeque::with_capacity(
@@ -121,61 +336,213 @@ where
 
         let chunk_map_read_lock = chunk_map.get_read_lock();
 
-        // Shoots a ray from the given point, and queue all (non-spawned) chunks intersecting the ray
-        let queue_chunks_intersecting_ray_from_point =
-            |point: Vec2, queue: &mut VecDeque<IVec3>| {
-                let Ok(ray) = camera.viewport_to_world(cam_gtf, point) else {
-                    return;
+        if let (Some((camera, cam_gtf)), Some(chunk_at_camera)) =
+            (camera, chunk_at_camera)
+        {
+            let viewport_size = camera.physical_viewport_size().unwrap_or_default();
+
+            // The camera projection, with the aspect ratio of the viewport
+            let mut projection =
+                camera_projection.get_single().cloned().unwrap_or_default();
+            if viewport_size.x > 0 && viewport_size.y > 0 {
+                projection.update(viewport_size.x as f32, viewport_size.y as f32);
+            }
+
+            // Shoots the given ray, and queue all (non-spawned) chunks intersecting the ray
+            let queue_chunks_intersecting_ray =
+                |ray: Ray3d, queue: &mut VecDeque<IVec3>| {
+                    let mut current = ray.origin;
+                    let mut t = 0.0;
+                    while t < spawning_distance as f32 * chunk_extent {
+                        let chunk_pos =
+                            chunk_at_world_position(current, configuration.as_ref());
+                        if let Some(chunk) = ChunkMap::<C, C::MaterialIndex>::get(
+                            &chunk_pos,
+                            &chunk_map_read_lock,
+                        ) {
+                            if chunk.is_full {
+                                // If we hit a full chunk, we can stop the ray early
+                                break;
+                            }
+                        } else {
+                            queue.push_back(chunk_pos);
+                        }
+                        t += chunk_extent;
+                        current = ray.origin + ray.direction * t;
+                    }
+                };
+
+            // Shoots a ray from the given point in the viewport
+            let queue_chunks_intersecting_ray_from_point =
+                |point: Vec2, queue: &mut VecDeque<IVec3>| {
+                    if let Ok(ray) = camera.viewport_to_world(&cam_gtf, point) {
+                        queue_chunks_intersecting_ray(ray, queue);
+                    }
                 };
-                let mut current = ray.origin;
-                let mut t = 0.0;
-                while t < (spawning_distance * CHUNK_SIZE_I) as f32 {
-                    let chunk_pos = current.as_ivec3() / CHUNK_SIZE_I;
-                    if let Some(chunk) = ChunkMap::<C, C::MaterialIndex>::get(
-                        &chunk_pos,
-                        &chunk_map_read_lock,
-                    ) {
-                        if chunk.is_full {
-                            // If we hit a full chunk, we can stop the ray early
-                            break;
+
+            if configuration.chunk_spawn_strategy() == ChunkSpawnStrategy::Frustum {
+                // Build the view frustum from the camera projection, with the aspect ratio of
+                // the viewport, and queue every unspawned chunk within the spawning distance
+                // that intersects it, closest first
+                let frustum = Frustum::from_clip_from_world(
+                    &(projection.get_clip_from_view()
+                        * cam_gtf.compute_matrix().inverse()),
+                );
+
+                let mut chunks_in_frustum = Vec::new();
+                for x in -spawning_distance..=spawning_distance {
+                    for y in -spawning_distance..=spawning_distance {
+                        for z in -spawning_distance..=spawning_distance {
+                            let offset = IVec3::new(x, y, z);
+                            if offset.length_squared() > spawning_distance_squared {
+                                continue;
+                            }
+                            let chunk_pos = chunk_at_camera + offset;
+                            if ChunkMap::<C, C::MaterialIndex>::contains_chunk(
+                                &chunk_pos,
+                                &chunk_map_read_lock,
+                            ) {
+                                continue;
+                            }
+                            let min = chunk_pos.as_vec3() * chunk_extent;
+                            let aabb = Aabb::from_min_max(min, min + chunk_extent);
+                            if frustum.intersects_obb(
+                                &aabb,
+                                &Affine3A::IDENTITY,
+                                true,
+                                false,
+                            ) {
+                                chunks_in_frustum.push(chunk_pos);
+                            }
                         }
-                    } else {
-                        queue.push_back(chunk_pos);
                     }
-                    t += CHUNK_SIZE_F;
-                    current = ray.origin + ray.direction * t;
                 }
-            };
 
-        // Each frame we pick some random points on the screen
-        let m = configuration.spawning_ray_margin();
-        for _ in 0..configuration.spawning_rays() {
-            let random_point_in_viewport = {
-                let x =
-                    rand::random::<f32>() * (viewport_size.x + m * 2) as f32 - m as f32;
-                let y =
-                    rand::random::<f32>() * (viewport_size.y + m * 2) as f32 - m as f32;
-                Vec2::new(x, y)
-            };
+                chunks_in_frustum
+                    .sort_by_key(|chunk_pos| chunk_pos.distance_squared(chunk_at_camera));
+                chunks_in_frustum.truncate(configuration.max_spawn_per_frame());
+                chunks_deque.extend(chunks_in_frustum);
+            } else if let Projection::Orthographic(ortho) = &projection {
+                // Rays from an orthographic camera are all parallel, so instead of points on
+                // the screen, we pick points on a grid covering the visible area of the camera,
+                // with the margin scaled from pixels to world units, and shoot the rays from
+                // there along the view direction
+                let pixel_size = if viewport_size.x > 0 {
+                    ortho.area.width() / viewport_size.x as f32
+                } else {
+                    0.0
+                };
+                let margin = configuration.spawning_ray_margin() as f32 * pixel_size;
+                let points = jittered_grid_points(
+                    ortho.area.min - margin,
+                    ortho.area.size() + margin * 2.0,
+                    configuration.spawning_rays(),
+                    &mut **spawn_rng,
+                );
+                for point in points {
+                    let ray = Ray3d {
+                        origin: cam_gtf.transform_point(point.extend(-ortho.near)),
+                        direction: cam_gtf.forward(),
+                    };
+                    queue_chunks_intersecting_ray(ray, &mut chunks_deque);
+                }
+            } else {
+                // Each frame we pick some random points on the screen
+                let m = configuration.spawning_ray_margin();
+                let points = match configuration.spawning_ray_pattern() {
+                    SpawningRayPattern::Random => (0..configuration.spawning_rays())
+                        .map(|_| {
+                            let x = spawn_rng.gen::<f32>()
+                                * (viewport_size.x + m * 2) as f32
+                                - m as f32;
+                            let y = spawn_rng.gen::<f32>()
+                                * (viewport_size.y + m * 2) as f32
+                                - m as f32;
+                            Vec2::new(x, y)
+                        })
+                        .collect::<Vec<_>>(),
+                    SpawningRayPattern::JitteredGrid => jittered_grid_points(
+                        Vec2::splat(-(m as f32)),
+                        (viewport_size + m * 2).as_vec2(),
+                        configuration.spawning_rays(),
+                        &mut **spawn_rng,
+                    ),
+                };
+
+                // Then, for each point, we cast a ray, picking up any unspawned chunks along the ray
+                for point in points {
+                    queue_chunks_intersecting_ray_from_point(point, &mut chunks_deque);
+                }
+            }
+
+            // We also queue the chunks closest to the camera to make sure they will always spawn early
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let queue_pos = chunk_at_camera + IVec3::new(x, y, z);
+                        chunks_deque.push_back(queue_pos);
+                    }
+                }
+            }
 
-            // Then, for each point, we cast a ray, picking up any unspawned chunks along the ray
-            queue_chunks_intersecting_ray_from_point(
-                random_point_in_viewport,
-                &mut chunks_deque,
-            );
+            // A fast moving camera can outrun the spawning, so the chunks around where it is
+            // heading are queued as well, all the way from the camera
+            let lookahead_seconds = configuration.spawn_lookahead_seconds();
+            if lookahead_seconds > 0.0 && camera_velocity.velocity != Vec3::ZERO {
+                let lookahead = (camera_velocity.velocity * lookahead_seconds)
+                    .clamp_length_max(spawning_distance as f32 * chunk_extent);
+                let steps = (lookahead.length() / chunk_extent).ceil() as i32;
+                for step in 1..=steps {
+                    let point =
+                        cam_gtf.translation() + lookahead * (step as f32 / steps as f32);
+                    let chunk_ahead =
+                        chunk_at_world_position(point, configuration.as_ref());
+                    for x in -1..=1 {
+                        for y in -1..=1 {
+                            for z in -1..=1 {
+                                chunks_deque.push_back(chunk_ahead + IVec3::new(x, y, z));
+                            }
+                        }
+                    }
+                }
+            }
         }
 
-        // We also queue the chunks closest to the camera to make sure they will always spawn early
-        let chunk_at_camera = cam_pos / CHUNK_SIZE_I;
-        for x in -1..=1 {
-            for y in -1..=1 {
-                for z in -1..=1 {
-                    let queue_pos = chunk_at_camera + IVec3::new(x, y, z);
-                    chunks_deque.push_back(queue_pos);
+        // Anchors have no view to cast rays through, so we queue all unspawned chunks within
+        // the spawning distance of each anchor
+        for chunk_at_anchor in chunks_at_anchors.iter() {
+            for x in -spawning_distance..=spawning_distance {
+                for y in -spawning_distance..=spawning_distance {
+                    for z in -spawning_distance..=spawning_distance {
+                        let offset = IVec3::new(x, y, z);
+                        if offset.length_squared() > spawning_distance_squared {
+                            continue;
+                        }
+                        let queue_pos = *chunk_at_anchor + offset;
+                        if !ChunkMap::<C, C::MaterialIndex>::contains_chunk(
+                            &queue_pos,
+                            &chunk_map_read_lock,
+                        ) {
+                            chunks_deque.push_back(queue_pos);
+                        }
+                    }
                 }
             }
         }
 
+        let is_within_spawning_distance = |chunk_position: IVec3| {
+            chunk_at_camera
+                .iter()
+                .chain(chunks_at_anchors.iter())
+                .any(|origin| {
+                    chunk_position.distance_squared(*origin) <= spawning_distance_squared
+                })
+        };
+
+        let neighbour_offsets =
+            configuration.flood_fill_connectivity().neighbour_offsets();
+        let spawn_y_range = configuration.spawn_y_range();
+
         // Then, when we have a queue of chunks, we can set them up for spawning
         while let Some(chunk_position) = chunks_deque.pop_front() {
             if visited.contains(&chunk_position)
@@ -185,33 +552,33 @@ where
             }
             visited.insert(chunk_position);
 
-            if chunk_position.distance_squared(chunk_at_camera)
-                > spawning_distance_squared
+            if !is_within_spawning_distance(chunk_position)
+                || !is_within_world_bounds(chunk_position, configuration.as_ref())
             {
                 continue;
             }
 
-            let has_chunk = ChunkMap::<C, C::MaterialIndex>::contains_chunk(
-                &chunk_position,
-                &chunk_map_read_lock,
-            );
-
-            if !has_chunk {
-                let chunk_entity = commands.spawn(NeedsRemesh).id();
-                commands.entity(world_root).add_child(chunk_entity);
-                let chunk = Chunk::<C>::new(chunk_position, chunk_entity);
-
-                chunk_map_insert_buffer
-                    .push((chunk_position, ChunkData::with_entity(chunk.entity)));
-
-                commands.entity(chunk.entity).try_insert((
-                    chunk,
-                    Transform::from_translation(
-                        chunk_position.as_vec3() * CHUNK_SIZE_F - 1.0,
-                    ),
-                ));
-            } else {
-                continue;
+            // Chunks outside the spawn y range are never spawned, but the flood fill still goes
+            // through them, so that it reaches the range from a camera above or below it
+            if spawn_y_range.contains(&chunk_position.y) {
+                let has_chunk = ChunkMap::<C, C::MaterialIndex>::contains_chunk(
+                    &chunk_position,
+                    &chunk_map_read_lock,
+                );
+
+                if !has_chunk {
+                    spawn_chunk(
+                        &mut commands,
+                        world_root,
+                        chunk_position,
+                        configuration.as_ref(),
+                        &mut chunk_map_insert_buffer,
+                        &mut chunk_entity_pool,
+                    );
+                    diagnostics.count_spawned();
+                } else {
+                    continue;
+                }
             }
 
             if configuration.chunk_spawn_strategy() != ChunkSpawnStrategy::Close {
@@ -219,16 +586,52 @@ where
             }
 
             // If we get here, we queue the neighbors
-            for x in -1..=1 {
-                for y in -1..=1 {
-                    for z in -1..=1 {
-                        let queue_pos = chunk_position + IVec3::new(x, y, z);
-                        if queue_pos == chunk_position {
-                            continue;
-                        }
-                        chunks_deque.push_back(queue_pos);
-                    }
-                }
+            for offset in neighbour_offsets.iter() {
+                chunks_deque.push_back(chunk_position + *offset);
+            }
+        }
+    }
+
+    /// Spawns chunks queued with `VoxelWorld::load_chunk`, regardless of where the camera is
+    pub fn flush_chunk_load_buffer(
+        mut commands: Commands,
+        mut chunk_load_buffer: ResMut<ChunkLoadBuffer<C>>,
+        mut chunk_map_insert_buffer: ResMut<ChunkMapInsertBuffer<C, C::MaterialIndex>>,
+        world_root: Query<Entity, With<WorldRoot<C>>>,
+        chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
+        configuration: Res<C>,
+        diagnostics: Res<VoxelWorldDiagnostics<C>>,
+        mut chunk_entity_pool: ResMut<ChunkEntityPool<C>>,
+    ) {
+        if chunk_load_buffer.is_empty() {
+            return;
+        }
+
+        let world_root = world_root.get_single().unwrap();
+        let chunk_map_read_lock = chunk_map.get_read_lock();
+
+        for chunk_position in chunk_load_buffer.drain(..) {
+            // The chunk map is not updated until the end of the frame, so also check for
+            // chunks that were spawned earlier in this frame
+            let has_chunk = ChunkMap::<C, C::MaterialIndex>::contains_chunk(
+                &chunk_position,
+                &chunk_map_read_lock,
+            ) || chunk_map_insert_buffer
+                .iter()
+                .any(|(position, _)| *position == chunk_position);
+
+            if !has_chunk
+                && is_within_spawn_bounds(chunk_position, configuration.as_ref())
+            {
+                spawn_chunk(
+                    &mut commands,
+                    world_root,
+                    chunk_position,
+                    configuration.as_ref(),
+                    &mut chunk_map_insert_buffer,
+                    &mut chunk_entity_pool,
+                );
+                diagnostics.count_spawned();
             }
         }
     }
@@ -236,47 +639,151 @@ where
     /// Tags chunks that are eligible for despawning
     pub fn retire_chunks(
         mut commands: Commands,
-        all_chunks: Query<(&Chunk<C>, Option<&ViewVisibility>)>,
+        all_chunks: Query<(
+            &Chunk<C>,
+            Option<&ViewVisibility>,
+            Option<&ChunkThread<C, C::MaterialIndex>>,
+        )>,
+        chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
         configuration: Res<C>,
         camera_info: CameraInfo<C>,
+        anchors: AnchorInfo<C>,
         mut ev_chunk_will_despawn: EventWriter<ChunkWillDespawn<C>>,
+        mut last_seen: Local<ChunkLastSeen>,
     ) {
         let spawning_distance = configuration.spawning_distance() as i32;
         let spawning_distance_squared = spawning_distance.pow(2);
 
-        let (_, cam_gtf) = camera_info.get_single().unwrap();
-        let cam_pos = cam_gtf.translation().as_ivec3();
+        let chunk_at_camera = camera_info.get_single().ok().map(|(_, cam_gtf)| {
+            chunk_at_world_position(cam_gtf.translation(), configuration.as_ref())
+        });
+        let mut chunks_at_anchors = anchors
+            .iter()
+            .map(|anchor_gtf| {
+                chunk_at_world_position(anchor_gtf.translation(), configuration.as_ref())
+            })
+            .collect::<Vec<_>>();
+
+        // Viewport rays stop right away when the camera is inside solid terrain, so the chunks
+        // around the camera are handled the same way as the chunks around an anchor
+        if let Some(chunk_at_camera) = chunk_at_camera {
+            if is_full_chunk(chunk_at_camera, chunk_map.as_ref()) {
+                chunks_at_anchors.push(chunk_at_camera);
+            }
+        }
+
+        if chunk_at_camera.is_none() && chunks_at_anchors.is_empty() {
+            return;
+        }
 
-        let chunk_at_camera = cam_pos / CHUNK_SIZE_I;
+        let despawn_strategy = configuration.chunk_despawn_strategy();
+        let track_last_seen = matches!(
+            despawn_strategy,
+            ChunkDespawnStrategy::LeastRecentlySeen { .. }
+        );
+        last_seen.frame += 1;
+        let frame = last_seen.frame;
+        let chunk_count = all_chunks.iter().count();
 
         let chunks_to_remove = {
             let mut remove = Vec::with_capacity(1000);
-            for (chunk, view_visibility) in all_chunks.iter() {
-                let should_be_culled = {
-                    match configuration.chunk_despawn_strategy() {
-                        ChunkDespawnStrategy::FarAway => false,
-                        ChunkDespawnStrategy::FarAwayOrOutOfView => {
-                            if let Some(visibility) = view_visibility {
-                                !visibility.get()
-                            } else {
-                                false
-                            }
-                        }
+            let mut unseen = Vec::new();
+            for (chunk, view_visibility, thread) in all_chunks.iter() {
+                // Chunks outside the world bounds or the spawn range can be left over from
+                // before they changed
+                if !is_within_spawn_bounds(chunk.position, configuration.as_ref()) {
+                    remove.push((chunk, thread));
+                    continue;
+                }
+
+                // Chunks that are new to the map count as seen when they spawn
+                if track_last_seen {
+                    last_seen.chunks.entry(chunk.position).or_insert(frame);
+                }
+
+                // Any anchor keeps the chunk alive if it is close enough
+                let near_anchor = chunks_at_anchors.iter().any(|chunk_at_anchor| {
+                    chunk.position.distance_squared(*chunk_at_anchor)
+                        <= spawning_distance_squared + 1
+                });
+                if near_anchor {
+                    if track_last_seen {
+                        last_seen.chunks.insert(chunk.position, frame);
                     }
+                    continue;
+                }
+
+                let Some(chunk_at_camera) = chunk_at_camera else {
+                    if track_last_seen {
+                        unseen.push((chunk, thread));
+                    } else {
+                        remove.push((chunk, thread));
+                    }
+                    continue;
                 };
+
+                let in_view = view_visibility.map_or(true, |visibility| visibility.get());
                 let dist_squared = chunk.position.distance_squared(chunk_at_camera);
-                if should_be_culled || dist_squared > spawning_distance_squared + 1 {
-                    remove.push(chunk);
+                let in_range = dist_squared <= spawning_distance_squared + 1;
+                match despawn_strategy {
+                    ChunkDespawnStrategy::FarAway => {
+                        if !in_range {
+                            remove.push((chunk, thread));
+                        }
+                    }
+                    ChunkDespawnStrategy::FarAwayOrOutOfView => {
+                        if !in_range || !in_view {
+                            remove.push((chunk, thread));
+                        }
+                    }
+                    ChunkDespawnStrategy::LeastRecentlySeen { .. } => {
+                        if in_range && in_view {
+                            last_seen.chunks.insert(chunk.position, frame);
+                        } else {
+                            unseen.push((chunk, thread));
+                        }
+                    }
                 }
             }
+
+            // Only the chunks that have gone unseen the longest are despawned, and only as many
+            // as needed to get back down to `keep`
+            if let ChunkDespawnStrategy::LeastRecentlySeen { keep } = despawn_strategy {
+                let excess = (chunk_count - remove.len()).saturating_sub(keep);
+                unseen.sort_by_key(|(chunk, _)| {
+                    last_seen.chunks.get(&chunk.position).copied()
+                });
+                remove.extend(unseen.into_iter().take(excess));
+            }
             remove
         };
 
-        for chunk in chunks_to_remove {
+        if track_last_seen {
+            for (chunk, _) in chunks_to_remove.iter() {
+                last_seen.chunks.remove(&chunk.position);
+            }
+            // Forget chunks that were despawned some other way, like `VoxelWorld::clear`
+            if last_seen.chunks.len() > chunk_count - chunks_to_remove.len() {
+                let spawned = all_chunks
+                    .iter()
+                    .map(|(chunk, _, _)| chunk.position)
+                    .collect::<HashSet<_>>();
+                last_seen
+                    .chunks
+                    .retain(|position, _| spawned.contains(position));
+            }
+        }
+
+        for (chunk, thread) in chunks_to_remove {
+            if let Some(thread) = thread {
+                thread.cancel();
+            }
             commands.entity(chunk.entity).try_insert(NeedsDespawn);
 
-            ev_chunk_will_despawn
-                .send(ChunkWillDespawn::<C>::new(chunk.position, chunk.entity));
+            if configuration.emit_chunk_events() {
+                ev_chunk_will_despawn
+                    .send(ChunkWillDespawn::<C>::new(chunk.position, chunk.entity));
+            }
         }
     }
 
@@ -285,66 +792,304 @@ where
         mut commands: Commands,
         mut chunk_map_remove_buffer: ResMut<ChunkMapRemoveBuffer<C>>,
         chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
-        retired_chunks: Query<(Entity, &Chunk<C>), With<NeedsDespawn>>,
+        retired_chunks: Query<
+            (Entity, &Chunk<C>, Option<&ChunkThread<C, C::MaterialIndex>>),
+            With<NeedsDespawn>,
+        >,
+        diagnostics: Res<VoxelWorldDiagnostics<C>>,
+        configuration: Res<C>,
+        mut chunk_entity_pool: ResMut<ChunkEntityPool<C>>,
     ) {
+        // The entities retired in the last update have been stripped by now
+        let chunk_entity_pool = &mut *chunk_entity_pool;
+        chunk_entity_pool
+            .ready
+            .append(&mut chunk_entity_pool.retired);
+
         let read_lock = chunk_map.get_read_lock();
-        for (entity, chunk) in retired_chunks.iter() {
+        for (entity, chunk, thread) in retired_chunks.iter() {
             if ChunkMap::<C, C::MaterialIndex>::contains_chunk(
                 &chunk.position,
                 &read_lock,
             ) {
-                commands.entity(entity).despawn_recursive();
+                if let Some(thread) = thread {
+                    thread.cancel();
+                }
+                if configuration.pool_chunk_entities() {
+                    commands
+                        .entity(entity)
+                        .despawn_descendants()
+                        .remove_parent()
+                        .clear();
+                    chunk_entity_pool.retired.push(entity);
+                } else {
+                    commands.entity(entity).despawn_recursive();
+                }
                 chunk_map_remove_buffer.push(chunk.position);
+                diagnostics.count_despawned();
             }
         }
     }
 
     /// Spawn a thread for each chunk that has been marked by NeedsRemesh
-    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub fn remesh_dirty_chunks(
         mut commands: Commands,
         mut ev_chunk_will_remesh: EventWriter<ChunkWillRemesh<C>>,
-        dirty_chunks: Query<&Chunk<C>, With<NeedsRemesh>>,
+        mut ev_chunk_invalidated: EventWriter<ChunkInvalidated<C>>,
+        dirty_chunks: Query<(
+            &Chunk<C>,
+            &NeedsRemesh,
+            Option<&ChunkVoxelEdits>,
+            Has<ChunkThread<C, C::MaterialIndex>>,
+        )>,
+        chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
         mesh_cache: Res<MeshCache<C>>,
         modified_voxels: Res<ModifiedVoxels<C, C::MaterialIndex>>,
         configuration: Res<C>,
+        diagnostics: Res<VoxelWorldDiagnostics<C>>,
+        camera_info: CameraInfo<C>,
     ) {
         let thread_pool = AsyncComputeTaskPool::get();
 
-        for chunk in dirty_chunks.iter() {
-            let voxel_data_fn = (configuration.voxel_lookup_delegate())(chunk.position);
-            let chunk_meshing_fn = (configuration
-                .chunk_meshing_delegate()
-                .unwrap_or(Box::new(default_chunk_meshing_delegate)))(
-                chunk.position
-            );
+        // Start the chunks closest to the camera first, since the rest may have to wait for a
+        // later frame when `max_remesh_tasks_per_frame` is reached
+        let mut dirty_chunks = dirty_chunks.iter().collect::<Vec<_>>();
+        let max_remesh_tasks = configuration.max_remesh_tasks_per_frame();
+        if dirty_chunks.len() > max_remesh_tasks {
+            if let Ok((_, cam_gtf)) = camera_info.get_single() {
+                let chunk_at_camera = chunk_at_world_position(
+                    cam_gtf.translation(),
+                    configuration.as_ref(),
+                );
+                dirty_chunks.sort_by_key(|(chunk, ..)| {
+                    chunk.position.distance_squared(chunk_at_camera)
+                });
+            }
+            dirty_chunks.truncate(max_remesh_tasks);
+        }
+        if dirty_chunks.is_empty() {
+            return;
+        }
+
+        // The functions that read from the config are shared by all the chunks remeshed this frame
+        let material_alpha: MaterialAlphaFn<C::MaterialIndex> = {
+            let config = configuration.clone();
+            Arc::new(move |material| config.material_alpha(material))
+        };
+        let voxel_light: Option<VoxelLightFn<C::MaterialIndex>> =
+            configuration.bake_voxel_light().then(|| {
+                let config = configuration.clone();
+                Arc::new(move |pos: IVec3, voxel: &WorldVoxel<C::MaterialIndex>| {
+                    config.voxel_light(pos, voxel)
+                }) as VoxelLightFn<C::MaterialIndex>
+            });
+
+        for (chunk, needs_remesh, voxel_edits, has_thread) in dirty_chunks {
+            let voxel_lookup = match configuration.shared_voxel_lookup_delegate() {
+                Some(delegate) => ChunkVoxelLookup::Shared(delegate(chunk.position)),
+                None => ChunkVoxelLookup::Sequential((configuration
+                    .voxel_lookup_delegate())(
+                    chunk.position
+                )),
+            };
+
+            // With `ChunkRegenerateStrategy::Reuse`, edited chunks only get the edited voxels
+            // applied to the voxels they were generated with. A running task is replaced below,
+            // and the edits it was applying would be lost, so then the chunk is generated again.
+            // `ModificationPrecedence::Fallback` needs the voxel lookup for every voxel, so it
+            // always regenerates as well.
+            let reused_voxels = voxel_edits
+                .filter(|_| {
+                    configuration.chunk_regenerate_strategy()
+                        == ChunkRegenerateStrategy::Reuse
+                        && configuration.modification_precedence()
+                            == ModificationPrecedence::Override
+                        && !has_thread
+                })
+                .and_then(|voxel_edits| {
+                    let chunk_data = ChunkMap::<C, C::MaterialIndex>::get_arc(
+                        &chunk.position,
+                        &chunk_map.get_read_lock(),
+                    )?;
+                    if !chunk_data.has_generated() {
+                        return None;
+                    }
+                    let voxels = chunk_data.decoded_voxels()?;
+                    Some((voxels, voxel_edits.0.clone()))
+                });
+
+            // Only custom meshing delegates that ask for them get the neighbouring chunks
+            let mut neighbours = None;
+            let mut mesh_variant = None;
+            let chunk_meshing_fn = match configuration.chunk_meshing_delegate() {
+                Some(delegate) if configuration.chunk_meshing_needs_neighbours() => {
+                    let chunk_map_read_lock = chunk_map.get_read_lock();
+                    let neighbour_chunks =
+                        ChunkNeighbours::<C::MaterialIndex>::DIRECTIONS.map(
+                            |direction| {
+                                ChunkMap::<C, C::MaterialIndex>::get_arc(
+                                    &(chunk.position + direction),
+                                    &chunk_map_read_lock,
+                                )
+                            },
+                        );
+                    // The delegate can mesh the chunk differently depending on its neighbours,
+                    // so the mesh is only shared with chunks that have the same neighbours
+                    mesh_variant = Some(stable_hash(&neighbour_chunks.each_ref().map(
+                        |neighbour| {
+                            neighbour.as_ref().map(|chunk_data| {
+                                let uniform_voxel = match chunk_data.get_fill_type() {
+                                    FillType::Uniform(voxel) => Some(*voxel),
+                                    _ => None,
+                                };
+                                (chunk_data.voxels_hash, uniform_voxel)
+                            })
+                        },
+                    )));
+                    neighbours = Some(ChunkNeighbours::new(neighbour_chunks));
+                    delegate(chunk.position)
+                }
+                Some(delegate) => delegate(chunk.position),
+                None => match configuration.chunk_mesh_attributes() {
+                    ChunkMeshAttributes::Full => {
+                        let ao_curve = if configuration.ao_enabled() {
+                            configuration.ao_curve()
+                        } else {
+                            [1.0; 4]
+                        };
+                        default_chunk_meshing_delegate_with_options(ChunkMeshOptions {
+                            material_alpha: material_alpha.clone(),
+                            ao_curve,
+                        })
+                    }
+                    ChunkMeshAttributes::PositionsAndNormals => {
+                        minimal_chunk_meshing_delegate(chunk.position, true)
+                    }
+                    ChunkMeshAttributes::PositionsOnly => {
+                        minimal_chunk_meshing_delegate(chunk.position, false)
+                    }
+                },
+            };
             let texture_index_mapper = configuration.texture_index_mapper().clone();
+            let generate_collision_data = configuration.generate_collision_data();
+            let compress_chunk_storage = configuration.compress_chunk_storage();
+            let sparse_chunk_threshold = configuration.sparse_chunk_threshold();
+            let detail_uv_tiling = configuration.detail_uv_tiling();
+            let surface_distance_range = configuration.surface_distance_range();
+            let voxel_light = voxel_light.clone();
+            // Baked light depends on where the chunk is, so lit meshes are never shared
+            if voxel_light.is_some() {
+                mesh_variant = Some(stable_hash(&(mesh_variant, chunk.position)));
+            }
 
             let mut chunk_task = ChunkTask::<C, C::MaterialIndex>::new(
                 chunk.entity,
                 chunk.position,
+                chunk.chunk_size(),
                 modified_voxels.clone(),
-            );
+            )
+            .with_bedrock(
+                configuration
+                    .bedrock_y()
+                    .map(|bedrock_y| (bedrock_y, configuration.bedrock_material())),
+            )
+            .with_default_fill(configuration.default_solid_below())
+            .with_modification_precedence(configuration.modification_precedence())
+            .with_neighbours(neighbours)
+            .with_mesh_variant(mesh_variant);
 
             let mesh_map = mesh_cache.get_mesh_map();
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let task_cancelled = cancelled.clone();
+            let diagnostic_counters = diagnostics.counters();
+            diagnostics.count_remeshed();
 
             let thread = thread_pool.spawn(async move {
-                chunk_task.generate(voxel_data_fn);
-
-                // No need to mesh if the chunk is empty or full
-                if chunk_task.is_empty() || chunk_task.is_full() {
+                // The chunk has been despawned before the task got to run
+                if task_cancelled.load(Ordering::Relaxed) {
                     return chunk_task;
                 }
+                let task_started = Instant::now();
+
+                // A panic in the voxel lookup is treated as a failed generation, which
+                // will get retried later
+                let generated =
+                    std::panic::catch_unwind(AssertUnwindSafe(|| match reused_voxels {
+                        Some((voxels, changed)) => {
+                            chunk_task.regenerate_changed(&voxels, &changed)
+                        }
+                        None => match voxel_lookup {
+                            ChunkVoxelLookup::Sequential(voxel_data_fn) => {
+                                chunk_task.generate(voxel_data_fn)
+                            }
+                            ChunkVoxelLookup::Shared(voxel_data_fn) => {
+                                chunk_task.generate_shared(voxel_data_fn)
+                            }
+                        },
+                    }));
+                if generated.is_err() {
+                    chunk_task.generation_failed = true;
+                    return chunk_task;
+                }
+
+                if generate_collision_data {
+                    chunk_task.generate_collision_data();
+                }
 
-                // Also no need to mesh if a matching mesh is already cached
-                let mesh_cache_hit = mesh_map
-                    .read()
-                    .unwrap()
-                    .contains_key(&chunk_task.voxels_hash());
-                if !mesh_cache_hit {
+                // No need to mesh if the chunk is empty or full, or if a matching mesh is
+                // already cached
+                let needs_mesh = !chunk_task.is_empty()
+                    && !chunk_task.is_full()
+                    && !mesh_map
+                        .read()
+                        .unwrap()
+                        .contains_key(&chunk_task.mesh_cache_key());
+                if needs_mesh {
                     chunk_task.mesh(chunk_meshing_fn, texture_index_mapper);
                 }
 
+                if let (Some(tiling), Some(mesh)) =
+                    (detail_uv_tiling, chunk_task.mesh.as_mut())
+                {
+                    add_detail_uvs(mesh, tiling);
+                }
+
+                if let (Some(range), Some(mesh), Some(voxels)) = (
+                    surface_distance_range,
+                    chunk_task.mesh.as_mut(),
+                    chunk_task.chunk_data.voxels.as_ref(),
+                ) {
+                    add_surface_distances(
+                        mesh,
+                        voxels,
+                        &padded_chunk_shape_uniform(chunk_task.chunk_data.chunk_size),
+                        range,
+                    );
+                }
+
+                if let (Some(voxel_light), Some(mesh), Some(voxels)) = (
+                    voxel_light,
+                    chunk_task.mesh.as_mut(),
+                    chunk_task.chunk_data.voxels.as_ref(),
+                ) {
+                    let chunk_size = chunk_task.chunk_data.chunk_size;
+                    add_voxel_light(
+                        mesh,
+                        voxels,
+                        &padded_chunk_shape_uniform(chunk_size),
+                        chunk_task.position * chunk_size as i32 - 1,
+                        voxel_light,
+                    );
+                }
+
+                if !chunk_task.chunk_data.make_sparse(sparse_chunk_threshold)
+                    && compress_chunk_storage
+                {
+                    chunk_task.chunk_data.compress();
+                }
+
+                diagnostic_counters.record_task(task_started.elapsed());
                 chunk_task
             });
 
@@ -352,15 +1097,84 @@ where
                 .entity(chunk.entity)
                 .try_insert(ChunkThread::<C, C::MaterialIndex>::new(
                     thread,
-                    chunk.position,
+                    cancelled,
+                    needs_remesh.0,
                 ))
-                .remove::<NeedsRemesh>();
+                .remove::<(NeedsRemesh, ChunkVoxelEdits)>();
+
+            if configuration.emit_chunk_events() {
+                ev_chunk_will_remesh
+                    .send(ChunkWillRemesh::<C>::new(chunk.position, chunk.entity));
+
+                // A chunk that hasn't generated yet is still being streamed in, even if it was
+                // also edited or regenerated before its first generation finished
+                let has_generated = chunk_map
+                    .get_read_lock()
+                    .get(&chunk.position)
+                    .is_some_and(|chunk_data| chunk_data.has_generated());
+                let reason = if has_generated {
+                    needs_remesh.0
+                } else {
+                    InvalidationReason::Streamed
+                };
+                ev_chunk_invalidated.send(ChunkInvalidated::<C>::new(
+                    chunk.position,
+                    chunk.entity,
+                    reason,
+                ));
+            }
+        }
+    }
 
-            ev_chunk_will_remesh
-                .send(ChunkWillRemesh::<C>::new(chunk.position, chunk.entity));
+    /// Queues chunks with failed generation for remeshing once their retry backoff has passed
+    #[allow(clippy::type_complexity)]
+    pub fn retry_failed_chunks(
+        mut commands: Commands,
+        retrying_chunks: Query<
+            (Entity, &ChunkGenerationRetry),
+            (
+                With<Chunk<C>>,
+                Without<NeedsRemesh>,
+                Without<ChunkThread<C, C::MaterialIndex>>,
+            ),
+        >,
+        time: Res<Time>,
+    ) {
+        for (entity, retry) in retrying_chunks.iter() {
+            if time.elapsed() >= retry.retry_at {
+                commands
+                    .entity(entity)
+                    .try_insert(NeedsRemesh(InvalidationReason::Regenerated));
+            }
         }
     }
 
+    /// Queues the chunks affected by a change to the configuration for remeshing.
+    /// See `VoxelWorldConfig::config_change_affects_chunk`.
+    pub fn remesh_changed_config_chunks(
+        mut commands: Commands,
+        chunks: Query<&Chunk<C>, Without<NeedsRemesh>>,
+        configuration: Res<C>,
+        mut previous_config: ResMut<PreviousConfig<C>>,
+    ) {
+        if !configuration.is_changed() {
+            return;
+        }
+
+        for chunk in chunks.iter() {
+            if configuration.config_change_affects_chunk(chunk.position, &previous_config)
+            {
+                // Edits would otherwise be applied to the voxels generated with the old config
+                commands
+                    .entity(chunk.entity)
+                    .try_insert(NeedsRemesh(InvalidationReason::Regenerated))
+                    .remove::<ChunkVoxelEdits>();
+            }
+        }
+
+        previous_config.0 = configuration.clone();
+    }
+
     /// Inserts new meshes for chunks that have just finished remeshing
     #[allow(clippy::type_complexity)]
     pub fn spawn_meshes(
@@ -371,6 +1185,7 @@ where
                 &mut ChunkThread<C, C::MaterialIndex>,
                 &mut Chunk<C>,
                 &Transform,
+                Option<&ChunkGenerationRetry>,
             ),
             Without<NeedsRemesh>,
         >,
@@ -379,9 +1194,21 @@ where
             ResMut<ChunkMapUpdateBuffer<C, C::MaterialIndex>>,
             ResMut<MeshCacheInsertBuffer<C>>,
         ),
-        res: (Res<MeshCache<C>>, Res<LoadingTexture>),
+        res: (
+            Res<MeshCache<C>>,
+            Res<LoadingTexture>,
+            Res<C>,
+            Res<Time>,
+            Res<VoxelWorldDiagnostics<C>>,
+        ),
+        events: (
+            EventWriter<ChunkGenerationFailed<C>>,
+            EventWriter<ChunkMeshReady<C>>,
+        ),
+        camera_info: CameraInfo<C>,
     ) {
-        let (mesh_cache, loading_texture) = res;
+        let (mesh_cache, loading_texture, configuration, time, diagnostics) = res;
+        let (mut ev_chunk_generation_failed, mut ev_chunk_mesh_ready) = events;
 
         if !loading_texture.is_loaded {
             return;
@@ -389,53 +1216,121 @@ where
 
         let (mut chunk_map_update_buffer, mut mesh_cache_insert_buffer) = buffers;
 
-        for (entity, mut thread, chunk, transform) in &mut chunking_threads {
+        // Handle the finished chunks closest to the camera first, since the rest may have to
+        // wait for a later frame when `max_meshes_per_frame` is reached
+        let mut finished_chunks = chunking_threads
+            .iter()
+            .filter(|(_, thread, ..)| thread.0.is_finished())
+            .map(|(entity, _, _, transform, _)| (entity, transform.translation))
+            .collect::<Vec<_>>();
+        if let Ok((_, cam_gtf)) = camera_info.get_single() {
+            let camera_position = cam_gtf.translation();
+            finished_chunks.sort_by(|(_, a), (_, b)| {
+                a.distance_squared(camera_position)
+                    .total_cmp(&b.distance_squared(camera_position))
+            });
+        }
+
+        let max_meshes_per_frame = configuration.max_meshes_per_frame();
+        let mut inserted_meshes = 0;
+
+        for (entity, _) in finished_chunks {
+            if inserted_meshes >= max_meshes_per_frame {
+                break;
+            }
+
+            let Ok((entity, mut thread, chunk, transform, retry)) =
+                chunking_threads.get_mut(entity)
+            else {
+                continue;
+            };
             let thread_result = future::block_on(future::poll_once(&mut thread.0));
 
             if thread_result.is_none() {
                 continue;
             }
 
-            let chunk_task = thread_result.unwrap();
+            let mut chunk_task = thread_result.unwrap();
+
+            if chunk_task.generation_failed {
+                retry_or_fail_chunk(
+                    &mut commands,
+                    &chunk_task,
+                    retry,
+                    configuration.as_ref(),
+                    time.elapsed(),
+                    &mut ev_chunk_generation_failed,
+                );
+                continue;
+            }
+
+            if retry.is_some() {
+                if let Some(mut entity_commands) = commands.get_entity(entity) {
+                    entity_commands.remove::<ChunkGenerationRetry>();
+                }
+            }
+
+            if let Some(collision_data) = chunk_task.collision_data.take() {
+                insert_collision_data(&mut commands, entity, collision_data);
+            }
 
             if !chunk_task.is_empty() {
                 if !chunk_task.is_full() {
-                    let mesh_handle = {
+                    let mesh_cache_key = chunk_task.mesh_cache_key();
+                    let (mesh_handle, custom_aabb) = {
                         if let Some(mesh_handle) =
-                            mesh_cache.get_mesh_handle(&chunk_task.voxels_hash())
+                            mesh_cache.get_mesh_handle(&mesh_cache_key)
                         {
                             if let Some(user_bundle) =
-                                mesh_cache.get_user_bundle(&chunk_task.voxels_hash())
+                                mesh_cache.get_user_bundle(&mesh_cache_key)
                             {
                                 commands.entity(entity).insert(user_bundle);
                             }
 
-                            mesh_handle
+                            diagnostics.count_mesh_cache_lookup(true);
+                            (mesh_handle, mesh_cache.get_aabb(&mesh_cache_key))
                         } else {
                             if chunk_task.mesh.is_none() {
                                 commands
                                     .entity(chunk.entity)
-                                    .try_insert(NeedsRemesh)
+                                    .try_insert(NeedsRemesh(thread.2))
                                     .remove::<ChunkThread<C, C::MaterialIndex>>();
                                 continue;
                             }
-                            let hash = chunk_task.voxels_hash();
                             let mesh_ref =
                                 Arc::new(mesh_assets.add(chunk_task.mesh.unwrap()));
                             let user_bundle = chunk_task.user_bundle;
 
                             mesh_cache_insert_buffer.push((
-                                hash,
+                                mesh_cache_key,
                                 mesh_ref.clone(),
                                 user_bundle.clone(),
+                                chunk_task.aabb,
                             ));
                             if let Some(bundle) = user_bundle {
                                 commands.entity(entity).insert(bundle);
                             }
-                            mesh_ref
+                            diagnostics.count_mesh_cache_lookup(false);
+                            (mesh_ref, chunk_task.aabb)
                         }
                     };
 
+                    if configuration.emit_chunk_events() {
+                        let aabb = custom_aabb
+                            .or_else(|| {
+                                mesh_assets
+                                    .get(mesh_handle.as_ref())
+                                    .and_then(|mesh| mesh.compute_aabb())
+                            })
+                            .unwrap_or_default();
+                        ev_chunk_mesh_ready.send(ChunkMeshReady::<C>::new(
+                            chunk_task.position,
+                            entity,
+                            mesh_handle.as_ref().clone(),
+                            aabb,
+                        ));
+                    }
+
                     commands
                         .entity(entity)
                         .try_insert((
@@ -443,7 +1338,19 @@ where
                             MeshRef(mesh_handle),
                             NeedsMaterial::<C>(PhantomData),
                         ))
-                        .remove::<bevy::render::primitives::Aabb>();
+                        .remove::<Aabb>();
+                    // Bevy only computes the bounds of meshes without an `Aabb`, so a custom one
+                    // from the meshing delegate is kept as is
+                    if let Some(custom_aabb) = custom_aabb {
+                        commands.entity(entity).try_insert(custom_aabb);
+                    }
+                    inserted_meshes += 1;
+
+                    if configuration.debug_chunk_age_tint() {
+                        commands
+                            .entity(entity)
+                            .try_insert(ChunkMeshedAt(time.elapsed()));
+                    }
                 }
             } else {
                 commands
@@ -464,38 +1371,189 @@ where
         }
     }
 
+    /// Moves the voxel data of chunks that have just finished generating into the chunk map,
+    /// without spawning any meshes. This is used in place of `spawn_meshes` when running
+    /// without rendering, for example in tests.
+    #[cfg(any(test, feature = "test-util"))]
+    #[allow(clippy::type_complexity)]
+    pub fn apply_chunk_tasks_headless(
+        mut commands: Commands,
+        mut chunking_threads: Query<
+            (
+                Entity,
+                &mut ChunkThread<C, C::MaterialIndex>,
+                &Chunk<C>,
+                Option<&ChunkGenerationRetry>,
+            ),
+            Without<NeedsRemesh>,
+        >,
+        mut chunk_map_update_buffer: ResMut<ChunkMapUpdateBuffer<C, C::MaterialIndex>>,
+        mut ev_chunk_generation_failed: EventWriter<ChunkGenerationFailed<C>>,
+        configuration: Res<C>,
+        time: Res<Time>,
+    ) {
+        for (entity, mut thread, chunk, retry) in &mut chunking_threads {
+            let Some(mut chunk_task) = future::block_on(future::poll_once(&mut thread.0))
+            else {
+                continue;
+            };
+
+            if chunk_task.generation_failed {
+                retry_or_fail_chunk(
+                    &mut commands,
+                    &chunk_task,
+                    retry,
+                    configuration.as_ref(),
+                    time.elapsed(),
+                    &mut ev_chunk_generation_failed,
+                );
+                continue;
+            }
+
+            if retry.is_some() {
+                if let Some(mut entity_commands) = commands.get_entity(entity) {
+                    entity_commands.remove::<ChunkGenerationRetry>();
+                }
+            }
+
+            if let Some(collision_data) = chunk_task.collision_data.take() {
+                insert_collision_data(&mut commands, entity, collision_data);
+            }
+
+            chunk_map_update_buffer.push((
+                chunk.position,
+                chunk_task.chunk_data,
+                ChunkWillSpawn::<C>::new(chunk_task.position, entity),
+            ));
+
+            commands
+                .entity(entity)
+                .remove::<ChunkThread<C, C::MaterialIndex>>();
+        }
+    }
+
     pub fn flush_voxel_write_buffer(
         mut commands: Commands,
-        mut buffer: ResMut<VoxelWriteBuffer<C, C::MaterialIndex>>,
+        mut buffers: (
+            ResMut<VoxelWriteBuffer<C, C::MaterialIndex>>,
+            ResMut<VoxelClearBuffer<C>>,
+        ),
         mut ev_chunk_will_update: EventWriter<ChunkWillUpdate<C>>,
         chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
         modified_voxels: ResMut<ModifiedVoxels<C, C::MaterialIndex>>,
+        mut voxel_edits: Query<&mut ChunkVoxelEdits>,
+        configuration: Res<C>,
     ) {
+        let (buffer, clear_buffer) = &mut buffers;
         let chunk_map_read_lock = chunk_map.get_read_lock();
         let mut modified_voxels = modified_voxels.write().unwrap();
+        let chunk_size = configuration.chunk_size();
+        let emit_chunk_events = configuration.emit_chunk_events();
+
+        let mut updated_chunks = HashMap::<(Entity, IVec3), Vec<IVec3>>::new();
+        let mut chunk_edits = HashMap::<Entity, Vec<IVec3>>::new();
+        let mut cleared_chunks = HashSet::<Entity>::new();
+
+        let writes = buffer
+            .iter()
+            .map(|(position, voxel)| (*position, Some(*voxel)));
+        let clears = clear_buffer.iter().map(|position| (*position, None));
+        for (position, voxel) in writes.chain(clears) {
+            let (chunk_pos, vox_pos) =
+                get_chunk_voxel_position_for_size(position, chunk_size);
+            match voxel {
+                Some(voxel) => {
+                    modified_voxels.insert(position, voxel);
+                }
+                None => {
+                    if modified_voxels.remove(&position).is_none() {
+                        continue;
+                    }
+                }
+            }
 
-        let mut updated_chunks = HashSet::<(Entity, IVec3)>::new();
-
-        for (position, voxel) in buffer.iter() {
-            let (chunk_pos, _vox_pos) = get_chunk_voxel_position(*position);
-            modified_voxels.insert(*position, *voxel);
+            // Mark the chunk as needing remeshing or spawn a new chunk if it doesn't exist.
+            // Voxels on the border of a chunk are also in the padding of the neighbouring
+            // chunks, which need remeshing too to keep face culling and AO consistent.
+            for sharing_chunk_pos in chunks_sharing_voxel(chunk_pos, vox_pos, chunk_size)
+            {
+                let Some(chunk_data) = ChunkMap::<C, C::MaterialIndex>::get(
+                    &sharing_chunk_pos,
+                    &chunk_map_read_lock,
+                ) else {
+                    continue;
+                };
+                let Some(mut ent) = commands.get_entity(chunk_data.entity) else {
+                    continue;
+                };
+                ent.try_insert(NeedsRemesh(InvalidationReason::Edited));
+                chunk_edits
+                    .entry(chunk_data.entity)
+                    .or_default()
+                    .push(position);
+                if voxel.is_none() {
+                    cleared_chunks.insert(chunk_data.entity);
+                }
+                if emit_chunk_events && sharing_chunk_pos == chunk_pos {
+                    updated_chunks
+                        .entry((chunk_data.entity, chunk_pos))
+                        .or_default()
+                        .push(position);
+                }
+            }
+        }
 
-            // Mark the chunk as needing remeshing or spawn a new chunk if it doesn't exist
-            if let Some(chunk_data) =
-                ChunkMap::<C, C::MaterialIndex>::get(&chunk_pos, &chunk_map_read_lock)
+        for (entity, changed) in chunk_edits {
+            // Edits are collected until the chunk is remeshed, which is usually in the next frame.
+            // Cleared voxels need the voxel lookup again, so those chunks are fully regenerated.
+            if cleared_chunks.contains(&entity) {
+                if let Some(mut ent) = commands.get_entity(entity) {
+                    ent.remove::<ChunkVoxelEdits>();
+                }
+            } else if configuration.chunk_regenerate_strategy()
+                == ChunkRegenerateStrategy::Reuse
             {
-                if let Some(mut ent) = commands.get_entity(chunk_data.entity) {
-                    ent.try_insert(NeedsRemesh);
-                    updated_chunks.insert((chunk_data.entity, chunk_pos));
+                if let Ok(mut voxel_edits) = voxel_edits.get_mut(entity) {
+                    voxel_edits.0.extend(changed);
+                } else if let Some(mut ent) = commands.get_entity(entity) {
+                    ent.try_insert(ChunkVoxelEdits(changed));
                 }
             }
         }
 
-        for (entity, chunk_pos) in updated_chunks {
-            ev_chunk_will_update.send(ChunkWillUpdate::<C>::new(chunk_pos, entity));
+        for ((entity, chunk_pos), changed) in updated_chunks {
+            ev_chunk_will_update
+                .send(ChunkWillUpdate::<C>::new(chunk_pos, entity, changed));
         }
 
         buffer.clear();
+        clear_buffer.clear();
+    }
+
+    /// Adds meshes queued with `VoxelWorld::preload_mesh` to the mesh cache
+    pub fn flush_mesh_preload_buffer(
+        mut preload_buffer: ResMut<MeshPreloadBuffer<C>>,
+        mut preloaded_meshes: ResMut<PreloadedMeshes<C>>,
+        mut mesh_cache_insert_buffer: ResMut<MeshCacheInsertBuffer<C>>,
+        mesh_assets: Option<ResMut<Assets<Mesh>>>,
+        mesh_cache: Res<MeshCache<C>>,
+    ) {
+        if preload_buffer.is_empty() {
+            return;
+        }
+
+        let Some(mut mesh_assets) = mesh_assets else {
+            return;
+        };
+
+        for (hash, mesh) in preload_buffer.drain(..) {
+            let mesh_ref = Arc::new(mesh_assets.add(mesh));
+            preloaded_meshes.push(mesh_ref.clone());
+            mesh_cache_insert_buffer.push((hash, mesh_ref, None, None));
+        }
+
+        // Apply right away, so that chunks remeshed this frame can use the preloaded meshes
+        mesh_cache.apply_buffers(&mut mesh_cache_insert_buffer);
     }
 
     pub fn flush_mesh_cache_buffers(
@@ -506,18 +1564,49 @@ where
     }
 
     pub fn flush_chunk_map_buffers(
+        mut commands: Commands,
         mut chunk_map_insert_buffer: ResMut<ChunkMapInsertBuffer<C, C::MaterialIndex>>,
         mut chunk_map_update_buffer: ResMut<ChunkMapUpdateBuffer<C, C::MaterialIndex>>,
         mut chunk_map_remove_buffer: ResMut<ChunkMapRemoveBuffer<C>>,
         mut ev_chunk_will_spawn: EventWriter<ChunkWillSpawn<C>>,
         chunk_map: Res<ChunkMap<C, C::MaterialIndex>>,
+        configuration: Res<C>,
     ) {
+        // Custom meshing delegates can get the voxels of the neighbours, which are only there
+        // once the neighbour has generated for the first time
+        let changed_chunks = if configuration.chunk_meshing_delegate().is_some()
+            && configuration.chunk_meshing_needs_neighbours()
+        {
+            let read_lock = chunk_map.get_read_lock();
+            chunk_map_update_buffer
+                .iter()
+                .map(|(position, ..)| *position)
+                .filter(|position| {
+                    !ChunkMap::<C, C::MaterialIndex>::get(position, &read_lock)
+                        .is_some_and(|chunk_data| chunk_data.has_generated())
+                })
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::new()
+        };
+
         chunk_map.apply_buffers(
             &mut chunk_map_insert_buffer,
             &mut chunk_map_update_buffer,
             &mut chunk_map_remove_buffer,
-            &mut ev_chunk_will_spawn,
+            configuration
+                .emit_chunk_events()
+                .then_some(&mut ev_chunk_will_spawn),
         );
+
+        // The buffers are kept for the next frame if the chunk map was locked
+        if !chunk_map_insert_buffer.is_empty()
+            || !chunk_map_update_buffer.is_empty()
+            || !chunk_map_remove_buffer.is_empty()
+        {
+            return;
+        }
+        remesh_neighbours_of(&mut commands, &chunk_map, &changed_chunks);
     }
 
     pub(crate) fn assign_material<M: Material>(
@@ -540,6 +1629,305 @@ where
     }
 }
 
+/// Spawns a chunk entity that will get generated and meshed, and queues its (empty) chunk data
+/// for insertion into the chunk map
+fn spawn_chunk<C: VoxelWorldConfig>(
+    commands: &mut Commands,
+    world_root: Entity,
+    chunk_position: IVec3,
+    configuration: &C,
+    chunk_map_insert_buffer: &mut ChunkMapInsertBuffer<C, C::MaterialIndex>,
+    chunk_entity_pool: &mut ChunkEntityPool<C>,
+) {
+    let chunk_size = configuration.chunk_size();
+    let chunk_entity = match chunk_entity_pool.take(commands) {
+        Some(chunk_entity) => {
+            commands
+                .entity(chunk_entity)
+                .try_insert(NeedsRemesh(InvalidationReason::Streamed));
+            chunk_entity
+        }
+        None => commands
+            .spawn(NeedsRemesh(InvalidationReason::Streamed))
+            .id(),
+    };
+    commands.entity(world_root).add_child(chunk_entity);
+    let chunk = Chunk::<C>::new(chunk_position, chunk_entity, chunk_size);
+
+    chunk_map_insert_buffer.push((
+        chunk_position,
+        ChunkData::with_entity(chunk.entity, chunk_size),
+    ));
+
+    commands
+        .entity(chunk.entity)
+        .try_insert((chunk, chunk_transform(chunk_position, configuration)));
+}
+
+/// Returns true if the chunk position is within `VoxelWorldConfig::world_chunk_bounds`, or if the
+/// world has no bounds
+pub(crate) fn is_within_world_bounds<C: VoxelWorldConfig>(
+    chunk_position: IVec3,
+    configuration: &C,
+) -> bool {
+    configuration.world_chunk_bounds().is_none_or(|(min, max)| {
+        chunk_position.cmpge(min).all() && chunk_position.cmple(max).all()
+    })
+}
+
+/// Returns true if chunks can be spawned at the chunk position, which has to be within the world
+/// bounds and `VoxelWorldConfig::spawn_y_range`
+pub(crate) fn is_within_spawn_bounds<C: VoxelWorldConfig>(
+    chunk_position: IVec3,
+    configuration: &C,
+) -> bool {
+    configuration.spawn_y_range().contains(&chunk_position.y)
+        && is_within_world_bounds(chunk_position, configuration)
+}
+
+/// Returns one random point in each cell of a grid covering the rectangle at `min` with the
+/// given `size`. The grid has about `count` cells, with rows and columns in proportion to the
+/// rectangle, so that the cells are roughly square.
+pub(crate) fn jittered_grid_points(
+    min: Vec2,
+    size: Vec2,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec2> {
+    if count == 0 || size.x <= 0.0 || size.y <= 0.0 {
+        return Vec::new();
+    }
+
+    let columns =
+        ((count as f32 * size.x / size.y).sqrt().round() as usize).clamp(1, count);
+    let rows = (count / columns).max(1);
+    let cell_size = size / Vec2::new(columns as f32, rows as f32);
+
+    let mut points = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let jitter = Vec2::new(rng.gen::<f32>(), rng.gen::<f32>());
+            points
+                .push(min + (Vec2::new(column as f32, row as f32) + jitter) * cell_size);
+        }
+    }
+    points
+}
+
+/// Returns the transform of the chunk entity at the given chunk position
+pub(crate) fn chunk_transform<C: VoxelWorldConfig>(
+    chunk_position: IVec3,
+    configuration: &C,
+) -> Transform {
+    let voxel_size = configuration.voxel_size();
+    Transform::from_translation(
+        configuration
+            .chunk_origin_convention()
+            .chunk_translation(chunk_position, configuration.chunk_size())
+            * voxel_size,
+    )
+    .with_scale(Vec3::splat(voxel_size))
+}
+
+/// Returns true if the chunk at the given position has generated and is full
+fn is_full_chunk<C: VoxelWorldConfig>(
+    chunk_position: IVec3,
+    chunk_map: &ChunkMap<C, C::MaterialIndex>,
+) -> bool {
+    chunk_map
+        .get_read_lock()
+        .get(&chunk_position)
+        .is_some_and(|chunk_data| chunk_data.is_full)
+}
+
+/// Returns a description of each setting in the configuration that chunks can't be generated or
+/// meshed with. These are logged as warnings in debug builds.
+pub(crate) fn configuration_problems<C: VoxelWorldConfig>(
+    configuration: &C,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // The voxel arrays are padded with one voxel on each side, so the padded shape has at least
+    // 3 voxels per axis as long as the chunk holds any voxels
+    if configuration.chunk_size() == 0 {
+        problems.push(
+            "VoxelWorldConfig::chunk_size is 0, which gives a padded chunk shape of 2 voxels per \
+             axis. The padded shape needs at least 3 voxels per axis, one for the chunk and one \
+             on each side for the neighbouring voxels"
+                .to_string(),
+        );
+    }
+
+    let voxel_size = configuration.voxel_size();
+    if !(voxel_size.is_finite() && voxel_size > 0.0) {
+        problems.push(format!(
+            "VoxelWorldConfig::voxel_size is {voxel_size}, but needs to be a positive number"
+        ));
+    }
+
+    let spawning_distance = configuration.spawning_distance();
+    if spawning_distance > MAX_SPAWNING_DISTANCE {
+        problems.push(format!(
+            "VoxelWorldConfig::spawning_distance is {spawning_distance}, but can be at most \
+             {MAX_SPAWNING_DISTANCE}, since squared chunk distances have to fit in an i32"
+        ));
+    }
+
+    for (setting, limit) in [
+        ("max_spawn_per_frame", configuration.max_spawn_per_frame()),
+        ("max_meshes_per_frame", configuration.max_meshes_per_frame()),
+        (
+            "max_remesh_tasks_per_frame",
+            configuration.max_remesh_tasks_per_frame(),
+        ),
+    ] {
+        if limit == 0 {
+            problems.push(format!(
+                "VoxelWorldConfig::{setting} is 0, so no chunks will ever get past that step"
+            ));
+        }
+    }
+
+    if let Some((min, max)) = configuration.world_chunk_bounds() {
+        if min.cmpgt(max).any() {
+            problems.push(format!(
+                "VoxelWorldConfig::world_chunk_bounds goes from {min} to {max}, which holds no \
+                 chunks. The first position needs to be the minimum corner"
+            ));
+        }
+    }
+
+    let spawn_y_range = configuration.spawn_y_range();
+    if spawn_y_range.is_empty() {
+        problems.push(format!(
+            "VoxelWorldConfig::spawn_y_range is {spawn_y_range:?}, which holds no chunks"
+        ));
+    }
+
+    problems
+}
+
+/// The largest `spawning_distance` for which the squared distance between two chunks within
+/// range, over all three axes, fits in an i32
+const MAX_SPAWNING_DISTANCE: u32 = 26_754;
+
+/// Returns the position of the chunk containing the given world position
+fn chunk_at_world_position<C: VoxelWorldConfig>(
+    position: Vec3,
+    configuration: &C,
+) -> IVec3 {
+    (position / configuration.voxel_size()).as_ivec3() / configuration.chunk_size() as i32
+}
+
+fn insert_collision_data(
+    commands: &mut Commands,
+    entity: Entity,
+    collision_data: ChunkCollisionData,
+) {
+    if collision_data.is_empty() {
+        commands.entity(entity).remove::<ChunkCollisionData>();
+    } else {
+        commands.entity(entity).try_insert(collision_data);
+    }
+}
+
+/// Returns the position of the chunk holding the voxel at `vox_pos`, followed by the chunks that
+/// have the voxel in their padding. Those are the face, edge and corner neighbours on the sides
+/// where the voxel lies on the chunk border.
+pub(crate) fn chunks_sharing_voxel(
+    chunk_pos: IVec3,
+    vox_pos: UVec3,
+    chunk_size: u32,
+) -> Vec<IVec3> {
+    let border_side = |v: u32| {
+        if v == 0 {
+            -1
+        } else if v == chunk_size - 1 {
+            1
+        } else {
+            0
+        }
+    };
+    let sides = IVec3::from(vox_pos.to_array().map(border_side));
+
+    let mut chunks = Vec::with_capacity(8);
+    for i in 0..8 {
+        let offset = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1) * sides;
+        if !chunks.contains(&(chunk_pos + offset)) {
+            chunks.push(chunk_pos + offset);
+        }
+    }
+    chunks
+}
+
+/// Marks the generated chunks next to any of the given chunk positions for remeshing
+fn remesh_neighbours_of<C: VoxelWorldConfig>(
+    commands: &mut Commands,
+    chunk_map: &ChunkMap<C, C::MaterialIndex>,
+    chunk_positions: &HashSet<IVec3>,
+) {
+    if chunk_positions.is_empty() {
+        return;
+    }
+
+    let read_lock = chunk_map.get_read_lock();
+    let neighbour_positions = chunk_positions
+        .iter()
+        .flat_map(|chunk_position| {
+            ChunkNeighbours::<C::MaterialIndex>::DIRECTIONS
+                .map(|direction| *chunk_position + direction)
+        })
+        .collect::<HashSet<_>>();
+    for neighbour_position in neighbour_positions {
+        let Some(neighbour) =
+            ChunkMap::<C, C::MaterialIndex>::get(&neighbour_position, &read_lock)
+        else {
+            continue;
+        };
+        if !neighbour.has_generated() {
+            continue;
+        }
+        if let Some(mut ent) = commands.get_entity(neighbour.entity) {
+            ent.try_insert(NeedsRemesh(InvalidationReason::NeighbourChanged));
+        }
+    }
+}
+
+/// Schedules another generation attempt for a chunk whose generation failed, or gives up and
+/// sends `ChunkGenerationFailed` if the retries have been used up.
+fn retry_or_fail_chunk<C: VoxelWorldConfig>(
+    commands: &mut Commands,
+    chunk_task: &ChunkTask<C, C::MaterialIndex>,
+    retry: Option<&ChunkGenerationRetry>,
+    configuration: &C,
+    now: std::time::Duration,
+    ev_chunk_generation_failed: &mut EventWriter<ChunkGenerationFailed<C>>,
+) {
+    let entity = chunk_task.chunk_data.entity;
+    let attempts = retry.map_or(0, |retry| retry.attempts) + 1;
+
+    // The chunk can be despawned while its task is running, and then there is nothing to retry
+    let Some(mut entity_commands) = commands.get_entity(entity) else {
+        return;
+    };
+    entity_commands.remove::<ChunkThread<C, C::MaterialIndex>>();
+
+    if attempts > configuration.max_generation_retries() {
+        entity_commands.remove::<ChunkGenerationRetry>();
+        ev_chunk_generation_failed
+            .send(ChunkGenerationFailed::<C>::new(chunk_task.position, entity));
+        return;
+    }
+
+    let backoff = configuration
+        .generation_retry_backoff()
+        .saturating_mul(2u32.saturating_pow(attempts - 1));
+    entity_commands.try_insert(ChunkGenerationRetry {
+        attempts,
+        retry_at: now + backoff,
+    });
+}
+
 /// Check if the given world point is within the camera's view
 #[inline]
 #[allow(dead_code)]